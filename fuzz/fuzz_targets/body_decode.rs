@@ -0,0 +1,47 @@
+#![no_main]
+use std::io::{Cursor, Read};
+use libfuzzer_sys::fuzz_target;
+use local_lfs::http::{BodyDecoder, BodyReader, Decoded, Framing};
+
+// The first byte picks the framing and the second how the input is split up
+// for the push decoder, which must agree with reading the body whole.
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 {
+        return;
+    }
+    let framing = match data[0] {
+        0 => Framing::Chunked,
+        length => Framing::Length(length as usize - 1),
+    };
+    let step = data[1] as usize + 1;
+    let body = &data[2..];
+
+    let mut decoder = BodyDecoder::new(&framing);
+    let mut pushed = Vec::new();
+    let mut input = body;
+    let pushed_ok = loop {
+        let end = std::cmp::min(step, input.len());
+        match decoder.push(&input[..end]) {
+            Ok((Decoded::Data(data), taken)) => {
+                pushed.extend_from_slice(data);
+                input = &input[taken..];
+            },
+            Ok((Decoded::NeedMore, taken)) => {
+                if end == 0 {
+                    break false;
+                }
+                input = &input[taken..];
+            },
+            Ok((Decoded::Complete, _)) => break true,
+            Err(_) => break false,
+        }
+    };
+
+    let mut read = Vec::new();
+    let reader = BodyReader::new(Cursor::new(body), &framing);
+    let read_ok = reader.take(1 << 20).read_to_end(&mut read).is_ok();
+    assert_eq!(pushed_ok, read_ok);
+    if pushed_ok {
+        assert_eq!(pushed, read);
+    }
+});
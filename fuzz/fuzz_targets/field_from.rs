@@ -0,0 +1,15 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use local_lfs::http::Field;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(line) = std::str::from_utf8(data) {
+        let _ = Field::from(String::from(line));
+    }
+    if let Ok(field) = Field::parse(data) {
+        // A parsed field must come back unchanged from its serialized form.
+        let mut line = format!("{}: ", field.name).into_bytes();
+        line.extend_from_slice(&field.value);
+        assert_eq!(field, Field::parse(&line).unwrap());
+    }
+});
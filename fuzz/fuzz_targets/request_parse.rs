@@ -0,0 +1,21 @@
+#![no_main]
+use std::io::Cursor;
+use libfuzzer_sys::fuzz_target;
+use local_lfs::http::{Request, Version};
+
+fuzz_target!(|data: &[u8]| {
+    let mut reader = Cursor::new(data);
+    if let Ok(request) = Request::parse(&mut reader) {
+        // Whatever parses must serialize to something which parses the same.
+        let mut encoded = Vec::new();
+        request.write_to(&mut encoded).unwrap();
+        let reparsed = Request::parse(&mut Cursor::new(encoded)).unwrap();
+        assert_eq!(request.method(), reparsed.method());
+        assert_eq!(request.target(), reparsed.target());
+        assert_eq!(request.version(), reparsed.version());
+        let _ = request.request_target();
+        let _ = request.framing();
+        let _ = request.keep_alive();
+        let _ = Version::new(request.version().major(), request.version().minor());
+    }
+});
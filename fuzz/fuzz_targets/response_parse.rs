@@ -0,0 +1,17 @@
+#![no_main]
+use std::io::Cursor;
+use libfuzzer_sys::fuzz_target;
+use local_lfs::http::Response;
+
+fuzz_target!(|data: &[u8]| {
+    let mut reader = Cursor::new(data);
+    if let Ok(response) = Response::parse(&mut reader) {
+        // Whatever parses must serialize to something which parses the same.
+        let mut encoded = Vec::new();
+        response.write_to(&mut encoded).unwrap();
+        let reparsed = Response::parse(&mut Cursor::new(encoded)).unwrap();
+        assert_eq!(response.status(), reparsed.status());
+        assert_eq!(response.version(), reparsed.version());
+        let _ = response.framing();
+    }
+});
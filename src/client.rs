@@ -0,0 +1,609 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use http::{Body, BodyReader, Error, ErrorKind, Field, Headers, Limits, MessageBuilder, Method,
+           Response, StatusCode, Target, TargetForm};
+
+// How long connecting, or any single read or write, may take.
+const TIMEOUT: Duration = Duration::from_secs(30);
+// Past this many redirects the server is likely redirecting in a loop.
+const MAX_REDIRECTS: usize = 10;
+
+// A request to send with a Client. Its body is held in memory so that it can
+// be sent again if the request is redirected.
+#[derive(Debug)]
+pub struct ClientRequest {
+    method: Method,
+    url: String,
+    fields: Vec<Field>,
+    body: Vec<u8>,
+}
+
+impl ClientRequest {
+    pub fn new(method: Method, url: &str) -> ClientRequest {
+        ClientRequest{ method, url: String::from(url), fields: Vec::new(), body: Vec::new() }
+    }
+
+    pub fn add_field(&mut self, field: Field) -> &mut Self {
+        self.fields.push(field);
+        self
+    }
+
+    pub fn add_field2(&mut self, name: &str, value: &str) -> &mut Self {
+        self.fields.push(Field::new(String::from(name), String::from(value)));
+        self
+    }
+
+    pub fn add_body<B: Into<Vec<u8>>>(&mut self, body: B) -> &mut Self {
+        self.body = body.into();
+        self
+    }
+}
+
+// The final response to a request, once any redirects have been followed,
+// with its body read into memory.
+#[derive(Debug)]
+pub struct ClientResponse {
+    url: String,
+    response: Response,
+    body: Body,
+}
+
+impl ClientResponse {
+    // Where the response came from, which differs from the URL requested if
+    // it was redirected.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn response(&self) -> &Response {
+        &self.response
+    }
+
+    pub fn status(&self) -> &StatusCode {
+        self.response.status()
+    }
+
+    pub fn body(&self) -> &[u8] {
+        self.body.content()
+    }
+}
+
+impl Headers for ClientResponse {
+    fn fields(&self) -> &[Field] {
+        self.response.fields()
+    }
+}
+
+// A blocking HTTP/1.1 client. Connections are kept open between requests to
+// the same server, and redirects are followed. Only http URLs are supported.
+pub struct Client {
+    // Connections waiting for their next request, by the authority they were
+    // made to.
+    idle: HashMap<String, Connection>,
+    timeout: Duration,
+    max_redirects: usize,
+    limits: Limits,
+}
+
+impl Default for Client {
+    fn default() -> Client {
+        Client::new()
+    }
+}
+
+impl Client {
+    pub fn new() -> Client {
+        Client{ idle: HashMap::new(), timeout: TIMEOUT, max_redirects: MAX_REDIRECTS,
+                limits: Limits::default() }
+    }
+
+    // Applies to connections opened from then on.
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    // With none allowed, redirects are returned rather than followed.
+    pub fn set_max_redirects(&mut self, max_redirects: usize) -> &mut Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    // Limits the responses which will be read, including their bodies.
+    pub fn set_limits(&mut self, limits: Limits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn get(&mut self, url: &str) -> io::Result<ClientResponse> {
+        self.send(&ClientRequest::new(Method::GET, url))
+    }
+
+    pub fn send(&mut self, request: &ClientRequest) -> io::Result<ClientResponse> {
+        let (mut authority, mut target) = split_url(&request.url)?;
+        let mut method = request.method.clone();
+        let mut fields = request.fields.clone();
+        let mut body = request.body.as_slice();
+        let mut redirects = 0;
+        loop {
+            let (response, response_body) = self.exchange(&authority, &method, &target,
+                                                          &fields, body)?;
+            let location = match response.status().code() {
+                301 | 302 | 303 | 307 | 308 if self.max_redirects > 0 => {
+                    response.header("Location")
+                },
+                _ => None,
+            };
+            let location = match location {
+                Some(location) => resolve_location(&authority, &target, location),
+                None => {
+                    let url = format!("http://{}{}", authority, target);
+                    return Ok(ClientResponse{ url, response, body: response_body });
+                },
+            };
+            redirects += 1;
+            if redirects > self.max_redirects {
+                return Err(io::Error::other("Too many redirects"));
+            }
+
+            // 303 (See Other) always asks for the result to be fetched, and
+            // 301 and 302 are treated the same way for POST, as browsers do.
+            let code = response.status().code();
+            if (code == 303 && method != Method::HEAD) ||
+                    ((code == 301 || code == 302) && method == Method::POST) {
+                method = Method::GET;
+                body = &[];
+                fields.retain(|field| !field.name.to_ascii_lowercase().starts_with("content-"));
+            }
+            let (next_authority, next_target) = split_url(&location)?;
+            // Credentials are only for the server they were given to.
+            if next_authority != authority {
+                fields.retain(|field| !field.name.eq_ignore_ascii_case("Authorization") &&
+                                      !field.name.eq_ignore_ascii_case("Cookie"));
+            }
+            authority = next_authority;
+            target = next_target;
+        }
+    }
+
+    // Sends a single request, reusing an idle connection to the server if
+    // there is one.
+    fn exchange(&mut self, authority: &str, method: &Method, target: &str,
+            fields: &[Field], body: &[u8]) -> io::Result<(Response, Body)> {
+        let idle = self.idle.remove(authority).filter(|connection| !connection.closed());
+        let (mut connection, reused) = match idle {
+            Some(connection) => (connection, true),
+            None => (Connection::open(authority, self.timeout)?, false),
+        };
+        match connection.send(authority, method, target, fields, body) {
+            Ok(()) => {},
+            // The server may still close an idle connection just as it is
+            // reused. The request can only be sent again if writing it failed
+            // that way, and even then part of it may have arrived, so only if
+            // repeating it can't do any harm.
+            Err(ref error) if reused && method.is_idempotent() && closed_by_peer(error) => {
+                connection = Connection::open(authority, self.timeout)?;
+                connection.send(authority, method, target, fields, body)?;
+            },
+            Err(error) => return Err(error),
+        }
+        let (response, body, keep_alive) = connection.receive(method, &self.limits)?;
+        if keep_alive {
+            self.idle.insert(String::from(authority), connection);
+        }
+        Ok((response, body))
+    }
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+}
+
+impl Connection {
+    fn open(authority: &str, timeout: Duration) -> io::Result<Connection> {
+        let mut last_error = None;
+        for addr in socket_addrs(authority)? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(timeout))?;
+                    stream.set_write_timeout(Some(timeout))?;
+                    let writer = BufWriter::new(stream.try_clone()?);
+                    return Ok(Connection{ reader: BufReader::new(stream), writer });
+                },
+                Err(error) => last_error = Some(error),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "No addresses found for host")
+        }))
+    }
+
+    // Whether the server has closed the connection while it was idle, as
+    // far as can be told without waiting. Anything else sent meanwhile can't
+    // be a response to a request, so also leaves it unusable.
+    fn closed(&self) -> bool {
+        if !self.reader.buffer().is_empty() {
+            return true;
+        }
+        let stream = self.reader.get_ref();
+        if stream.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let readable = !matches!(stream.peek(&mut [0]),
+                                 Err(ref error) if error.kind() == io::ErrorKind::WouldBlock);
+        stream.set_nonblocking(false).is_err() || readable
+    }
+
+    fn send(&mut self, authority: &str, method: &Method, target: &str,
+            fields: &[Field], body: &[u8]) -> io::Result<()> {
+        let mut message = MessageBuilder::request(method.clone(), String::from(target));
+        if fields.host().ok().flatten().is_none() {
+            message.add_field2("Host", authority);
+        }
+        for field in fields {
+            message.add_field(field.clone());
+        }
+        message.add_body(body);
+        message.write_to(&mut self.writer)?;
+        self.writer.flush()
+    }
+
+    // Reads the final response to a request, skipping any interim ones.
+    // Returns the response along with whether the connection can be reused.
+    fn receive(&mut self, method: &Method, limits: &Limits)
+            -> io::Result<(Response, Body, bool)> {
+        if self.reader.fill_buf()?.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                    "Connection closed before responding"));
+        }
+        let response = loop {
+            let response = Response::parse_with_limits(&mut self.reader, limits)
+                .map_err(parse_error)?;
+            if !response.status().is_informational() ||
+                    *response.status() == StatusCode::SwitchingProtocols {
+                break response;
+            }
+        };
+        let code = response.status().code();
+        // After 101 (Switching Protocols) the connection no longer speaks HTTP.
+        let keep_alive = response.keep_alive() && code != 101;
+        let framed = !response.headers("Content-Length").is_empty() ||
+            !response.headers("Transfer-Encoding").is_empty();
+        let body = if *method == Method::HEAD || code < 200 || code == 204 || code == 304 {
+            Body::from(String::new())
+        } else if !framed {
+            // Without framing the body runs until the connection is closed.
+            let body = Body::read_limited(&mut self.reader, limits.body).map_err(parse_error)?;
+            return Ok((response, body, false));
+        } else {
+            let framing = response.framing().map_err(parse_error)?;
+            let mut reader = BodyReader::with_limits(&mut self.reader, &framing, limits);
+            Body::read_limited(&mut reader, limits.body).map_err(parse_error)?
+        };
+        Ok((response, body, keep_alive))
+    }
+}
+
+// Whether an error writing a request shows the server had closed the
+// connection.
+fn closed_by_peer(error: &io::Error) -> bool {
+    matches!(error.kind(), io::ErrorKind::BrokenPipe | io::ErrorKind::ConnectionReset |
+                           io::ErrorKind::ConnectionAborted)
+}
+
+fn parse_error(error: Error) -> io::Error {
+    let kind = match error.kind() {
+        ErrorKind::Timeout => io::ErrorKind::TimedOut,
+        _ => io::ErrorKind::InvalidData,
+    };
+    io::Error::new(kind, error)
+}
+
+fn invalid_url() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "Invalid URL")
+}
+
+// Splits an http URL into the authority to connect to and the target to
+// request from it.
+fn split_url(url: &str) -> io::Result<(String, String)> {
+    let target = Target::parse(url).map_err(|_| invalid_url())?;
+    if *target.form() != TargetForm::Absolute {
+        return Err(invalid_url());
+    }
+    if target.scheme() != Some("http") {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Unsupported URL scheme"));
+    }
+    let authority = target.authority().unwrap_or("");
+    // Credentials in URLs aren't supported, they're given with fields.
+    if authority.contains('@') {
+        return Err(invalid_url());
+    }
+    let mut path = String::from(target.path());
+    if let Some(query) = target.query() {
+        path.push('?');
+        path.push_str(query);
+    }
+    Ok((authority.to_ascii_lowercase(), path))
+}
+
+// The addresses to try for an authority: host [ ":" port ], where IPv6
+// addresses are bracketed.
+fn socket_addrs(authority: &str) -> io::Result<Vec<SocketAddr>> {
+    let (host, port) = match authority.rfind(':') {
+        Some(sep) if !authority[sep..].contains(']') => {
+            let port = authority[sep+1..].parse().map_err(|_| invalid_url())?;
+            (&authority[..sep], port)
+        },
+        _ => (authority, 80),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    Ok((host, port).to_socket_addrs()?.collect())
+}
+
+// The URL a Location field refers to, relative to the target redirected
+// from.
+fn resolve_location(authority: &str, target: &str, location: &str) -> String {
+    let location = location.split('#').next().unwrap_or("");
+    if location.contains("://") {
+        String::from(location)
+    } else if location.starts_with("//") {
+        format!("http:{}", location)
+    } else if location.starts_with('/') {
+        format!("http://{}{}", authority, location)
+    } else {
+        let path = target.split('?').next().unwrap_or("");
+        let directory = &path[..path.rfind('/').map_or(0, |sep| sep + 1)];
+        format!("http://{}{}{}", authority, directory, location)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+    use http::Request;
+    use super::*;
+
+    // A request as the server received it, with its body.
+    type Received = (Request, Vec<u8>);
+
+    // Serves a connection for each list of responses, answering each request
+    // on it with the next response and then closing it. Returns the URL to
+    // request along with the requests received.
+    fn serve(connections: Vec<Vec<&'static str>>)
+            -> (String, thread::JoinHandle<Vec<Received>>) {
+        let (url, handle, _) = serve_closing(connections);
+        (url, handle)
+    }
+
+    // As serve(), also signalling each time a connection has been closed.
+    fn serve_closing(connections: Vec<Vec<&'static str>>)
+            -> (String, thread::JoinHandle<Vec<Received>>, mpsc::Receiver<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (closed, closes) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let mut received = Vec::new();
+            for responses in connections {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut writer = &stream;
+                for response in responses {
+                    let request = Request::parse(&mut reader).unwrap();
+                    let body = Body::parse_framed(&mut reader, &request.framing().unwrap())
+                        .unwrap();
+                    received.push((request, body.content().to_vec()));
+                    writer.write_all(response.as_bytes()).unwrap();
+                }
+                drop(reader);
+                drop(stream);
+                let _ = closed.send(());
+            }
+            received
+        });
+        (url, handle, closes)
+    }
+
+    #[test]
+    fn framed_bodies() {
+        let (url, handle) = serve(vec![vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello",
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+             3\r\nwor\r\n2\r\nld\r\n0\r\nExpires: never\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n",
+            "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 204 No Content\r\n\r\n",
+        ]]);
+        let mut client = Client::new();
+        let response = client.get(&format!("{}/a?b=c", url)).unwrap();
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(b"hello", response.body());
+        assert_eq!(format!("{}/a?b=c", url), response.url());
+        let response = client.get(&url).unwrap();
+        assert_eq!(b"world", response.body());
+        let response = client.send(&ClientRequest::new(Method::HEAD, &url)).unwrap();
+        assert_eq!(Some(5), response.content_length().unwrap());
+        assert!(response.body().is_empty());
+        let mut request = ClientRequest::new(Method::PUT, &format!("{}/c", url));
+        request.add_field2("Content-Type", "text/plain")
+               .add_body("upload");
+        let response = client.send(&request).unwrap();
+        assert_eq!(&StatusCode::NoContent, response.status());
+
+        // All over the one connection.
+        let received = handle.join().unwrap();
+        assert_eq!(4, received.len());
+        assert_eq!("/a?b=c", received[0].0.target());
+        assert_eq!(Some(&url["http://".len()..]), received[0].0.host().unwrap());
+        assert_eq!(None, received[0].0.content_length().unwrap());
+        assert_eq!("/", received[1].0.target());
+        assert_eq!(&Method::PUT, received[3].0.method());
+        assert_eq!(b"upload".to_vec(), received[3].1);
+    }
+
+    #[test]
+    fn body_until_close() {
+        let (url, handle) = serve(vec![
+            vec!["HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nhello"],
+            vec!["HTTP/1.0 200 OK\r\nContent-Length: 2\r\n\r\nhi"],
+            vec!["HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"],
+        ]);
+        let mut client = Client::new();
+        assert_eq!(b"hello", client.get(&url).unwrap().body());
+        // HTTP/1.0 servers close the connection unless asked not to.
+        assert_eq!(b"hi", client.get(&url).unwrap().body());
+        assert!(client.get(&url).unwrap().body().is_empty());
+        assert_eq!(3, handle.join().unwrap().len());
+    }
+
+    #[test]
+    fn closed_connection_not_reused() {
+        // The server closes the connection without saying it would.
+        let (url, handle, closes) = serve_closing(vec![
+            vec!["HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\na"],
+            vec!["HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\nb"],
+            vec!["HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\nc"],
+        ]);
+        let mut client = Client::new();
+        assert_eq!(b"a", client.get(&url).unwrap().body());
+        closes.recv().unwrap();
+        assert_eq!(b"b", client.get(&url).unwrap().body());
+        closes.recv().unwrap();
+        let mut request = ClientRequest::new(Method::POST, &url);
+        request.add_body("c");
+        assert_eq!(b"c", client.send(&request).unwrap().body());
+        let received = handle.join().unwrap();
+        assert_eq!(3, received.len());
+        assert_eq!(b"c".to_vec(), received[2].1);
+    }
+
+    #[test]
+    fn request_not_repeated() {
+        // The server reads the second request, then closes without answering.
+        let (url, handle) = serve(vec![vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\na",
+            "",
+        ]]);
+        let mut client = Client::new();
+        assert_eq!(b"a", client.get(&url).unwrap().body());
+        let mut request = ClientRequest::new(Method::POST, &url);
+        request.add_body("b");
+        assert_eq!(io::ErrorKind::UnexpectedEof, client.send(&request).unwrap_err().kind());
+        assert_eq!(2, handle.join().unwrap().len());
+    }
+
+    #[test]
+    fn redirects() {
+        let (url, handle) = serve(vec![vec![
+            "HTTP/1.1 302 Found\r\nLocation: b?c=d\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok",
+            "HTTP/1.1 303 See Other\r\nLocation: /result\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 307 Temporary Redirect\r\nLocation: /other\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n",
+        ]]);
+        let mut client = Client::new();
+        let response = client.get(&format!("{}/a/index", url)).unwrap();
+        assert_eq!(b"ok", response.body());
+        assert_eq!(format!("{}/a/b?c=d", url), response.url());
+
+        // The result of a POST is fetched with GET.
+        let mut request = ClientRequest::new(Method::POST, &format!("{}/form", url));
+        request.add_field2("Content-Type", "text/plain")
+               .add_field2("Authorization", "Basic YTpi")
+               .add_body("data");
+        assert_eq!(&StatusCode::Ok, client.send(&request).unwrap().status());
+
+        // Others are sent again as they were.
+        let mut request = ClientRequest::new(Method::PUT, &format!("{}/upload", url));
+        request.add_body("data");
+        assert_eq!(&StatusCode::Created, client.send(&request).unwrap().status());
+
+        let received = handle.join().unwrap();
+        assert_eq!("/a/b?c=d", received[1].0.target());
+        assert_eq!(&Method::GET, received[3].0.method());
+        assert_eq!("/result", received[3].0.target());
+        assert!(received[3].1.is_empty());
+        assert_eq!(None, received[3].0.header("Content-Type"));
+        // Still the same server.
+        assert_eq!(Some("Basic YTpi"), received[3].0.header("Authorization"));
+        assert_eq!(&Method::PUT, received[5].0.method());
+        assert_eq!(b"data".to_vec(), received[5].1);
+    }
+
+    #[test]
+    fn too_many_redirects() {
+        const REDIRECT: &str = "HTTP/1.1 301 Moved Permanently\r\n\
+                                Location: /\r\n\
+                                Content-Length: 0\r\n\
+                                \r\n";
+        let (url, handle) = serve(vec![vec![REDIRECT, REDIRECT, REDIRECT, REDIRECT]]);
+        let mut client = Client::new();
+        client.set_max_redirects(2);
+        let error = client.get(&url).unwrap_err();
+        assert_eq!("Too many redirects", format!("{}", error));
+        // Unless they aren't followed at all.
+        client.set_max_redirects(0);
+        let response = client.get(&url).unwrap();
+        assert_eq!(&StatusCode::MovedPermanently, response.status());
+        assert_eq!(Some("/"), response.header("Location"));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let mut client = Client::new();
+        client.set_timeout(Duration::from_millis(100));
+        let error = client.get(&url).unwrap_err();
+        assert!(error.kind() == io::ErrorKind::TimedOut ||
+                error.kind() == io::ErrorKind::WouldBlock);
+    }
+
+    #[test]
+    fn body_too_large() {
+        let (url, handle) = serve(vec![vec!["HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello"]]);
+        let mut client = Client::new();
+        client.set_limits(Limits{ body: 4, ..Limits::default() });
+        let error = client.get(&url).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn invalid_urls() {
+        let mut client = Client::new();
+        for url in &["/relative", "example.com:80", "http://user@example.com/", "http://"] {
+            assert_eq!(io::ErrorKind::InvalidInput, client.get(url).unwrap_err().kind());
+        }
+        let error = client.get("https://example.com/").unwrap_err();
+        assert_eq!("Unsupported URL scheme", format!("{}", error));
+    }
+
+    #[test]
+    fn split_urls() {
+        assert_eq!(("example.com".to_string(), "/".to_string()),
+                   split_url("http://Example.com").unwrap());
+        assert_eq!(("[::1]:8080".to_string(), "/a/b?c".to_string()),
+                   split_url("http://[::1]:8080/a/b?c").unwrap());
+        let addrs = socket_addrs("[::1]:8080").unwrap();
+        assert_eq!(vec![SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], 8080))], addrs);
+        let addrs = socket_addrs("127.0.0.1").unwrap();
+        assert_eq!(vec![SocketAddr::from(([127, 0, 0, 1], 80))], addrs);
+    }
+
+    #[test]
+    fn resolve_locations() {
+        let resolve = |location| resolve_location("a.com", "/b/c?d", location);
+        assert_eq!("http://x.org/y", resolve("http://x.org/y"));
+        assert_eq!("http://x.org/y", resolve("//x.org/y"));
+        assert_eq!("http://a.com/y", resolve("/y#z"));
+        assert_eq!("http://a.com/b/y?q", resolve("y?q"));
+    }
+}
@@ -0,0 +1,468 @@
+use std::cmp;
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Read};
+use std::str;
+use super::ChunkedDecoder;
+use super::ChunkedReader;
+use super::{Error, ErrorKind};
+use super::Field;
+use super::Headers;
+use super::Limits;
+
+// How the end of a message body is found.
+#[derive(Debug, PartialEq)]
+pub enum Framing {
+    Length(usize),
+    Chunked,
+}
+
+impl Framing {
+    // Transfer-Encoding = 1#transfer-coding
+    // The only transfer coding supported is chunked, which must come last.
+    // A message with both Transfer-Encoding and Content-Length is rejected:
+    // intermediaries may disagree about which one applies, which can be used
+    // to smuggle requests past them.
+    pub fn from_fields(fields: &[Field]) -> Result<Framing, Error> {
+        let length = fields.content_length()?;
+        if fields.headers("Transfer-Encoding").is_empty() {
+            return Ok(Framing::Length(length.unwrap_or(0)));
+        }
+        if length.is_some() {
+            return Error::err(ErrorKind::Header, "Both Transfer-Encoding and Content-Length given");
+        }
+        match fields.transfer_encoding().as_slice() {
+            [coding] if coding == "chunked" => Ok(Framing::Chunked),
+            _ => Error::err(ErrorKind::Header, "Unsupported transfer coding"),
+        }
+    }
+}
+
+// What a BodyDecoder made of its input.
+#[derive(Debug, PartialEq)]
+pub enum Decoded<'a> {
+    // Some of the body's content.
+    Data(&'a [u8]),
+    // All of the input was taken, and more is needed.
+    NeedMore,
+    // The body has ended.
+    Complete,
+}
+
+// Decodes the body of a message, however it is framed, from input as it
+// arrives. Whatever follows the body is left untaken.
+pub enum BodyDecoder {
+    Length(u64),
+    Chunked(ChunkedDecoder),
+}
+
+impl BodyDecoder {
+    pub fn new(framing: &Framing) -> BodyDecoder {
+        BodyDecoder::with_limits(framing, &Limits::default())
+    }
+
+    // The limits apply to a chunked body's trailer fields.
+    pub fn with_limits(framing: &Framing, limits: &Limits) -> BodyDecoder {
+        match *framing {
+            Framing::Length(length) => BodyDecoder::Length(length as u64),
+            Framing::Chunked => BodyDecoder::Chunked(ChunkedDecoder::with_limits(limits)),
+        }
+    }
+
+    // Decodes what it can of the input, returning it along with how much of
+    // the input was taken.
+    pub fn push<'a>(&mut self, input: &'a [u8]) -> Result<(Decoded<'a>, usize), Error> {
+        match *self {
+            BodyDecoder::Length(0) => Ok((Decoded::Complete, 0)),
+            BodyDecoder::Length(_) if input.is_empty() => Ok((Decoded::NeedMore, 0)),
+            BodyDecoder::Length(ref mut remaining) => {
+                let len = cmp::min(input.len() as u64, *remaining) as usize;
+                *remaining -= len as u64;
+                Ok((Decoded::Data(&input[..len]), len))
+            },
+            BodyDecoder::Chunked(ref mut decoder) => decoder.push(input),
+        }
+    }
+
+    // The trailer fields of a chunked body, available once it has ended.
+    pub fn trailers(&self) -> &[Field] {
+        match *self {
+            BodyDecoder::Length(_) => &[],
+            BodyDecoder::Chunked(ref decoder) => decoder.trailers(),
+        }
+    }
+}
+
+// Reads exactly the body of a message, however it is framed. Ending before
+// the whole body has been read is an error.
+pub enum BodyReader<B> {
+    Length(B, u64),
+    Chunked(ChunkedReader<B>),
+}
+
+impl<B: BufRead> BodyReader<B> {
+    pub fn new(reader: B, framing: &Framing) -> BodyReader<B> {
+        BodyReader::with_limits(reader, framing, &Limits::default())
+    }
+
+    // The limits apply to a chunked body's trailer fields.
+    pub fn with_limits(reader: B, framing: &Framing, limits: &Limits) -> BodyReader<B> {
+        match *framing {
+            Framing::Length(length) => BodyReader::Length(reader, length as u64),
+            Framing::Chunked => BodyReader::Chunked(ChunkedReader::with_limits(reader, limits)),
+        }
+    }
+
+    // The number of bytes left to read, if known.
+    pub fn remaining(&self) -> Option<u64> {
+        match *self {
+            BodyReader::Length(_, remaining) => Some(remaining),
+            BodyReader::Chunked(_) => None,
+        }
+    }
+}
+
+impl<B: BufRead> Read for BodyReader<B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            BodyReader::Length(ref mut reader, ref mut remaining) => {
+                if *remaining == 0 || buf.is_empty() {
+                    return Ok(0);
+                }
+                let max = cmp::min(buf.len() as u64, *remaining) as usize;
+                let len = reader.read(&mut buf[..max])?;
+                if len == 0 {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                            Error::new(ErrorKind::Io, "Failed to read requested bytes")));
+                }
+                *remaining -= len as u64;
+                Ok(len)
+            },
+            BodyReader::Chunked(ref mut reader) => reader.read(buf),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Body {
+    content: Vec<u8>,
+    trailers: Vec<Field>,
+}
+
+impl Body {
+    pub fn parse<R: Read>(reader: &mut R, length: usize) -> Result<Body, Error> {
+        // The length comes from the peer, so the content grows as it arrives
+        // rather than being allocated up front.
+        let mut content = Vec::new();
+        reader.take(length as u64).read_to_end(&mut content)?;
+        if content.len() < length {
+            return Error::err(ErrorKind::Io, "Failed to read requested bytes");
+        }
+        Ok(Body{ content, trailers: Vec::new() })
+    }
+
+    pub fn parse_chunked<B: BufRead>(reader: &mut B) -> Result<Body, Error> {
+        let mut reader = ChunkedReader::new(reader);
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        Ok(Body{ content, trailers: reader.into_trailers() })
+    }
+
+    pub fn parse_framed<B: BufRead>(reader: &mut B, framing: &Framing)
+            -> Result<Body, Error> {
+        match *framing {
+            Framing::Length(length) => Body::parse(reader, length),
+            Framing::Chunked => Body::parse_chunked(reader),
+        }
+    }
+
+    // Reads a body which has already been unframed, e.g. by a BodyReader.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Body, Error> {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        Ok(Body{ content, trailers: Vec::new() })
+    }
+
+    // Reads everything, unless there is more than max bytes of it.
+    pub fn read_limited<R: Read>(reader: &mut R, max: u64) -> Result<Body, Error> {
+        let mut content = Vec::new();
+        reader.take(max + 1).read_to_end(&mut content)?;
+        if content.len() as u64 > max {
+            return Error::err(ErrorKind::BodyTooLarge, "Body too large");
+        }
+        Ok(Body{ content, trailers: Vec::new() })
+    }
+
+    pub fn from(string: String) -> Body {
+        Body{ content: string.into_bytes(), trailers: Vec::new() }
+    }
+
+    pub fn content_length(&self) -> usize {
+        self.content.len()
+    }
+
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
+
+    pub fn trailers(&self) -> &[Field] {
+        &self.trailers
+    }
+}
+
+impl fmt::Display for Body {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Binary content isn't mangled into text; it is only summarised.
+        match str::from_utf8(&self.content) {
+            Ok(text) => f.pad(text),
+            Err(_) => f.pad(&format!("<{} bytes of binary data>", self.content.len())),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    fn assert_body_empty(body: &Body) {
+        let empty_vec: Vec<u8> = Vec::new();
+        assert_eq!(empty_vec, body.content);
+        assert_eq!(0, body.content_length());
+        assert!(body.content().is_empty());
+        assert_eq!("", format!("{}", body));
+    }
+
+    fn assert_body_eq(expected: String, actual: &Body, ) {
+        assert_eq!(expected, format!("{}", actual));
+        let expected = expected.into_bytes();
+        assert_eq!(expected, actual.content);
+        assert_eq!(expected.len(), actual.content_length());
+        assert_eq!(expected.as_slice(), actual.content());
+    }
+
+    #[test]
+    fn from_empty_string() {
+        let body = Body::from(String::new());
+        assert_body_empty(&body);
+    }
+
+    #[test]
+    fn from_nonempty_string() {
+        let expected = String::from("hello world");
+        let body = Body::from(expected.clone());
+        assert_body_eq(expected, &body);
+    }
+
+    #[test]
+    fn display_binary() {
+        let mut reader = &[0x00, 0xff, 0xfe][..];
+        let body = Body::parse(&mut reader, 3).unwrap();
+        assert_eq!(&[0x00, 0xff, 0xfe], body.content());
+        assert_eq!("<3 bytes of binary data>", format!("{}", body));
+    }
+
+    #[test]
+    fn parse_no_bytes() {
+        let mut reader = StringReader::new("");
+        let body = Body::parse(&mut reader, 0).unwrap();
+        assert_body_empty(&body);
+    }
+
+    #[test]
+    fn parse_exact_bytes() {
+        let expected = "hello world";
+        let mut reader = StringReader::new(expected);
+        let body = Body::parse(&mut reader, 11).unwrap();
+        assert_body_eq(String::from(expected), &body);
+    }
+
+    #[test]
+    fn parse_too_many_bytes() {
+        let expected = "hello world";
+        let mut reader = StringReader::new(expected);
+        let body = Body::parse(&mut reader, 4).unwrap();
+        assert_body_eq(String::from(&expected[..4]), &body);
+    }
+
+    #[test]
+    fn parse_too_few_bytes() {
+        let expected = "hello world";
+        let mut reader = StringReader::new(expected);
+        let result = Body::parse(&mut reader, 20);
+        assert_parse_error("HTTP parsing error: Failed to read requested bytes",
+                result);
+    }
+
+    #[test]
+    fn parse_huge_length() {
+        // Nothing is allocated for content which never arrives.
+        let mut reader = StringReader::new("hello world");
+        let result = Body::parse(&mut reader, usize::MAX / 2);
+        assert_parse_error("HTTP parsing error: Failed to read requested bytes",
+                result);
+    }
+
+    #[test]
+    fn read_limited() {
+        let body = Body::read_limited(&mut StringReader::new("hello"), 5).unwrap();
+        assert_eq!(b"hello", body.content());
+        let result = Body::read_limited(&mut StringReader::new("hello!"), 5);
+        assert_eq!(ErrorKind::BodyTooLarge, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn framing_from_fields() {
+        let field = |name: &str, value: &str| {
+            Field::new(String::from(name), String::from(value))
+        };
+        assert_eq!(Framing::Length(0), Framing::from_fields(&[]).unwrap());
+        assert_eq!(Framing::Length(42), Framing::from_fields(
+            &[field("content-length", "42")]).unwrap());
+        assert_eq!(Framing::Chunked, Framing::from_fields(
+            &[field("Transfer-Encoding", "Chunked")]).unwrap());
+        assert_eq!(Framing::Chunked, Framing::from_fields(
+            &[field("Transfer-Encoding", " chunked ,")]).unwrap());
+        assert_parse_error("HTTP parsing error: Invalid content length",
+            Framing::from_fields(&[field("Content-Length", "-1")]));
+        assert_parse_error("HTTP parsing error: Unsupported transfer coding",
+            Framing::from_fields(&[field("Transfer-Encoding", "gzip")]));
+        assert_parse_error("HTTP parsing error: Unsupported transfer coding",
+            Framing::from_fields(&[field("Transfer-Encoding", "gzip, chunked")]));
+        assert_parse_error("HTTP parsing error: Unsupported transfer coding",
+            Framing::from_fields(&[field("Transfer-Encoding", "")]));
+        assert_parse_error(
+            "HTTP parsing error: Both Transfer-Encoding and Content-Length given",
+            Framing::from_fields(&[field("Transfer-Encoding", "chunked"),
+                                   field("Content-Length", "5")]));
+    }
+
+    #[test]
+    fn parse_chunked() {
+        let mut reader = StringReader::new("\
+            5\r\n\
+            hello\r\n\
+            0\r\n\
+            Foo: bar\r\n\
+            \r\n\
+            rest");
+        let body = Body::parse_framed(&mut reader, &Framing::Chunked).unwrap();
+        assert_eq!(b"hello", body.content());
+        assert_eq!(&[Field::new(String::from("Foo"), String::from("bar"))],
+                   body.trailers());
+        assert_eq!(b"rest", reader.fill_buf().unwrap());
+    }
+
+    #[test]
+    fn parse_chunked_malformed() {
+        let mut reader = StringReader::new("5\r\nhello0\r\n\r\n");
+        assert_parse_error("HTTP parsing error: Missing chunk terminator at byte 8",
+                Body::parse_chunked(&mut reader));
+        let mut reader = StringReader::new("five\r\nhello\r\n0\r\n\r\n");
+        assert_parse_error("HTTP parsing error: Invalid chunk size at byte 0",
+                Body::parse_chunked(&mut reader));
+        let mut reader = StringReader::new("5\r\nhello\r\n");
+        assert_parse_error("HTTP parsing error: Unexpected end of stream at byte 10",
+                Body::parse_chunked(&mut reader));
+    }
+
+    #[test]
+    fn body_reader_length() {
+        let mut input = StringReader::new("hello world");
+        let mut reader = BodyReader::new(&mut input, &Framing::Length(5));
+        assert_eq!(Some(5), reader.remaining());
+        let body = Body::read_from(&mut reader).unwrap();
+        assert_eq!(b"hello", body.content());
+        assert_eq!(Some(0), reader.remaining());
+
+        let mut input = StringReader::new("hello");
+        let mut reader = BodyReader::new(&mut input, &Framing::Length(10));
+        assert_parse_error("HTTP parsing error: Failed to read requested bytes",
+                Body::read_from(&mut reader));
+    }
+
+    #[test]
+    fn body_reader_chunked() {
+        let mut input = StringReader::new("5\r\nhello\r\n0\r\n\r\n");
+        let mut reader = BodyReader::new(&mut input, &Framing::Chunked);
+        assert_eq!(None, reader.remaining());
+        let body = Body::read_from(&mut reader).unwrap();
+        assert_eq!(b"hello", body.content());
+    }
+
+    // Pushes input in pieces of the given size, returning the content and
+    // the input which followed the body.
+    fn decode_pieces(framing: &Framing, input: &[u8], size: usize)
+            -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let mut decoder = BodyDecoder::new(framing);
+        let mut content = Vec::new();
+        let mut pieces = input.chunks(size);
+        while let Some(mut piece) = pieces.next() {
+            loop {
+                let (decoded, len) = decoder.push(piece)?;
+                piece = &piece[len..];
+                match decoded {
+                    Decoded::Data(data) => content.extend_from_slice(data),
+                    Decoded::NeedMore => break,
+                    Decoded::Complete => {
+                        let mut rest = piece.to_vec();
+                        rest.extend(pieces.flatten());
+                        return Ok((content, rest));
+                    },
+                }
+            }
+        }
+        panic!("Body not complete");
+    }
+
+    #[test]
+    fn decode_length() {
+        for size in 1..12 {
+            let (content, rest) = decode_pieces(&Framing::Length(5), b"helloGET /",
+                                                size).unwrap();
+            assert_eq!(b"hello".to_vec(), content);
+            assert_eq!(b"GET /".to_vec(), rest);
+        }
+        let mut decoder = BodyDecoder::new(&Framing::Length(0));
+        assert_eq!((Decoded::Complete, 0), decoder.push(b"next").unwrap());
+    }
+
+    #[test]
+    fn decode_chunked() {
+        let input = b"5;x=y\r\nhello\r\n6\r\n world\r\n0\r\nDigest: abc\r\n\r\nnext";
+        for size in 1..input.len() + 1 {
+            let (content, rest) = decode_pieces(&Framing::Chunked, input, size).unwrap();
+            assert_eq!(b"hello world".to_vec(), content);
+            assert_eq!(b"next".to_vec(), rest);
+        }
+
+        let mut decoder = BodyDecoder::new(&Framing::Chunked);
+        let mut input = &input[..];
+        loop {
+            let (decoded, len) = decoder.push(input).unwrap();
+            input = &input[len..];
+            if decoded == Decoded::Complete {
+                break;
+            }
+        }
+        assert_eq!(b"next", input);
+        assert_eq!(vec![Field::new(String::from("Digest"), String::from("abc"))],
+                   decoder.trailers());
+    }
+
+    #[test]
+    fn decode_chunked_errors() {
+        // Errors are the same however the input arrives.
+        let input = b"5\r\nhello\r\nxyz\r\n";
+        for size in 1..input.len() + 1 {
+            assert_parse_error("HTTP parsing error: Invalid chunk size at byte 10",
+                    decode_pieces(&Framing::Chunked, input, size));
+        }
+        let input = b"5\r\nhello\n0\r\n\r\n";
+        for size in 1..input.len() + 1 {
+            assert_parse_error("HTTP parsing error: Line not ended by CRLF at byte 8",
+                    decode_pieces(&Framing::Chunked, input, size));
+        }
+    }
+}
@@ -0,0 +1,471 @@
+use std::cmp;
+use std::io;
+use std::io::{BufRead, Read, Write};
+use std::mem;
+use super::{Error, ErrorKind};
+use super::Decoded;
+use super::Field;
+use super::Limits;
+
+// Chunk size lines and trailer fields longer than this are rejected rather
+// than buffered indefinitely.
+const MAX_LINE_LENGTH: usize = 8192;
+
+#[derive(Debug, PartialEq)]
+enum State {
+    Size,
+    Data,
+    DataEnd,
+    Trailers,
+    Done,
+}
+
+// Decodes a body sent with the chunked transfer coding, from input as it
+// arrives:
+//   chunked-body   = *chunk
+//                    last-chunk
+//                    trailer-part
+//                    CRLF
+//   chunk          = chunk-size [ chunk-ext ] CRLF
+//                    chunk-data CRLF
+//   chunk-size     = 1*HEXDIG
+//   last-chunk     = 1*("0") [ chunk-ext ] CRLF
+//   chunk-ext      = *( ";" chunk-ext-name [ "=" chunk-ext-val ] )
+//   trailer-part   = *( header-field CRLF )
+// The trailer fields are held to the same limits as header fields. Errors
+// give the offset in the body at which they were found.
+pub struct ChunkedDecoder {
+    state: State,
+    remaining: u64,
+    // How much of the body has been taken.
+    offset: usize,
+    // The line being received, and where it started.
+    line: Vec<u8>,
+    line_start: usize,
+    limits: Limits,
+    trailers: Vec<Field>,
+    trailer_bytes: usize,
+}
+
+impl Default for ChunkedDecoder {
+    fn default() -> ChunkedDecoder {
+        ChunkedDecoder::new()
+    }
+}
+
+impl ChunkedDecoder {
+    pub fn new() -> ChunkedDecoder {
+        ChunkedDecoder::with_limits(&Limits::default())
+    }
+
+    pub fn with_limits(limits: &Limits) -> ChunkedDecoder {
+        ChunkedDecoder{ state: State::Size, remaining: 0, offset: 0, line: Vec::new(),
+                        line_start: 0, limits: limits.clone(), trailers: Vec::new(),
+                        trailer_bytes: 0 }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // The trailer fields, available once the whole body has been decoded.
+    pub fn trailers(&self) -> &[Field] {
+        &self.trailers
+    }
+
+    pub fn into_trailers(self) -> Vec<Field> {
+        self.trailers
+    }
+
+    // Decodes what it can of the input, returning it along with how much of
+    // the input was taken.
+    pub fn push<'a>(&mut self, input: &'a [u8]) -> Result<(Decoded<'a>, usize), Error> {
+        let mut taken = 0;
+        loop {
+            let rest = &input[taken..];
+            match self.state {
+                State::Done => return Ok((Decoded::Complete, taken)),
+                State::Data => {
+                    if rest.is_empty() {
+                        return Ok((Decoded::NeedMore, taken));
+                    }
+                    let len = cmp::min(rest.len() as u64, self.remaining) as usize;
+                    self.offset += len;
+                    self.remaining -= len as u64;
+                    if self.remaining == 0 {
+                        self.state = State::DataEnd;
+                    }
+                    return Ok((Decoded::Data(&rest[..len]), taken + len));
+                },
+                _ => {
+                    let (line, len) = self.take_line(rest)?;
+                    taken += len;
+                    match line {
+                        Some(line) => {
+                            let start = self.line_start;
+                            self.decode_line(line, start)?;
+                        },
+                        None => return Ok((Decoded::NeedMore, taken)),
+                    }
+                },
+            }
+        }
+    }
+
+    // Takes input up to the end of a line, returning the line without its
+    // CRLF if it has ended. A bare LF isn't accepted as a line ending, as
+    // intermediaries which disagree about that can be made to see different
+    // messages.
+    fn take_line(&mut self, input: &[u8]) -> Result<(Option<Vec<u8>>, usize), Error> {
+        if self.line.is_empty() {
+            self.line_start = self.offset;
+        }
+        let rest = &input[..cmp::min(input.len(), MAX_LINE_LENGTH - self.line.len())];
+        let (len, ended) = match rest.iter().position(|&b| b == b'\n') {
+            Some(index) => (index + 1, true),
+            None => (rest.len(), false),
+        };
+        self.line.extend_from_slice(&rest[..len]);
+        self.offset += len;
+        if !ended {
+            if self.line.len() == MAX_LINE_LENGTH {
+                return Err(Error::new(ErrorKind::Body, "Chunk line too long")
+                    .at(self.line_start));
+            }
+            return Ok((None, len));
+        }
+        let mut line = mem::take(&mut self.line);
+        line.pop();
+        if line.pop() != Some(b'\r') {
+            return Err(Error::new(ErrorKind::Body, "Line not ended by CRLF")
+                .at(self.offset - 1));
+        }
+        Ok((Some(line), len))
+    }
+
+    fn decode_line(&mut self, line: Vec<u8>, start: usize) -> Result<(), Error> {
+        match self.state {
+            State::Size => {
+                self.remaining = parse_size(&line)
+                    .ok_or_else(|| Error::new(ErrorKind::Body, "Invalid chunk size").at(start))?;
+                self.state = if self.remaining == 0 { State::Trailers } else { State::Data };
+            },
+            State::DataEnd => {
+                if !line.is_empty() {
+                    return Err(Error::new(ErrorKind::Body, "Missing chunk terminator")
+                        .at(start));
+                }
+                self.state = State::Size;
+            },
+            State::Trailers => {
+                if line.is_empty() {
+                    self.state = State::Done;
+                } else {
+                    self.trailer_bytes += line.len();
+                    if self.trailer_bytes > self.limits.header_bytes {
+                        return Err(Error::new(ErrorKind::HeaderTooLarge, "Trailer too large")
+                            .at(start));
+                    }
+                    if self.trailers.len() == self.limits.header_count {
+                        return Err(Error::new(ErrorKind::HeaderTooLarge,
+                                "Too many trailer fields").at(start));
+                    }
+                    let field = Field::parse(&line).map_err(|error| error.offset_by(start))?;
+                    self.trailers.push(field);
+                }
+            },
+            State::Data | State::Done => unreachable!("Not expecting a line"),
+        }
+        Ok(())
+    }
+}
+
+fn parse_size(line: &[u8]) -> Option<u64> {
+    // Extensions are not used for anything, so are skipped.
+    let end = line.iter().position(|&b| b == b';').unwrap_or(line.len());
+    let size = trim_end(&line[..end]);
+    if size.is_empty() || !size.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    u64::from_str_radix(&String::from_utf8_lossy(size), 16).ok()
+}
+
+// Decodes a chunked body from a reader. Malformed framing is reported as an
+// io::Error wrapping a ParseError.
+pub struct ChunkedReader<B> {
+    inner: B,
+    decoder: ChunkedDecoder,
+}
+
+impl<B: BufRead> ChunkedReader<B> {
+    pub fn new(inner: B) -> ChunkedReader<B> {
+        ChunkedReader::with_limits(inner, &Limits::default())
+    }
+
+    pub fn with_limits(inner: B, limits: &Limits) -> ChunkedReader<B> {
+        ChunkedReader{ inner, decoder: ChunkedDecoder::with_limits(limits) }
+    }
+
+    // The trailer fields, available once the whole body has been read.
+    pub fn trailers(&self) -> &[Field] {
+        self.decoder.trailers()
+    }
+
+    pub fn into_trailers(self) -> Vec<Field> {
+        self.decoder.into_trailers()
+    }
+}
+
+impl<B: BufRead> Read for ChunkedReader<B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let input = self.inner.fill_buf()?;
+            // Never take more data than there is room for.
+            let input = &input[..cmp::min(input.len(), buf.len())];
+            let (decoded, taken) = self.decoder.push(input)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            let len = match decoded {
+                Decoded::Data(data) => {
+                    buf[..data.len()].copy_from_slice(data);
+                    data.len()
+                },
+                Decoded::Complete => 0,
+                Decoded::NeedMore if input.is_empty() => {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                            Error::new(ErrorKind::Body, "Unexpected end of stream")
+                                .at(self.decoder.offset())));
+                },
+                Decoded::NeedMore => {
+                    self.inner.consume(taken);
+                    continue;
+                },
+            };
+            self.inner.consume(taken);
+            return Ok(len);
+        }
+    }
+}
+
+// Encodes everything written to it with the chunked transfer coding, for
+// bodies whose length isn't known up front. Each write becomes one chunk, so
+// small writes should be buffered first. finish() must be called to write the
+// last chunk.
+pub struct ChunkedWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> ChunkedWriter<W> {
+    pub fn new(inner: W) -> ChunkedWriter<W> {
+        ChunkedWriter{ inner }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.write_all(b"0\r\n\r\n")?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for ChunkedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // An empty chunk would end the body.
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        write!(self.inner, "{:x}\r\n", buf.len())?;
+        self.inner.write_all(buf)?;
+        self.inner.write_all(b"\r\n")?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn trim_end(bytes: &[u8]) -> &[u8] {
+    let end = bytes.iter().rposition(|&b| b != b' ' && b != b'\t')
+        .map_or(0, |index| index + 1);
+    &bytes[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    fn decode(encoded: &str) -> Result<(Vec<u8>, Vec<Field>), Error> {
+        let mut reader = ChunkedReader::new(StringReader::new(encoded));
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        Ok((content, reader.into_trailers()))
+    }
+
+    #[test]
+    fn decode_chunks() {
+        let (content, trailers) = decode("\
+            5\r\n\
+            hello\r\n\
+            A\r\n \
+            big world\r\n\
+            0\r\n\
+            \r\n").unwrap();
+        assert_eq!(b"hello big world".to_vec(), content);
+        assert!(trailers.is_empty());
+    }
+
+    #[test]
+    fn decode_empty() {
+        let (content, _) = decode("0\r\n\r\n").unwrap();
+        assert!(content.is_empty());
+        let (content, _) = decode("000\r\n\r\n").unwrap();
+        assert!(content.is_empty());
+    }
+
+    #[test]
+    fn decode_extensions() {
+        let (content, _) = decode("\
+            5;name=value;flag\r\n\
+            hello\r\n\
+            0 ; last=\"yes\"\r\n\
+            \r\n").unwrap();
+        assert_eq!(b"hello".to_vec(), content);
+    }
+
+    #[test]
+    fn decode_trailers() {
+        let (content, trailers) = decode("\
+            5\r\n\
+            hello\r\n\
+            0\r\n\
+            Digest: sha-256=abc\r\n\
+            Expires: never\r\n\
+            \r\n").unwrap();
+        assert_eq!(b"hello".to_vec(), content);
+        assert_eq!(vec![Field::new(String::from("Digest"), String::from("sha-256=abc")),
+                        Field::new(String::from("Expires"), String::from("never"))],
+                   trailers);
+    }
+
+    // Trailer fields without end.
+    struct TrailerFlood(usize);
+
+    impl Read for TrailerFlood {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let line = b"X: y\r\n";
+            for b in buf.iter_mut() {
+                *b = line[self.0 % line.len()];
+                self.0 += 1;
+            }
+            Ok(buf.len())
+        }
+    }
+
+    #[test]
+    fn decode_too_many_trailers() {
+        let input = io::BufReader::new((&b"0\r\n"[..]).chain(TrailerFlood(0)));
+        let result = ChunkedReader::new(input).read_to_end(&mut Vec::new());
+        let error = Error::from(result.unwrap_err());
+        assert_eq!(ErrorKind::HeaderTooLarge, error.kind());
+        assert_eq!("HTTP parsing error: Too many trailer fields at byte 603",
+                   format!("{}", error));
+
+        let limits = Limits{ header_bytes: 10, ..Limits::default() };
+        let mut reader = ChunkedReader::with_limits(
+            StringReader::new("0\r\nA: 1234\r\nB: 1234\r\n\r\n"), &limits);
+        let result = reader.read_to_end(&mut Vec::new()).map_err(Error::from);
+        assert_parse_error("HTTP parsing error: Trailer too large at byte 12", result);
+    }
+
+    #[test]
+    fn decode_leaves_following_bytes() {
+        let mut input = StringReader::new("1\r\na\r\n0\r\n\r\nnext");
+        let mut content = Vec::new();
+        ChunkedReader::new(&mut input).read_to_end(&mut content).unwrap();
+        assert_eq!(b"a".to_vec(), content);
+        let mut rest = String::new();
+        input.read_to_string(&mut rest).unwrap();
+        assert_eq!("next", rest);
+    }
+
+    #[test]
+    fn decode_invalid_size() {
+        assert_parse_error("HTTP parsing error: Invalid chunk size at byte 0",
+                decode("x\r\nhello\r\n0\r\n\r\n"));
+        assert_parse_error("HTTP parsing error: Invalid chunk size at byte 0",
+                decode("\r\nhello\r\n0\r\n\r\n"));
+        assert_parse_error("HTTP parsing error: Invalid chunk size at byte 0",
+                decode("+5\r\nhello\r\n0\r\n\r\n"));
+        assert_parse_error("HTTP parsing error: Invalid chunk size at byte 0",
+                decode("-1\r\nhello\r\n0\r\n\r\n"));
+        assert_parse_error("HTTP parsing error: Invalid chunk size at byte 0",
+                decode("10000000000000000\r\n"));
+    }
+
+    #[test]
+    fn decode_long_line() {
+        let line = format!("5;{}\r\nhello\r\n0\r\n\r\n", "x".repeat(10000));
+        assert_parse_error("HTTP parsing error: Chunk line too long at byte 0",
+                decode(&line));
+    }
+
+    #[test]
+    fn decode_missing_terminator() {
+        assert_parse_error("HTTP parsing error: Missing chunk terminator at byte 6",
+                decode("3\r\nhello\r\n0\r\n\r\n"));
+        // Every line must end with CRLF, not just LF.
+        assert_parse_error("HTTP parsing error: Line not ended by CRLF at byte 1",
+                decode("5\nhello\r\n0\r\n\r\n"));
+        assert_parse_error("HTTP parsing error: Line not ended by CRLF at byte 8",
+                decode("5\r\nhello\n0\r\n\r\n"));
+        assert_parse_error("HTTP parsing error: Line not ended by CRLF at byte 11",
+                decode("5\r\nhello\r\n0\n\r\n"));
+        assert_parse_error("HTTP parsing error: Line not ended by CRLF at byte 17",
+                decode("5\r\nhello\r\n0\r\nA: 1\n\r\n"));
+        assert_parse_error("HTTP parsing error: Line not ended by CRLF at byte 13",
+                decode("5\r\nhello\r\n0\r\n\n"));
+    }
+
+    #[test]
+    fn decode_truncated() {
+        assert_parse_error("HTTP parsing error: Unexpected end of stream at byte 6",
+                decode("5\r\nhel"));
+        assert_parse_error("HTTP parsing error: Unexpected end of stream at byte 8",
+                decode("5\r\nhello"));
+        assert_parse_error("HTTP parsing error: Unexpected end of stream at byte 10",
+                decode("5\r\nhello\r\n"));
+        assert_parse_error("HTTP parsing error: Unexpected end of stream at byte 13",
+                decode("5\r\nhello\r\n0\r\n"));
+    }
+
+    #[test]
+    fn decode_invalid_trailer() {
+        assert_parse_error("HTTP parsing error: Invalid field at byte 14",
+                decode("0\r\nnot a field\r\n\r\n"));
+    }
+
+    #[test]
+    fn encode() {
+        let mut writer = ChunkedWriter::new(Vec::new());
+        writer.write_all(b"hello").unwrap();
+        writer.write_all(b"").unwrap();
+        writer.write_all(b" big world").unwrap();
+        let encoded = writer.finish().unwrap();
+        assert_eq!(b"5\r\nhello\r\na\r\n big world\r\n0\r\n\r\n".to_vec(), encoded);
+    }
+
+    #[test]
+    fn round_trip() {
+        let content: Vec<u8> = (0..5000).map(|i| (i * 7 % 256) as u8).collect();
+        let mut writer = ChunkedWriter::new(Vec::new());
+        for chunk in content.chunks(1000) {
+            writer.write_all(chunk).unwrap();
+        }
+        let encoded = writer.finish().unwrap();
+        let mut decoded = Vec::new();
+        ChunkedReader::new(encoded.as_slice()).read_to_end(&mut decoded).unwrap();
+        assert_eq!(content, decoded);
+    }
+}
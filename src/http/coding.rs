@@ -0,0 +1,157 @@
+use std::io;
+use std::io::{Read, Write};
+use flate2::Compression;
+use flate2::read::{GzDecoder, GzEncoder, ZlibDecoder, ZlibEncoder};
+use flate2::write::{GzEncoder as GzWriter, ZlibEncoder as ZlibWriter};
+use super::{Error, ErrorKind};
+
+// The content codings which can be applied to a body. Deflate is the zlib
+// format, as HTTP defines it, rather than raw deflate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContentCoding {
+    Identity,
+    Gzip,
+    Deflate,
+}
+
+impl ContentCoding {
+    pub fn from(name: &str) -> Option<ContentCoding> {
+        match name.to_ascii_lowercase().as_str() {
+            "identity" => Some(ContentCoding::Identity),
+            "gzip" | "x-gzip" => Some(ContentCoding::Gzip),
+            "deflate" => Some(ContentCoding::Deflate),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ContentCoding::Identity => "identity",
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Deflate => "deflate",
+        }
+    }
+
+    pub fn encode(&self, content: &[u8]) -> Vec<u8> {
+        let written = match *self {
+            ContentCoding::Identity => return content.to_vec(),
+            ContentCoding::Gzip => {
+                let mut writer = GzWriter::new(Vec::new(), Compression::default());
+                writer.write_all(content).and_then(|_| writer.finish())
+            },
+            ContentCoding::Deflate => {
+                let mut writer = ZlibWriter::new(Vec::new(), Compression::default());
+                writer.write_all(content).and_then(|_| writer.finish())
+            },
+        };
+        written.expect("Writing to memory failed")
+    }
+
+    pub fn encoder<'a, R: Read + 'a>(&self, reader: R) -> Box<dyn Read + 'a> {
+        match *self {
+            ContentCoding::Identity => Box::new(reader),
+            ContentCoding::Gzip => Box::new(GzEncoder::new(reader, Compression::default())),
+            ContentCoding::Deflate => Box::new(ZlibEncoder::new(reader, Compression::default())),
+        }
+    }
+
+    pub fn decoder<'a, R: Read + 'a>(&self, reader: R) -> Box<dyn Read + 'a> {
+        match *self {
+            ContentCoding::Identity => Box::new(reader),
+            ContentCoding::Gzip => Box::new(Decoder(GzDecoder::new(reader))),
+            ContentCoding::Deflate => Box::new(Decoder(ZlibDecoder::new(reader))),
+        }
+    }
+}
+
+// Undoes the codings applied to a body, given in the order they were applied.
+pub fn decode_content<'a, R: Read + 'a>(reader: R, codings: &[ContentCoding]) -> Box<dyn Read + 'a> {
+    codings.iter().rev().fold(Box::new(reader), |reader, coding| coding.decoder(reader))
+}
+
+// Reports content which can't be decoded as a malformed body, leaving errors
+// reading the body itself as they are.
+struct Decoder<R>(R);
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf).map_err(|error| {
+            let wrapped = error.get_ref().is_some_and(|inner| inner.is::<Error>());
+            match error.kind() {
+                io::ErrorKind::InvalidData |
+                io::ErrorKind::InvalidInput |
+                io::ErrorKind::UnexpectedEof if !wrapped => {
+                    io::Error::new(io::ErrorKind::InvalidData,
+                            Error::new(ErrorKind::Body, "Invalid content coding"))
+                },
+                _ => error,
+            }
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    const CODINGS: [ContentCoding; 3] =
+        [ContentCoding::Identity, ContentCoding::Gzip, ContentCoding::Deflate];
+
+    fn decode_all(encoded: &[u8], codings: &[ContentCoding]) -> Result<Vec<u8>, Error> {
+        let mut decoded = Vec::new();
+        decode_content(encoded, codings).read_to_end(&mut decoded)?;
+        Ok(decoded)
+    }
+
+    #[test]
+    fn from() {
+        assert_eq!(Some(ContentCoding::Gzip), ContentCoding::from("GZIP"));
+        assert_eq!(Some(ContentCoding::Gzip), ContentCoding::from("x-gzip"));
+        assert_eq!(Some(ContentCoding::Deflate), ContentCoding::from("deflate"));
+        assert_eq!(Some(ContentCoding::Identity), ContentCoding::from("identity"));
+        assert_eq!(None, ContentCoding::from("br"));
+        for coding in &CODINGS {
+            assert_eq!(Some(*coding), ContentCoding::from(coding.name()));
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let content = random_bytes(7, 10_000);
+        for coding in &CODINGS {
+            let encoded = coding.encode(&content);
+            assert_eq!(content, decode_all(&encoded, &[*coding]).unwrap());
+            let mut streamed = Vec::new();
+            coding.encoder(content.as_slice()).read_to_end(&mut streamed).unwrap();
+            assert_eq!(content, decode_all(&streamed, &[*coding]).unwrap());
+        }
+    }
+
+    #[test]
+    fn compresses() {
+        let content = "{\"oid\": \"abc\", \"size\": 1}, ".repeat(100);
+        assert!(ContentCoding::Gzip.encode(content.as_bytes()).len() < content.len() / 10);
+        assert!(ContentCoding::Deflate.encode(content.as_bytes()).len() < content.len() / 10);
+    }
+
+    #[test]
+    fn decode_in_order() {
+        let content = b"hello hello hello";
+        let encoded = ContentCoding::Gzip.encode(&ContentCoding::Deflate.encode(content));
+        assert_eq!(content.to_vec(),
+                   decode_all(&encoded, &[ContentCoding::Deflate, ContentCoding::Gzip]).unwrap());
+    }
+
+    #[test]
+    fn decode_invalid() {
+        let encoded = ContentCoding::Gzip.encode(b"hello");
+        for coding in &[ContentCoding::Gzip, ContentCoding::Deflate] {
+            assert_parse_error("HTTP parsing error: Invalid content coding",
+                    decode_all(b"hello", &[*coding]));
+        }
+        assert_parse_error("HTTP parsing error: Invalid content coding",
+                decode_all(&encoded[..encoded.len() - 4], &[ContentCoding::Gzip]));
+    }
+}
@@ -0,0 +1,229 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAY_NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTH_NAMES: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun",
+                                 "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+// A UTC date and time, to the second. Fields are ordered from most to least
+// significant so that later times compare greater.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl DateTime {
+    pub fn from(time: SystemTime) -> DateTime {
+        // Times before the epoch are clamped to it.
+        let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let days = (secs / 86400) as i64;
+        let secs_of_day = (secs % 86400) as u32;
+        let (year, month, day) = civil_from_days(days);
+        DateTime{ year, month, day,
+                  hour: secs_of_day / 3600,
+                  minute: secs_of_day / 60 % 60,
+                  second: secs_of_day % 60 }
+    }
+
+    pub fn now() -> DateTime {
+        DateTime::from(SystemTime::now())
+    }
+
+    // Formats as an RFC 3339 UTC timestamp, e.g. 2016-05-17T15:49:06Z
+    pub fn rfc3339(&self) -> String {
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", self.year, self.month,
+                self.day, self.hour, self.minute, self.second)
+    }
+
+    // Formats as an HTTP date, e.g. Sun, 06 Nov 1994 08:49:37 GMT
+    pub fn http_date(&self) -> String {
+        // IMF-fixdate = day-name "," SP date1 SP time-of-day SP GMT
+        let days = days_from_civil(self.year, self.month, self.day);
+        let weekday = (days + 4).rem_euclid(7) as usize;
+        format!("{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT", DAY_NAMES[weekday],
+                self.day, MONTH_NAMES[self.month as usize - 1], self.year,
+                self.hour, self.minute, self.second)
+    }
+
+    // Parses an HTTP date in any of the three formats which have been used:
+    //   Sun, 06 Nov 1994 08:49:37 GMT    ; IMF-fixdate
+    //   Sunday, 06-Nov-94 08:49:37 GMT   ; obsolete RFC 850 format
+    //   Sun Nov  6 08:49:37 1994         ; ANSI C's asctime() format
+    // The day name isn't checked against the date.
+    pub fn from_http_date(value: &str) -> Option<DateTime> {
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        let (day, month, year, time): (i64, &str, i64, &str) = match parts.as_slice() {
+            [day_name, day, month, year, time, "GMT"] if day_name.ends_with(',') => {
+                (parse_number(day, 2)?, *month, parse_number(year, 4)?, *time)
+            },
+            [day_name, date, time, "GMT"] if day_name.ends_with(',') => {
+                let mut date = date.split('-');
+                let (day, month) = (parse_number(date.next()?, 2)?, date.next()?);
+                // Two digit years more than 50 years in the future are in the
+                // past century.
+                let year = match parse_number(date.next()?, 2)? {
+                    year if year < 70 => 2000 + year,
+                    year => 1900 + year,
+                };
+                (day, month, year, *time)
+            },
+            [_, month, day, time, year] => {
+                // Days before the 10th are padded with a space, rather than 0.
+                let day = parse_number(day, 1).or_else(|| parse_number(day, 2))?;
+                (day, *month, parse_number(year, 4)?, *time)
+            },
+            _ => return None,
+        };
+        let month = MONTH_NAMES.iter().position(|name| *name == month)? as u32 + 1;
+        let mut time = time.split(':');
+        let hour = parse_number(time.next()?, 2)?;
+        let minute = parse_number(time.next()?, 2)?;
+        let second = parse_number(time.next()?, 2)?;
+        if time.next().is_some() || day < 1 || day > days_in_month(year, month) ||
+           hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        Some(DateTime{ year, month, day: day as u32, hour: hour as u32,
+                       minute: minute as u32, second: second as u32 })
+    }
+}
+
+// Parses exactly the given number of digits.
+fn parse_number(digits: &str, count: usize) -> Option<i64> {
+    if digits.len() != count || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+fn days_in_month(year: i64, month: u32) -> i64 {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Converts a number of days since 1970-01-01 to a (year, month, day) in the
+// proleptic Gregorian calendar. See
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = (z - era * 146097) as u32;                    // [0, 146096]
+    let yoe = (doe - doe/1460 + doe/36524 - doe/146096) / 365;  // [0, 399]
+    let doy = doe - (365*yoe + yoe/4 - yoe/100);            // [0, 365]
+    let mp = (5*doy + 2) / 153;                             // [0, 11]
+    let day = doy - (153*mp + 2) / 5 + 1;                   // [1, 31]
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };      // [1, 12]
+    let year = yoe as i64 + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// The inverse of civil_from_days. See
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = (year - era * 400) as u32;                    // [0, 399]
+    let mp = if month > 2 { month - 3 } else { month + 9 }; // [0, 11]
+    let doy = (153*mp + 2) / 5 + day - 1;                   // [0, 365]
+    let doe = yoe * 365 + yoe/4 - yoe/100 + doy;            // [0, 146096]
+    era * 146097 + doe as i64 - 719468
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(secs: u64) -> DateTime {
+        DateTime::from(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn epoch() {
+        assert_eq!(DateTime{ year: 1970, month: 1, day: 1,
+                             hour: 0, minute: 0, second: 0 }, at(0));
+        assert_eq!("1970-01-01T00:00:00Z", at(0).rfc3339());
+    }
+
+    #[test]
+    fn before_epoch() {
+        let time = UNIX_EPOCH - Duration::from_secs(1);
+        assert_eq!(at(0), DateTime::from(time));
+    }
+
+    #[test]
+    fn rfc3339() {
+        assert_eq!("2016-05-17T15:49:06Z", at(1463500146).rfc3339());
+        // Leap day.
+        assert_eq!("2020-02-29T23:59:59Z", at(1583020799).rfc3339());
+        assert_eq!("2020-03-01T00:00:00Z", at(1583020800).rfc3339());
+        // End of year.
+        assert_eq!("1999-12-31T23:59:59Z", at(946684799).rfc3339());
+        assert_eq!("2000-01-01T00:00:00Z", at(946684800).rfc3339());
+    }
+
+    #[test]
+    fn civil_from_days_round_trip() {
+        assert_eq!((1970, 1, 1), civil_from_days(0));
+        assert_eq!((1969, 12, 31), civil_from_days(-1));
+        assert_eq!((2000, 3, 1), civil_from_days(11017));
+        assert_eq!((2100, 2, 28), civil_from_days(47540));
+        assert_eq!((2100, 3, 1), civil_from_days(47541));
+        for days in -1000..100_000 {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days, days_from_civil(year, month, day));
+        }
+    }
+
+    #[test]
+    fn http_date() {
+        assert_eq!("Thu, 01 Jan 1970 00:00:00 GMT", at(0).http_date());
+        assert_eq!("Sun, 06 Nov 1994 08:49:37 GMT", at(784111777).http_date());
+        assert_eq!("Sat, 29 Feb 2020 23:59:59 GMT", at(1583020799).http_date());
+    }
+
+    #[test]
+    fn from_http_date() {
+        let expected = Some(at(784111777));
+        assert_eq!(expected, DateTime::from_http_date("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert_eq!(expected, DateTime::from_http_date("Sunday, 06-Nov-94 08:49:37 GMT"));
+        assert_eq!(expected, DateTime::from_http_date("Sun Nov  6 08:49:37 1994"));
+        assert_eq!(Some(at(1583020799)),
+                   DateTime::from_http_date("Saturday, 29-Feb-20 23:59:59 GMT"));
+        for &secs in &[0, 951782400, 1583020799, 4102444800] {
+            assert_eq!(Some(at(secs)), DateTime::from_http_date(&at(secs).http_date()));
+        }
+    }
+
+    #[test]
+    fn from_invalid_http_date() {
+        for value in &["", "yesterday", "Sun, 06 Nov 1994 08:49:37",
+                       "Sun, 06 Nov 1994 08:49:37 UTC", "Sun, 6 Nov 1994 08:49:37 GMT",
+                       "Sun, 06 November 1994 08:49:37 GMT", "Sun, 06 Nov 94 08:49:37 GMT",
+                       "Sun, 06 Nov 1994 8:49:37 GMT", "Sun, 06 Nov 1994 08:49 GMT",
+                       "Sun, 06 Nov 1994 08:49:37:00 GMT", "Sun, 31 Nov 1994 08:49:37 GMT",
+                       "Sun, 29 Feb 1900 08:49:37 GMT", "Sun, 06 Nov 1994 24:00:00 GMT",
+                       "Sun, 00 Nov 1994 08:49:37 GMT", "Sun, +6 Nov 1994 08:49:37 GMT",
+                       "Sunday, 06-Nov-1994 08:49:37 GMT", "Sun Nov 06 08:49:37 94"] {
+            assert_eq!(None, DateTime::from_http_date(value), "{}", value);
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(at(0) < at(1));
+        assert!(at(59) < at(60));
+        assert!(at(86399) < at(86400));
+        assert!(at(1583020799) < at(1583020800));
+    }
+}
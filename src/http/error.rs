@@ -0,0 +1,203 @@
+use std::fmt;
+use std::error::Error as StdError;
+use std::io::Error as IoError;
+use std::io::ErrorKind as IoErrorKind;
+use super::StatusCode;
+
+// What went wrong, which decides how the peer is answered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+    StartLine,          // Malformed request or status line.
+    Header,             // Malformed header field, or one with an invalid value.
+    Body,               // Malformed body framing.
+    UnsupportedVersion,
+    StartLineTooLong,
+    HeaderTooLarge,
+    BodyTooLarge,
+    UnsupportedCoding,  // A content coding which can't be decoded.
+    Io,
+    Timeout,
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    kind: ErrorKind,
+    description: &'static str,
+    // Where in the message the error was found, if known.
+    offset: Option<usize>,
+    source: Option<Box<dyn StdError + Send + Sync>>,
+}
+
+impl ParseError {
+    pub fn new(kind: ErrorKind, description: &'static str) -> ParseError {
+        ParseError{ kind, description, offset: None, source: None }
+    }
+    pub fn err<T>(kind: ErrorKind, description: &'static str) -> Result<T, ParseError> {
+        Err(ParseError::new(kind, description))
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    pub fn at(mut self, offset: usize) -> ParseError {
+        self.offset = Some(offset);
+        self
+    }
+
+    // Moves the offset of an error found in part of a message, such as a
+    // single line, to be relative to whatever that part was found in.
+    pub fn offset_by(mut self, start: usize) -> ParseError {
+        self.offset = Some(start + self.offset.unwrap_or(0));
+        self
+    }
+
+    // The status of the response to a request which failed to parse.
+    pub fn status_code(&self) -> StatusCode {
+        match self.kind {
+            ErrorKind::StartLine |
+            ErrorKind::Header |
+            ErrorKind::Body |
+            ErrorKind::Io => StatusCode::BadRequest,
+            ErrorKind::UnsupportedVersion => StatusCode::HTTPVersionNotSupported,
+            // Most of a request line is its target.
+            ErrorKind::StartLineTooLong => StatusCode::URITooLong,
+            ErrorKind::HeaderTooLarge => StatusCode::RequestHeaderFieldsTooLarge,
+            ErrorKind::BodyTooLarge => StatusCode::PayloadTooLarge,
+            ErrorKind::UnsupportedCoding => StatusCode::UnsupportedMediaType,
+            ErrorKind::Timeout => StatusCode::RequestTimeout,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.offset {
+            Some(offset) => f.pad(&format!("HTTP parsing error: {} at byte {}",
+                                           self.description, offset)),
+            None => f.pad(&format!("HTTP parsing error: {}", self.description)),
+        }
+    }
+}
+
+impl StdError for ParseError {
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source.as_ref().map(|source| &**source as &(dyn StdError + 'static))
+    }
+}
+
+impl From<IoError> for ParseError {
+    fn from(error: IoError) -> ParseError {
+        // Readers which decode the message wrap their own parse errors.
+        if error.get_ref().is_some_and(|inner| inner.is::<ParseError>()) {
+            let inner = error.into_inner().expect("Inner error checked");
+            return *inner.downcast::<ParseError>().expect("Inner error type checked");
+        }
+        let (kind, description) = match error.kind() {
+            IoErrorKind::WouldBlock |
+            IoErrorKind::TimedOut => (ErrorKind::Timeout, "Timed out reading from connection"),
+            _ => (ErrorKind::Io, "Failed to read from connection"),
+        };
+        ParseError{ kind, description, offset: None, source: Some(Box::new(error)) }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(deprecated)]
+    fn assert_error_eq(desc: &str, error: &ParseError) {
+        assert_eq!(desc, error.description);
+        assert_eq!(desc, error.description());
+    }
+
+    #[test]
+    fn new() {
+        let desc = "hello world";
+        let error = ParseError::new(ErrorKind::Header, desc);
+        assert_error_eq(desc, &error);
+        assert_eq!(ErrorKind::Header, error.kind());
+        assert!(error.source().is_none());
+    }
+
+    #[test]
+    fn err() {
+        let desc = "hello world";
+        let result = ParseError::err::<String>(ErrorKind::Header, desc);
+        assert!(result.is_err());
+        assert_error_eq(desc, &result.unwrap_err());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!("HTTP parsing error: hello world",
+            format!("{}", ParseError::new(ErrorKind::StartLine, "hello world")));
+    }
+
+    #[test]
+    fn offsets() {
+        let error = ParseError::new(ErrorKind::Header, "hello world");
+        assert_eq!(None, error.offset());
+        let error = error.at(3);
+        assert_eq!(Some(3), error.offset());
+        assert_eq!("HTTP parsing error: hello world at byte 3", format!("{}", error));
+        assert_eq!(Some(13), error.offset_by(10).offset());
+        assert_eq!(Some(10), ParseError::new(ErrorKind::Header, "").offset_by(10).offset());
+    }
+
+    #[test]
+    fn from_std_io_error() {
+        let payload = "foo";
+        let io_err = IoError::new(IoErrorKind::ConnectionReset, payload);
+        let pa_err = ParseError::from(io_err);
+        assert_error_eq("Failed to read from connection", &pa_err);
+        assert_eq!(ErrorKind::Io, pa_err.kind());
+        assert_eq!("foo", format!("{}", pa_err.source().unwrap()));
+    }
+
+    #[test]
+    fn from_timed_out() {
+        for kind in &[IoErrorKind::WouldBlock, IoErrorKind::TimedOut] {
+            let pa_err = ParseError::from(IoError::from(*kind));
+            assert_eq!(ErrorKind::Timeout, pa_err.kind());
+            assert_eq!(StatusCode::RequestTimeout, pa_err.status_code());
+            assert!(pa_err.source().is_some());
+        }
+    }
+
+    #[test]
+    fn from_wrapped_parse_error() {
+        let io_err = IoError::new(IoErrorKind::InvalidData,
+                                  ParseError::new(ErrorKind::Body, "foo"));
+        let pa_err = ParseError::from(io_err);
+        assert_error_eq("foo", &pa_err);
+        assert_eq!(ErrorKind::Body, pa_err.kind());
+    }
+
+    #[test]
+    fn status_codes() {
+        let status = |kind| ParseError::new(kind, "").status_code();
+        assert_eq!(StatusCode::BadRequest, status(ErrorKind::StartLine));
+        assert_eq!(StatusCode::BadRequest, status(ErrorKind::Header));
+        assert_eq!(StatusCode::BadRequest, status(ErrorKind::Body));
+        assert_eq!(StatusCode::BadRequest, status(ErrorKind::Io));
+        assert_eq!(StatusCode::HTTPVersionNotSupported,
+                   status(ErrorKind::UnsupportedVersion));
+        assert_eq!(StatusCode::URITooLong, status(ErrorKind::StartLineTooLong));
+        assert_eq!(StatusCode::RequestHeaderFieldsTooLarge,
+                   status(ErrorKind::HeaderTooLarge));
+        assert_eq!(StatusCode::PayloadTooLarge, status(ErrorKind::BodyTooLarge));
+        assert_eq!(StatusCode::UnsupportedMediaType, status(ErrorKind::UnsupportedCoding));
+        assert_eq!(StatusCode::RequestTimeout, status(ErrorKind::Timeout));
+    }
+}
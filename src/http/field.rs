@@ -0,0 +1,176 @@
+use std::fmt;
+use std::str;
+use super::{Error, ErrorKind};
+use super::headers::is_tchar;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub name:  String,
+    // Values are almost always ASCII, but obs-text is allowed too. What it
+    // means isn't defined, so values are kept as the bytes which were sent.
+    pub value: Vec<u8>,
+}
+
+impl Field {
+    pub fn new(name: String, value: String) -> Field {
+        Field{ name, value: value.into_bytes() }
+    }
+
+    pub fn new_contentlength(length: usize) -> Field {
+        let name = String::from("Content-Length");
+        let value = format!("{}", length).into_bytes();
+        Field{ name, value }
+    }
+
+    pub fn from(line: String) -> Result<Field, Error> {
+        Field::parse(line.as_bytes())
+    }
+
+    // Parses a field from a line, without its line ending, with any obsolete
+    // line folding already replaced. Errors give the offset within the line.
+    pub fn parse(line: &[u8]) -> Result<Field, Error> {
+        // header-field   = field-name ":" OWS field-value OWS
+        // field-name     = token
+        // field-value    = *( field-content / obs-fold )
+        // field-content  = field-vchar [ 1*( SP / HTAB ) field-vchar ]
+        // field-vchar    = VCHAR / obs-text
+        // obs-text       = %x80-FF
+        // obs-fold       = CRLF 1*( SP / HTAB )
+        //                ; obsolete line folding (see Section 3.2.4)
+        let invalid = |offset| Error::new(ErrorKind::Header, "Invalid field").at(offset);
+        // Split by the first colon separator.
+        let sep = line.iter().position(|&b| b == b':')
+            .ok_or_else(|| invalid(line.len()))?;
+        // Parse name. No whitespace is allowed before the colon.
+        if sep == 0 {
+            return Err(invalid(0));
+        }
+        if let Some(offset) = line[..sep].iter().position(|&b| !is_tchar(b as char)) {
+            return Err(invalid(offset));
+        }
+        let name = String::from_utf8(line[..sep].to_vec()).expect("Tokens are ASCII");
+        // Parse value. Values must have leading/trailing whitespace removed.
+        let is_ows = |b: &u8| *b == b' ' || *b == b'\t';
+        let value = &line[sep+1..];
+        let leading = value.iter().take_while(|b| is_ows(b)).count();
+        let trailing = value[leading..].iter().rev().take_while(|b| is_ows(b)).count();
+        let start = sep + 1 + leading;
+        let value = &value[leading..value.len() - trailing];
+        let is_content = |b: &u8| is_ows(b) || (b'!'..=b'~').contains(b) || *b >= 0x80;
+        if let Some(offset) = value.iter().position(|b| !is_content(b)) {
+            return Err(invalid(start + offset));
+        }
+
+        Ok(Field{ name, value: value.to_vec() })
+    }
+
+    // The value as text, if it is.
+    pub fn value_str(&self) -> Option<&str> {
+        str::from_utf8(&self.value).ok()
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(&format!("{}: {}", self.name, String::from_utf8_lossy(&self.value)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+    use proptest::prelude::*;
+
+    fn assert_field_eq(name: &str, value: &str, actual: &Field) {
+        assert_eq!(String::from(name), actual.name);
+        assert_eq!(value.as_bytes(), actual.value.as_slice());
+    }
+
+    #[test]
+    fn new() {
+        let field = Field::new(String::from("foo"), String::from("bar"));
+        assert_field_eq("foo", "bar", &field);
+    }
+
+    #[test]
+    fn new_contentlength() {
+        let field = Field::new_contentlength(42);
+        assert_field_eq("Content-Length", "42", &field);
+    }
+
+    #[test]
+    fn from_empty_string() {
+        let result = Field::from(String::from(""));
+        assert_parse_error("HTTP parsing error: Invalid field at byte 0", result);
+    }
+
+    #[test]
+    fn from_invalid_name_string() {
+        let result = Field::from(String::from("Invalid Foo: Bar"));
+        assert_parse_error("HTTP parsing error: Invalid field at byte 7", result);
+    }
+
+    #[test]
+    fn from_invalid_value_string() {
+        let result = Field::from(String::from("Foo: Invalid\nBar"));
+        assert_parse_error("HTTP parsing error: Invalid field at byte 12", result);
+    }
+
+    #[test]
+    fn from_valid_string() {
+        // Test without whitespace.
+        let field = Field::from(String::from("Foo:Bar")).unwrap();
+        assert_field_eq("Foo", "Bar", &field);
+
+        // Test with whitespace which must be removed.
+        let field = Field::from(String::from("Foo: \t bar bug \t ")).unwrap();
+        assert_field_eq("Foo", "bar bug", &field);
+    }
+
+    #[test]
+    fn parse_obs_text() {
+        let field = Field::parse(b"Foo: \xe9t\xe9 \x80").unwrap();
+        assert_eq!(b"\xe9t\xe9 \x80".to_vec(), field.value);
+        assert_eq!(None, field.value_str());
+        assert_eq!("Foo: \u{fffd}t\u{fffd} \u{fffd}", format!("{}", field));
+        assert_eq!(Some("caf\u{e9}"),
+                   Field::parse("Foo: caf\u{e9}".as_bytes()).unwrap().value_str());
+    }
+
+    proptest! {
+        #[test]
+        fn parse_round_trip(field in valid_field(), before in "[ \t]{0,3}", after in "[ \t]{0,3}") {
+            let mut line = format!("{}:{}", field.name, before).into_bytes();
+            line.extend_from_slice(&field.value);
+            line.extend_from_slice(after.as_bytes());
+            prop_assert_eq!(field, Field::parse(&line).unwrap());
+        }
+
+        #[test]
+        fn from_never_panics(line in "\\PC{0,40}") {
+            let _ = Field::from(line);
+        }
+    }
+
+    #[test]
+    fn parse_invalid_offsets() {
+        assert_parse_error("HTTP parsing error: Invalid field at byte 3",
+                Field::parse(b"Foo"));
+        assert_parse_error("HTTP parsing error: Invalid field at byte 0",
+                Field::parse(b": bar"));
+        assert_parse_error("HTTP parsing error: Invalid field at byte 3",
+                Field::parse(b"Foo : bar"));
+        assert_parse_error("HTTP parsing error: Invalid field at byte 1",
+                Field::parse(b"F(o: bar"));
+        assert_parse_error("HTTP parsing error: Invalid field at byte 8",
+                Field::parse(b"Foo: bar\rbaz"));
+    }
+
+    #[test]
+    fn display() {
+        let field = Field::new(String::from("foo"), String::from("bar"));
+        assert_eq!("foo: bar", format!("{}", field));
+    }
+}
\ No newline at end of file
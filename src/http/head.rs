@@ -0,0 +1,483 @@
+use std::cmp;
+use std::io::BufRead;
+use std::marker::PhantomData;
+use std::mem;
+use super::{Error, ErrorKind};
+use super::Field;
+
+// Bounds on what is read from a peer, so that a misbehaving one can't make us
+// buffer without end. Sizes exclude line endings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Limits {
+    pub start_line: usize,
+    pub header_field: usize,
+    // All of the header fields together.
+    pub header_bytes: usize,
+    pub header_count: usize,
+    // Bodies read into memory. Bodies which are streamed elsewhere, such as
+    // uploaded objects, aren't limited.
+    pub body: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits{ start_line: 8 * 1024, header_field: 8 * 1024,
+                header_bytes: 64 * 1024, header_count: 100,
+                body: 16 * 1024 * 1024 }
+    }
+}
+
+// What a head is parsed into, from its start line and header fields.
+pub trait Head: Sized {
+    fn from_head(line: String, fields: Vec<Field>) -> Result<Self, Error>;
+}
+
+// The head as it was sent.
+impl Head for (String, Vec<Field>) {
+    fn from_head(line: String, fields: Vec<Field>) -> Result<Self, Error> {
+        Ok((line, fields))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Parsed<T> {
+    // All of the input was taken, and more is needed.
+    NeedMore,
+    // The head, along with how much of the input it took. Whatever follows
+    // is the body, or the next message.
+    HeadComplete(T, usize),
+}
+
+// Parses the head of a message from input as it arrives, in whatever pieces
+// it arrives in:
+//   HTTP-message   = start-line
+//                    *( header-field CRLF )
+//                    CRLF
+//                    [ message-body ]
+// Lines must end with CRLF. Obsolete line folding is replaced with a single
+// space, as allowed by RFC 7230. Errors give the offset in the message at
+// which they were found. Once a head is complete the parser starts on the
+// next one.
+pub struct HeadParser<T> {
+    limits: Limits,
+    // How much of the message has been taken.
+    offset: usize,
+    // The line being received, and where it started.
+    line: Vec<u8>,
+    line_start: usize,
+    start_line: Option<String>,
+    // The last field's line, which later lines may yet continue.
+    field: Option<FieldLine>,
+    fields: Vec<Field>,
+    header_bytes: usize,
+    head: PhantomData<T>,
+}
+
+struct FieldLine {
+    line: Vec<u8>,
+    // Where each part of a folded line came from, as (index in the line,
+    // offset in the message).
+    parts: Vec<(usize, usize)>,
+}
+
+impl<T: Head> HeadParser<T> {
+    pub fn new(limits: Limits) -> HeadParser<T> {
+        HeadParser{ limits, offset: 0, line: Vec::new(), line_start: 0,
+                    start_line: None, field: None, fields: Vec::new(),
+                    header_bytes: 0, head: PhantomData }
+    }
+
+    // How much of the message has been taken so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn push(&mut self, input: &[u8]) -> Result<Parsed<T>, Error> {
+        let mut taken = 0;
+        while taken < input.len() {
+            // Allow for the CRLF.
+            let limit = 2 + if self.start_line.is_none() {
+                self.limits.start_line
+            } else {
+                self.limits.header_field
+            };
+            let rest = &input[taken..];
+            let rest = &rest[..cmp::min(rest.len(), limit - self.line.len())];
+            let (len, ended) = match rest.iter().position(|&b| b == b'\n') {
+                Some(index) => (index + 1, true),
+                None => (rest.len(), false),
+            };
+            self.line.extend_from_slice(&rest[..len]);
+            self.offset += len;
+            taken += len;
+            if !ended {
+                if self.line.len() == limit {
+                    return Err(self.too_long(self.line_start));
+                }
+                continue;
+            }
+
+            let start = mem::replace(&mut self.line_start, self.offset);
+            let mut line = mem::take(&mut self.line);
+            line.pop();
+            if line.pop() != Some(b'\r') {
+                let kind = if self.start_line.is_none() {
+                    ErrorKind::StartLine
+                } else {
+                    ErrorKind::Header
+                };
+                return Err(Error::new(kind, "Line not ended by CRLF").at(self.offset - 1));
+            }
+            if let Some(head) = self.take_line(line, start)? {
+                return Ok(Parsed::HeadComplete(head, taken));
+            }
+        }
+        Ok(Parsed::NeedMore)
+    }
+
+    fn too_long(&self, start: usize) -> Error {
+        if self.start_line.is_none() {
+            Error::new(ErrorKind::StartLineTooLong, "Start line too long").at(start)
+        } else {
+            Error::new(ErrorKind::HeaderTooLarge, "Header field too large").at(start)
+        }
+    }
+
+    // Takes a whole line, without its CRLF, returning the head if it was the
+    // last line of it.
+    fn take_line(&mut self, line: Vec<u8>, start: usize) -> Result<Option<T>, Error> {
+        if self.start_line.is_none() {
+            // Empty lines before the start line are ignored (RFC 7230 section
+            // 3.5), but no more of them than would fit in one.
+            if line.is_empty() {
+                if self.offset > self.limits.start_line + 2 {
+                    return Err(self.too_long(0));
+                }
+                return Ok(None);
+            }
+            if let Some(index) = line.iter().position(|&b| is_ctl(b)) {
+                return Err(Error::new(ErrorKind::StartLine, "Invalid start line")
+                    .at(start + index));
+            }
+            // Anything which isn't ASCII is invalid in a request line, and
+            // ignored in a status line, so needn't be kept as sent.
+            self.start_line = Some(String::from_utf8_lossy(&line).into_owned());
+            return Ok(None);
+        }
+
+        if line.is_empty() {
+            // header finished.
+            self.take_field()?;
+            let parser = mem::replace(self, HeadParser::new(self.limits.clone()));
+            let line = parser.start_line.expect("The start line comes first");
+            return T::from_head(line, parser.fields).map(Some);
+        }
+        if is_ows(line[0]) {
+            // Folding continues the last field's line with a single space.
+            let field = match self.field {
+                Some(ref mut field) => field,
+                None => {
+                    return Err(Error::new(ErrorKind::Header,
+                            "Whitespace before first field").at(start));
+                },
+            };
+            let leading = line.iter().take_while(|&&b| is_ows(b)).count();
+            field.line.push(b' ');
+            field.parts.push((field.line.len(), start + leading));
+            field.line.extend_from_slice(&line[leading..]);
+            if field.line.len() > self.limits.header_field {
+                return Err(Error::new(ErrorKind::HeaderTooLarge, "Header field too large")
+                    .at(field.parts[0].1));
+            }
+            return Ok(None);
+        }
+        self.take_field()?;
+        self.field = Some(FieldLine{ line, parts: vec![(0, start)] });
+        Ok(None)
+    }
+
+    // Parses the last field's line, now that nothing more can be added to it.
+    fn take_field(&mut self) -> Result<(), Error> {
+        let FieldLine{ line, parts } = match self.field.take() {
+            Some(field) => field,
+            None => return Ok(()),
+        };
+        let start = parts[0].1;
+        self.header_bytes += line.len();
+        if self.header_bytes > self.limits.header_bytes {
+            return Err(Error::new(ErrorKind::HeaderTooLarge, "Header too large").at(start));
+        }
+        if self.fields.len() == self.limits.header_count {
+            return Err(Error::new(ErrorKind::HeaderTooLarge, "Too many header fields")
+                .at(start));
+        }
+        let field = Field::parse(&line).map_err(|error| {
+            let index = error.offset().unwrap_or(0);
+            let &(part_index, part_offset) = parts.iter().rev()
+                .find(|part| part.0 <= index)
+                .expect("The first part starts the line");
+            error.at(part_offset + index - part_index)
+        })?;
+        self.fields.push(field);
+        Ok(())
+    }
+}
+
+// Reads the head of a message, leaving the reader at the start of its body.
+pub fn read_head<T: Head, B: BufRead>(reader: &mut B, limits: &Limits)
+        -> Result<T, Error> {
+    let mut parser = HeadParser::new(limits.clone());
+    loop {
+        let (head, len) = {
+            let input = reader.fill_buf()?;
+            if input.is_empty() {
+                return Err(Error::new(ErrorKind::Io, "Unexpected end of stream")
+                    .at(parser.offset()));
+            }
+            match parser.push(input)? {
+                Parsed::NeedMore => (None, input.len()),
+                Parsed::HeadComplete(head, len) => (Some(head), len),
+            }
+        };
+        reader.consume(len);
+        if let Some(head) = head {
+            return Ok(head);
+        }
+    }
+}
+
+fn is_ows(b: u8) -> bool {
+    b == b' ' || b == b'\t'
+}
+
+fn is_ctl(b: u8) -> bool {
+    (b < b' ' && b != b'\t') || b == 0x7f
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+    use super::super::{Headers, Method, Request, Response, StatusCode};
+
+    fn limits() -> Limits {
+        Limits{ start_line: 20, header_field: 10, header_bytes: 25, header_count: 3,
+                body: 0 }
+    }
+
+    fn parse(head: &str) -> Result<(String, Vec<Field>), Error> {
+        read_head(&mut StringReader::new(head), &limits())
+    }
+
+    fn assert_kind(kind: ErrorKind, result: Result<(String, Vec<Field>), Error>) {
+        assert_eq!(kind, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn within_limits() {
+        let (line, fields) = parse("GET /12345 HTTP/1.1\r\n\
+                                    A: 1234567\r\n\
+                                    B: 1\r\n\
+                                    C: 1\r\n\
+                                    \r\n").unwrap();
+        assert_eq!("GET /12345 HTTP/1.1", line);
+        assert_eq!(3, fields.len());
+        assert_eq!(Field::new(String::from("A"), String::from("1234567")), fields[0]);
+    }
+
+    #[test]
+    fn start_line_too_long() {
+        assert_kind(ErrorKind::StartLineTooLong,
+                    parse("GET /123456789 HTTP/1.1\r\n\r\n"));
+        // Without an end in sight.
+        assert_kind(ErrorKind::StartLineTooLong,
+                    parse(&"GET /".repeat(1000)));
+    }
+
+    #[test]
+    fn header_field_too_large() {
+        assert_parse_error("HTTP parsing error: Header field too large at byte 16",
+                parse("GET / HTTP/1.1\r\nA: 12345678\r\n\r\n"));
+    }
+
+    #[test]
+    fn header_too_large() {
+        assert_parse_error("HTTP parsing error: Header too large at byte 40",
+                parse("GET / HTTP/1.1\r\nA: 1234567\r\nB: 1234567\r\nC: 1234567\r\n\r\n"));
+    }
+
+    #[test]
+    fn too_many_header_fields() {
+        assert_parse_error("HTTP parsing error: Too many header fields at byte 34",
+                parse("GET / HTTP/1.1\r\nA: 1\r\nB: 1\r\nC: 1\r\nD: 1\r\n\r\n"));
+    }
+
+    #[test]
+    fn truncated() {
+        assert_parse_error("HTTP parsing error: Unexpected end of stream at byte 22",
+                parse("GET / HTTP/1.1\r\nA: 1\r\n"));
+        assert_parse_error("HTTP parsing error: Unexpected end of stream at byte 0",
+                parse(""));
+    }
+
+    #[test]
+    fn leading_empty_lines() {
+        let (line, fields) = parse("\r\n\r\nGET / HTTP/1.1\r\nA: 1\r\n\r\n").unwrap();
+        assert_eq!("GET / HTTP/1.1", line);
+        assert_eq!(1, fields.len());
+        assert_parse_error("HTTP parsing error: Line not ended by CRLF at byte 0",
+                parse("\nGET / HTTP/1.1\r\n\r\n"));
+        assert_kind(ErrorKind::StartLineTooLong,
+                    parse(&format!("{}GET / HTTP/1.1\r\n\r\n", "\r\n".repeat(20))));
+    }
+
+    #[test]
+    fn obs_text() {
+        let mut input = &b"GET / HTTP/1.1\r\nA: caf\xe9\r\n\r\n"[..];
+        let (_, fields) = read_head(&mut input, &Limits::default()).unwrap();
+        assert_eq!(b"caf\xe9".to_vec(), fields[0].value);
+        assert_eq!(None, fields[0].value_str());
+    }
+
+    #[test]
+    fn bare_lf() {
+        assert_parse_error("HTTP parsing error: Line not ended by CRLF at byte 14",
+                parse("GET / HTTP/1.1\n\r\n"));
+        assert_parse_error("HTTP parsing error: Line not ended by CRLF at byte 20",
+                parse("GET / HTTP/1.1\r\nA: 1\n\r\n"));
+        assert_parse_error("HTTP parsing error: Line not ended by CRLF at byte 16",
+                parse("GET / HTTP/1.1\r\n\n"));
+    }
+
+    #[test]
+    fn invalid_start_line() {
+        assert_parse_error("HTTP parsing error: Invalid start line at byte 3",
+                parse("GET\0/ HTTP/1.1\r\n\r\n"));
+        assert_parse_error("HTTP parsing error: Invalid start line at byte 5",
+                parse("GET /\r HTTP/1.1\r\n\r\n"));
+    }
+
+    #[test]
+    fn obs_fold() {
+        let (_, fields) = read_head(&mut StringReader::new("\
+            GET / HTTP/1.1\r\n\
+            A: one\r\n \
+            two\r\n\
+            \t\t three\r\n\
+            B: 1\r\n\
+            \r\n"), &Limits::default()).unwrap();
+        assert_eq!(vec![Field::new(String::from("A"), String::from("one two three")),
+                        Field::new(String::from("B"), String::from("1"))],
+                   fields);
+
+        // The folded line is limited as a whole.
+        assert_parse_error("HTTP parsing error: Header field too large at byte 16",
+                parse("GET / HTTP/1.1\r\nA: 12345\r\n 6789\r\n\r\n"));
+        // Errors are found where they were sent.
+        assert_parse_error("HTTP parsing error: Invalid field at byte 25",
+                parse("GET / HTTP/1.1\r\nA: 1\r\n  2\x7f\r\n\r\n"));
+    }
+
+    #[test]
+    fn whitespace_before_first_field() {
+        assert_parse_error("HTTP parsing error: Whitespace before first field at byte 16",
+                parse("GET / HTTP/1.1\r\n A: 1\r\n\r\n"));
+    }
+
+    const REQUEST: &[u8] = b"\
+        POST /a/b HTTP/1.1\r\n\
+        Host: example.com\r\n\
+        X-Folded: one\r\n two\r\n\
+        Content-Length: 5\r\n\
+        \r\n\
+        hello";
+    const HEAD_LENGTH: usize = 81;
+
+    // Pushes input in pieces of the given size, returning the head and the
+    // input which followed it.
+    fn push_pieces<T: Head>(input: &[u8], size: usize) -> Result<(T, Vec<u8>), Error> {
+        let mut parser = HeadParser::new(Limits::default());
+        let mut pieces = input.chunks(size);
+        for piece in &mut pieces {
+            if let Parsed::HeadComplete(head, len) = parser.push(piece)? {
+                let mut rest = piece[len..].to_vec();
+                rest.extend(pieces.flatten());
+                return Ok((head, rest));
+            }
+        }
+        panic!("Head not complete");
+    }
+
+    #[test]
+    fn push_in_pieces() {
+        let expected = Request::parse(&mut &REQUEST[..]).unwrap();
+        for size in 1..REQUEST.len() + 1 {
+            let (request, rest) = push_pieces::<Request>(REQUEST, size).unwrap();
+            assert_eq!(expected, request);
+            assert_eq!(b"hello".to_vec(), rest);
+        }
+        assert_eq!(Some("one two"), expected.header("X-Folded"));
+    }
+
+    #[test]
+    fn push_need_more() {
+        let mut parser = HeadParser::<Request>::new(Limits::default());
+        for (index, &b) in REQUEST[..HEAD_LENGTH - 1].iter().enumerate() {
+            assert_eq!(Parsed::NeedMore, parser.push(&[b]).unwrap());
+            assert_eq!(index + 1, parser.offset());
+        }
+        match parser.push(&REQUEST[HEAD_LENGTH - 1..]).unwrap() {
+            Parsed::HeadComplete(request, len) => {
+                assert_eq!(1, len);
+                assert_eq!(&Method::POST, request.method());
+                assert_eq!(Some(5), request.content_length().unwrap());
+            },
+            Parsed::NeedMore => panic!("Head not complete"),
+        }
+        assert_eq!(Parsed::NeedMore, parser.push(&[]).unwrap());
+    }
+
+    #[test]
+    fn push_pipelined() {
+        let input = b"GET /a HTTP/1.1\r\n\r\nGET /b HTTP/1.1\r\n\r\n";
+        let mut parser = HeadParser::<Request>::new(Limits::default());
+        let first = match parser.push(input).unwrap() {
+            Parsed::HeadComplete(request, len) => {
+                assert_eq!(19, len);
+                request
+            },
+            Parsed::NeedMore => panic!("Head not complete"),
+        };
+        assert_eq!("/a", first.target());
+        // The parser starts again on the next head.
+        assert_eq!(0, parser.offset());
+        match parser.push(&input[19..]).unwrap() {
+            Parsed::HeadComplete(request, _) => assert_eq!("/b", request.target()),
+            Parsed::NeedMore => panic!("Head not complete"),
+        }
+    }
+
+    #[test]
+    fn push_response() {
+        let input = b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n";
+        for size in 1..input.len() + 1 {
+            let (response, rest) = push_pieces::<Response>(input, size).unwrap();
+            assert_eq!(&StatusCode::NotFound, response.status());
+            assert!(rest.is_empty());
+        }
+    }
+
+    #[test]
+    fn push_errors() {
+        // Errors are the same however the input arrives.
+        let input = b"GET / HTTP/1.1\r\nA: 1\r\nB : 2\r\n\r\n";
+        for size in 1..input.len() + 1 {
+            assert_parse_error("HTTP parsing error: Invalid field at byte 23",
+                    push_pieces::<Request>(input, size));
+        }
+        let mut parser = HeadParser::<Request>::new(limits());
+        assert_parse_error("HTTP parsing error: Start line too long at byte 0",
+                parser.push(&[b'a'; 100]));
+    }
+}
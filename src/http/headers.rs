@@ -0,0 +1,676 @@
+use std::cmp;
+use super::{Error, ErrorKind};
+use super::ContentCoding;
+use super::DateTime;
+use super::Field;
+
+// tchar = "!" / "#" / "$" / "%" / "&" / "'" / "*" / "+" / "-" / "." /
+//         "^" / "_" / "`" / "|" / "~" / DIGIT / ALPHA
+pub fn is_tchar(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+pub fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(is_tchar)
+}
+
+// Values which aren't text can't be interpreted, but mustn't be mistaken for
+// missing ones either, so are replaced with something which is never valid.
+fn value_str(field: &Field) -> &str {
+    field.value_str().unwrap_or("\u{fffd}")
+}
+
+// Splits a comma-separated list, ignoring commas within quoted strings and
+// empty elements.
+fn split_list(value: &str) -> Vec<&str> {
+    let mut elements = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                elements.push(value[start..index].trim());
+                start = index + 1;
+            },
+            _ => {},
+        }
+    }
+    elements.push(value[start..].trim());
+    elements.retain(|element| !element.is_empty());
+    elements
+}
+
+// A media type such as application/vnd.git-lfs+json; charset=utf-8, or a
+// media range as used by Accept such as text/*. Type, subtype and parameter
+// names are case-insensitive so are held in lowercase.
+#[derive(Debug, PartialEq)]
+pub struct MediaType {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+}
+
+impl MediaType {
+    pub fn parse(value: &str) -> Result<MediaType, Error> {
+        // media-type = type "/" subtype *( OWS ";" OWS parameter )
+        // parameter  = parameter-name "=" parameter-value
+        // parameter-value = ( token / quoted-string )
+        let mut parts = value.splitn(2, ';');
+        let name = parts.next().unwrap_or("").trim();
+        let valid_name = name.find('/')
+            .is_some_and(|sep| is_token(&name[..sep]) && is_token(&name[sep+1..]));
+        if !valid_name {
+            return Error::err(ErrorKind::Header, "Invalid media type");
+        }
+
+        let mut params = Vec::new();
+        let mut rest = parts.next().unwrap_or("");
+        while !rest.trim().is_empty() {
+            let sep = rest.find('=').ok_or(Error::new(ErrorKind::Header, "Invalid media type"))?;
+            let param_name = rest[..sep].trim();
+            if !is_token(param_name) {
+                return Error::err(ErrorKind::Header, "Invalid media type");
+            }
+            let (param_value, remainder) = parse_param_value(&rest[sep+1..])?;
+            params.push((param_name.to_ascii_lowercase(), param_value));
+            rest = remainder;
+        }
+
+        Ok(MediaType{ name: name.to_ascii_lowercase(), params })
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter()
+            .find(|param| param.0.eq_ignore_ascii_case(name))
+            .map(|param| param.1.as_str())
+    }
+
+    // Whether this media range matches the given media type.
+    pub fn matches(&self, media_type: &str) -> bool {
+        let media_type = media_type.to_ascii_lowercase();
+        match self.name.as_str() {
+            "*/*" => true,
+            range if range.ends_with("/*") => {
+                media_type.starts_with(&range[..range.len() - 1])
+            },
+            range => range == media_type,
+        }
+    }
+}
+
+// Parses a parameter value (a token or quoted-string) along with the ";"
+// which follows, returning the value and the rest of the parameters.
+fn parse_param_value(input: &str) -> Result<(String, &str), Error> {
+    let input = input.trim_start();
+    let (value, rest) = if let Some(quoted) = input.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+        let end = loop {
+            match chars.next() {
+                Some((index, '"')) => break index + 1,
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, c)) => value.push(c),
+                    None => return Error::err(ErrorKind::Header, "Invalid media type"),
+                },
+                Some((_, c)) => value.push(c),
+                None => return Error::err(ErrorKind::Header, "Invalid media type"),
+            }
+        };
+        (value, &quoted[end..])
+    } else {
+        let end = input.find(';').unwrap_or(input.len());
+        let token = input[..end].trim_end();
+        if !is_token(token) {
+            return Error::err(ErrorKind::Header, "Invalid media type");
+        }
+        (String::from(token), &input[end..])
+    };
+    let rest = rest.trim_start();
+    match rest.strip_prefix(';') {
+        Some(rest) => Ok((value, rest)),
+        None if rest.is_empty() => Ok((value, rest)),
+        None => Error::err(ErrorKind::Header, "Invalid media type"),
+    }
+}
+
+// The credentials given with Authorization: <scheme> <credentials>
+#[derive(Debug, PartialEq)]
+pub struct Authorization<'a> {
+    pub scheme: &'a str,
+    pub credentials: &'a str,
+}
+
+// Whether an entity tag is among those listed, or the list is "*". Weak tags
+// only match with weak comparison.
+fn entity_tag_listed(list: &[&str], tag: &str, weak: bool) -> bool {
+    fn opaque(tag: &str) -> &str {
+        tag.strip_prefix("W/").unwrap_or(tag)
+    }
+    list.iter().any(|listed| {
+        *listed == "*" || *listed == tag || (weak && opaque(listed) == opaque(tag))
+    })
+}
+
+// A range of bytes asked for with Range: bytes=first-last, bytes=first- or
+// bytes=-suffix.
+#[derive(Debug, PartialEq)]
+pub enum ByteRange {
+    FromTo(u64, Option<u64>),
+    Suffix(u64),
+}
+
+impl ByteRange {
+    pub fn parse(spec: &str) -> Result<ByteRange, Error> {
+        // range-spec   = int-range / suffix-range
+        // int-range    = first-pos "-" [ last-pos ]
+        // suffix-range = "-" suffix-length
+        let invalid = || Error::new(ErrorKind::Header, "Invalid range");
+        let number = |digits: &str| {
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            digits.parse::<u64>().map_err(|_| invalid())
+        };
+        let sep = spec.find('-').ok_or_else(invalid)?;
+        let (first, last) = (&spec[..sep], &spec[sep+1..]);
+        if first.is_empty() {
+            return Ok(ByteRange::Suffix(number(last)?));
+        }
+        let first = number(first)?;
+        if last.is_empty() {
+            return Ok(ByteRange::FromTo(first, None));
+        }
+        let last = number(last)?;
+        if last < first {
+            return Err(invalid());
+        }
+        Ok(ByteRange::FromTo(first, Some(last)))
+    }
+
+    // The first and last byte this selects of a representation of the given
+    // length, or None if it selects none of it.
+    pub fn resolve(&self, length: u64) -> Option<(u64, u64)> {
+        match *self {
+            ByteRange::FromTo(first, _) if first >= length => None,
+            ByteRange::FromTo(first, last) => {
+                Some((first, last.map_or(length - 1, |last| cmp::min(last, length - 1))))
+            },
+            ByteRange::Suffix(suffix) if suffix == 0 || length == 0 => None,
+            ByteRange::Suffix(suffix) => Some((length - cmp::min(suffix, length), length - 1)),
+        }
+    }
+}
+
+// Typed access to the header fields of a message. Field names are
+// case-insensitive. Fields which may only appear once are rejected if they
+// are repeated with different values.
+pub trait Headers {
+    fn fields(&self) -> &[Field];
+
+    // The value of the first field with the name.
+    fn header(&self, name: &str) -> Option<&str> {
+        self.fields().iter()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+            .map(value_str)
+    }
+
+    // The values of every field with the name, in order.
+    fn headers(&self, name: &str) -> Vec<&str> {
+        self.fields().iter()
+            .filter(|field| field.name.eq_ignore_ascii_case(name))
+            .map(value_str)
+            .collect()
+    }
+
+    // The elements of a comma-separated list, which may be split across
+    // several fields with the name.
+    fn header_list(&self, name: &str) -> Vec<&str> {
+        self.headers(name).into_iter().flat_map(split_list).collect()
+    }
+
+    fn content_length(&self) -> Result<Option<usize>, Error> {
+        // Content-Length = 1*DIGIT
+        // A list of identical lengths is allowed, as some intermediaries
+        // combine repeated fields that way.
+        let mut length = None;
+        for value in self.header_list("Content-Length") {
+            if !value.bytes().all(|b| b.is_ascii_digit()) {
+                return Error::err(ErrorKind::Header, "Invalid content length");
+            }
+            let value = value.parse()
+                .map_err(|_| Error::new(ErrorKind::Header, "Invalid content length"))?;
+            if length.is_some_and(|length| length != value) {
+                return Error::err(ErrorKind::Header, "Conflicting content lengths");
+            }
+            length = Some(value);
+        }
+        if length.is_none() && !self.headers("Content-Length").is_empty() {
+            return Error::err(ErrorKind::Header, "Invalid content length");
+        }
+        Ok(length)
+    }
+
+    fn content_type(&self) -> Result<Option<MediaType>, Error> {
+        match self.headers("Content-Type").as_slice() {
+            [] => Ok(None),
+            [value] => MediaType::parse(value).map(Some),
+            _ => Error::err(ErrorKind::Header, "Multiple Content-Type fields"),
+        }
+    }
+
+    // The transfer codings applied, in the order they were applied.
+    fn transfer_encoding(&self) -> Vec<String> {
+        self.header_list("Transfer-Encoding").into_iter()
+            .map(str::to_ascii_lowercase)
+            .collect()
+    }
+
+    fn connection(&self) -> Vec<String> {
+        self.header_list("Connection").into_iter()
+            .map(str::to_ascii_lowercase)
+            .collect()
+    }
+
+    // The content codings applied to the body, in the order they were
+    // applied. Ones which aren't known can't be undone, so are errors.
+    fn content_encoding(&self) -> Result<Vec<ContentCoding>, Error> {
+        self.header_list("Content-Encoding").into_iter()
+            .map(|name| ContentCoding::from(name)
+                .ok_or(Error::new(ErrorKind::UnsupportedCoding, "Unsupported content coding")))
+            .filter(|coding| coding.as_ref().ok() != Some(&ContentCoding::Identity))
+            .collect()
+    }
+
+    // The content coding most wanted by Accept-Encoding, of those which can
+    // be applied. Identity is always acceptable, but only preferred if asked
+    // for: it's what is sent without Accept-Encoding.
+    fn preferred_coding(&self) -> ContentCoding {
+        // Accept-Encoding = #( codings [ weight ] )
+        // codings         = content-coding / "identity" / "*"
+        // weight          = OWS ";" OWS "q=" qvalue
+        let weights: Vec<(&str, f32)> = self.header_list("Accept-Encoding").into_iter()
+            .map(|element| {
+                let mut parts = element.split(';');
+                let name = parts.next().unwrap_or("").trim();
+                let q = parts.map(str::trim)
+                    .find(|param| param.starts_with("q=") || param.starts_with("Q="))
+                    .and_then(|param| param[2..].parse().ok())
+                    .unwrap_or(1.0);
+                (name, q)
+            })
+            .collect();
+        let named = |coding: ContentCoding| {
+            weights.iter()
+                .find(|&&(name, _)| ContentCoding::from(name) == Some(coding))
+                .map(|&(_, q)| q)
+        };
+        let any = weights.iter().find(|&&(name, _)| name == "*").map(|&(_, q)| q);
+        let identity = named(ContentCoding::Identity).map_or(f32::MIN_POSITIVE, |q| q.max(0.0));
+        [ContentCoding::Gzip, ContentCoding::Deflate].iter()
+            .filter_map(|&coding| named(coding).or(any).map(|q| (coding, q)))
+            .fold((ContentCoding::Identity, identity), |best, (coding, q)| {
+                if q > best.1 { (coding, q) } else { best }
+            })
+            .0
+    }
+
+    // The expectations of the request, such as 100-continue.
+    fn expect(&self) -> Vec<String> {
+        self.header_list("Expect").into_iter()
+            .map(str::to_ascii_lowercase)
+            .collect()
+    }
+
+    fn authorization(&self) -> Option<Authorization<'_>> {
+        // Authorization = auth-scheme [ 1*SP ( token68 / #auth-param ) ]
+        let value = self.header("Authorization")?;
+        let (scheme, credentials) = match value.find(' ') {
+            Some(sep) => (&value[..sep], value[sep..].trim()),
+            None => (value, ""),
+        };
+        if is_token(scheme) {
+            Some(Authorization{ scheme, credentials })
+        } else {
+            None
+        }
+    }
+
+    // The media ranges accepted, in the order given.
+    fn accept(&self) -> Result<Vec<MediaType>, Error> {
+        self.header_list("Accept").into_iter().map(MediaType::parse).collect()
+    }
+
+    // Whether the media type is acceptable. Anything is if there's no Accept
+    // field, while a range with a q of 0 excludes those types.
+    fn accepts(&self, media_type: &str) -> bool {
+        let ranges = match self.accept() {
+            Ok(ranges) => ranges,
+            Err(_) => return true,
+        };
+        if ranges.is_empty() {
+            return true;
+        }
+        ranges.iter()
+            .filter(|range| range.matches(media_type))
+            // The most specific range applies.
+            .max_by_key(|range| {
+                let specificity = match range.name.as_str() {
+                    "*/*" => 0,
+                    name if name.ends_with("/*") => 1,
+                    _ => 2,
+                };
+                (specificity, range.params.iter().filter(|param| param.0 != "q").count())
+            })
+            .is_some_and(|range| range.param("q")
+                .and_then(|q| q.parse::<f32>().ok())
+                .is_none_or(|q| q > 0.0))
+    }
+
+    // The byte ranges asked for, in the order given. Ranges in other units
+    // aren't understood, so are treated as if there were none.
+    fn range(&self) -> Result<Option<Vec<ByteRange>>, Error> {
+        // Range = ranges-specifier
+        // ranges-specifier = range-unit "=" range-set
+        // range-set = 1#range-spec
+        let value = match self.headers("Range").as_slice() {
+            [] => return Ok(None),
+            [value] => *value,
+            _ => return Error::err(ErrorKind::Header, "Multiple Range fields"),
+        };
+        let sep = value.find('=').ok_or(Error::new(ErrorKind::Header, "Invalid range"))?;
+        if !value[..sep].trim().eq_ignore_ascii_case("bytes") {
+            return Ok(None);
+        }
+        let ranges = split_list(&value[sep+1..]).into_iter()
+            .map(ByteRange::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if ranges.is_empty() {
+            return Error::err(ErrorKind::Header, "Invalid range");
+        }
+        Ok(Some(ranges))
+    }
+
+    // Whether If-Match lists the entity tag, if it was given. The comparison
+    // is strong, so weak tags never match.
+    fn if_match(&self, tag: &str) -> Option<bool> {
+        // If-Match = "*" / #entity-tag
+        let list = self.header_list("If-Match");
+        if list.is_empty() {
+            return None;
+        }
+        Some(!tag.starts_with("W/") && entity_tag_listed(&list, tag, false))
+    }
+
+    // Whether If-None-Match lists the entity tag, if it was given.
+    fn if_none_match(&self, tag: &str) -> Option<bool> {
+        // If-None-Match = "*" / #entity-tag
+        let list = self.header_list("If-None-Match");
+        if list.is_empty() {
+            return None;
+        }
+        Some(entity_tag_listed(&list, tag, true))
+    }
+
+    // Dates which can't be understood are ignored.
+    fn if_modified_since(&self) -> Option<DateTime> {
+        match self.headers("If-Modified-Since").as_slice() {
+            [value] => DateTime::from_http_date(value),
+            _ => None,
+        }
+    }
+
+    fn host(&self) -> Result<Option<&str>, Error> {
+        // Host = uri-host [ ":" port ]
+        match self.headers("Host").as_slice() {
+            [] => Ok(None),
+            [value] if !value.is_empty() && !value.contains(|c: char| {
+                c.is_whitespace() || "/?#@".contains(c)
+            }) => Ok(Some(value)),
+            [_] => Error::err(ErrorKind::Header, "Invalid host"),
+            _ => Error::err(ErrorKind::Header, "Multiple Host fields"),
+        }
+    }
+}
+
+impl Headers for [Field] {
+    fn fields(&self) -> &[Field] {
+        self
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    fn fields(fields: &[(&str, &str)]) -> Vec<Field> {
+        fields.iter()
+            .map(|&(name, value)| Field::new(String::from(name), String::from(value)))
+            .collect()
+    }
+
+    #[test]
+    fn lookup() {
+        let fields = fields(&[("Foo", "1"), ("bar", "2"), ("FOO", "3")]);
+        assert_eq!(Some("1"), fields.header("foo"));
+        assert_eq!(Some("2"), fields.header("BAR"));
+        assert_eq!(None, fields.header("baz"));
+        assert_eq!(vec!["1", "3"], fields.headers("Foo"));
+        assert!(fields.headers("baz").is_empty());
+    }
+
+    #[test]
+    fn list() {
+        let fields = fields(&[("Accept", "a/b, c/d;x=\"1,2\""), ("Accept", ", e/f ,")]);
+        assert_eq!(vec!["a/b", "c/d;x=\"1,2\"", "e/f"], fields.header_list("accept"));
+    }
+
+    #[test]
+    fn content_length() {
+        assert_eq!(None, fields(&[]).content_length().unwrap());
+        assert_eq!(Some(42), fields(&[("content-length", "42")]).content_length().unwrap());
+        assert_eq!(Some(42), fields(&[("Content-Length", "42, 42"),
+                                      ("Content-Length", "42")])
+                   .content_length().unwrap());
+        for value in &["", "-1", "+1", "0x10", "1 2", "99999999999999999999999"] {
+            assert_parse_error("HTTP parsing error: Invalid content length",
+                    fields(&[("Content-Length", value)]).content_length());
+        }
+    }
+
+    #[test]
+    fn conflicting_content_length() {
+        assert_parse_error("HTTP parsing error: Conflicting content lengths",
+                fields(&[("Content-Length", "42, 43")]).content_length());
+        assert_parse_error("HTTP parsing error: Conflicting content lengths",
+                fields(&[("Content-Length", "42"), ("Content-Length", "0")])
+                    .content_length());
+    }
+
+    #[test]
+    fn content_type() {
+        assert_eq!(None, fields(&[]).content_type().unwrap());
+        let media_type = fields(&[("Content-Type",
+                "Application/VND.git-lfs+JSON; Charset=\"utf-8\"")])
+            .content_type().unwrap().unwrap();
+        assert_eq!("application/vnd.git-lfs+json", media_type.name);
+        assert_eq!(Some("utf-8"), media_type.param("charset"));
+        assert_parse_error("HTTP parsing error: Multiple Content-Type fields",
+                fields(&[("Content-Type", "a/b"), ("Content-Type", "a/b")])
+                    .content_type());
+    }
+
+    #[test]
+    fn media_type_parse() {
+        let media_type = MediaType::parse("text/plain ; a=1;b=\"x; \\\"y\\\"\" ;c=2").unwrap();
+        assert_eq!("text/plain", media_type.name);
+        assert_eq!(vec![(String::from("a"), String::from("1")),
+                        (String::from("b"), String::from("x; \"y\"")),
+                        (String::from("c"), String::from("2"))],
+                   media_type.params);
+        for value in &["", "text", "text/", "/plain", "te xt/plain",
+                       "text/plain;a", "text/plain;a=", "text/plain;a=\"1",
+                       "text/plain;a=\"1\"x", "text/plain;a=1 2"] {
+            assert_parse_error("HTTP parsing error: Invalid media type",
+                    MediaType::parse(value));
+        }
+    }
+
+    #[test]
+    fn transfer_encoding() {
+        assert!(fields(&[]).transfer_encoding().is_empty());
+        assert_eq!(vec!["gzip", "chunked"],
+                   fields(&[("Transfer-Encoding", "GZIP"),
+                            ("Transfer-Encoding", "Chunked")]).transfer_encoding());
+    }
+
+    #[test]
+    fn connection() {
+        assert_eq!(vec!["keep-alive", "upgrade"],
+                   fields(&[("Connection", "Keep-Alive, Upgrade")]).connection());
+    }
+
+    #[test]
+    fn content_encoding() {
+        assert!(fields(&[]).content_encoding().unwrap().is_empty());
+        assert_eq!(vec![ContentCoding::Deflate, ContentCoding::Gzip],
+                   fields(&[("Content-Encoding", "deflate, identity"),
+                            ("Content-Encoding", "X-Gzip")]).content_encoding().unwrap());
+        assert_parse_error("HTTP parsing error: Unsupported content coding",
+                fields(&[("Content-Encoding", "gzip, br")]).content_encoding());
+    }
+
+    #[test]
+    fn preferred_coding() {
+        let preferred = |value| fields(&[("Accept-Encoding", value)]).preferred_coding();
+        assert_eq!(ContentCoding::Identity, fields(&[]).preferred_coding());
+        assert_eq!(ContentCoding::Identity, preferred(""));
+        assert_eq!(ContentCoding::Identity, preferred("br"));
+        assert_eq!(ContentCoding::Gzip, preferred("gzip"));
+        assert_eq!(ContentCoding::Gzip, preferred("gzip, deflate, br"));
+        assert_eq!(ContentCoding::Gzip, preferred("x-gzip;q=0.5"));
+        assert_eq!(ContentCoding::Deflate, preferred("gzip;q=0.5, deflate"));
+        assert_eq!(ContentCoding::Deflate, preferred("deflate, gzip;q=0"));
+        assert_eq!(ContentCoding::Gzip, preferred("*"));
+        assert_eq!(ContentCoding::Deflate, preferred("*;q=0.1, deflate;q=0.2"));
+        assert_eq!(ContentCoding::Identity, preferred("gzip;q=0.5, identity"));
+        assert_eq!(ContentCoding::Identity, preferred("gzip;q=0, deflate;q=0"));
+        assert_eq!(ContentCoding::Gzip, preferred("gzip;q=bad"));
+    }
+
+    #[test]
+    fn expect() {
+        assert!(fields(&[]).expect().is_empty());
+        assert_eq!(vec!["100-continue"], fields(&[("Expect", "100-Continue")]).expect());
+    }
+
+    #[test]
+    fn authorization() {
+        assert_eq!(None, fields(&[]).authorization());
+        assert_eq!(Some(Authorization{ scheme: "Basic", credentials: "YWxpY2U6" }),
+                   fields(&[("Authorization", "Basic  YWxpY2U6")]).authorization());
+        assert_eq!(Some(Authorization{ scheme: "Negotiate", credentials: "" }),
+                   fields(&[("Authorization", "Negotiate")]).authorization());
+        assert_eq!(None, fields(&[("Authorization", "")]).authorization());
+        assert_eq!(None, fields(&[("Authorization", "Bad\"Scheme x")]).authorization());
+    }
+
+    #[test]
+    fn accept() {
+        let fields = fields(&[("Accept", "application/*;q=0.5, Text/Plain")]);
+        let ranges = fields.accept().unwrap();
+        assert_eq!(vec!["application/*", "text/plain"],
+                   ranges.iter().map(|range| range.name.as_str()).collect::<Vec<_>>());
+        assert!(fields.accepts("application/vnd.git-lfs+json"));
+        assert!(fields.accepts("text/plain"));
+        assert!(!fields.accepts("text/html"));
+    }
+
+    #[test]
+    fn accepts() {
+        assert!(fields(&[]).accepts("text/html"));
+        assert!(fields(&[("Accept", "*/*")]).accepts("text/html"));
+        assert!(!fields(&[("Accept", "text/*;q=0")]).accepts("text/html"));
+        assert!(fields(&[("Accept", "text/*;q=0, text/html")]).accepts("text/html"));
+        assert!(!fields(&[("Accept", "*/*, text/html;q=0")]).accepts("text/html"));
+    }
+
+    #[test]
+    fn range() {
+        assert_eq!(None, fields(&[]).range().unwrap());
+        assert_eq!(Some(vec![ByteRange::FromTo(0, Some(99)), ByteRange::FromTo(200, None),
+                             ByteRange::Suffix(50)]),
+                   fields(&[("Range", "Bytes=0-99, 200-,-50")]).range().unwrap());
+        assert_eq!(None, fields(&[("Range", "lines=1-2")]).range().unwrap());
+        for value in &["", "bytes", "bytes=", "bytes=,", "bytes=1", "bytes=a-b",
+                       "bytes=2-1", "bytes=-", "bytes=1-2-3", "bytes=+1-2",
+                       "bytes=0-99999999999999999999999"] {
+            assert_parse_error("HTTP parsing error: Invalid range",
+                    fields(&[("Range", value)]).range());
+        }
+        assert_parse_error("HTTP parsing error: Multiple Range fields",
+                fields(&[("Range", "bytes=0-1"), ("Range", "bytes=0-1")]).range());
+    }
+
+    #[test]
+    fn byte_range_resolve() {
+        assert_eq!(Some((0, 9)), ByteRange::FromTo(0, None).resolve(10));
+        assert_eq!(Some((2, 4)), ByteRange::FromTo(2, Some(4)).resolve(10));
+        assert_eq!(Some((2, 9)), ByteRange::FromTo(2, Some(100)).resolve(10));
+        assert_eq!(None, ByteRange::FromTo(10, None).resolve(10));
+        assert_eq!(None, ByteRange::FromTo(0, Some(0)).resolve(0));
+        assert_eq!(Some((7, 9)), ByteRange::Suffix(3).resolve(10));
+        assert_eq!(Some((0, 9)), ByteRange::Suffix(100).resolve(10));
+        assert_eq!(None, ByteRange::Suffix(0).resolve(10));
+        assert_eq!(None, ByteRange::Suffix(3).resolve(0));
+    }
+
+    #[test]
+    fn if_match() {
+        let tag = "\"abc\"";
+        assert_eq!(None, fields(&[]).if_match(tag));
+        assert_eq!(Some(true), fields(&[("If-Match", "*")]).if_match(tag));
+        assert_eq!(Some(true), fields(&[("If-Match", "\"x\", \"abc\"")]).if_match(tag));
+        assert_eq!(Some(false), fields(&[("If-Match", "\"x\"")]).if_match(tag));
+        assert_eq!(Some(false), fields(&[("If-Match", "W/\"abc\"")]).if_match(tag));
+        assert_eq!(Some(false), fields(&[("If-Match", "W/\"abc\"")]).if_match("W/\"abc\""));
+    }
+
+    #[test]
+    fn if_none_match() {
+        let tag = "\"abc\"";
+        assert_eq!(None, fields(&[]).if_none_match(tag));
+        assert_eq!(Some(true), fields(&[("If-None-Match", "*")]).if_none_match(tag));
+        assert_eq!(Some(true), fields(&[("If-None-Match", "\"x\""),
+                                        ("If-None-Match", "W/\"abc\"")]).if_none_match(tag));
+        assert_eq!(Some(false), fields(&[("If-None-Match", "\"x\"")]).if_none_match(tag));
+    }
+
+    #[test]
+    fn if_modified_since() {
+        assert_eq!(None, fields(&[]).if_modified_since());
+        assert_eq!(None, fields(&[("If-Modified-Since", "yesterday")]).if_modified_since());
+        assert_eq!(Some(DateTime{ year: 1994, month: 11, day: 6,
+                                  hour: 8, minute: 49, second: 37 }),
+                   fields(&[("If-Modified-Since", "Sun, 06 Nov 1994 08:49:37 GMT")])
+                       .if_modified_since());
+    }
+
+    #[test]
+    fn host() {
+        assert_eq!(None, fields(&[]).host().unwrap());
+        assert_eq!(Some("example.com:8080"),
+                   fields(&[("host", "example.com:8080")]).host().unwrap());
+        assert_eq!(Some("[::1]:80"), fields(&[("Host", "[::1]:80")]).host().unwrap());
+        for value in &["", "a b", "user@example.com", "example.com/foo"] {
+            assert_parse_error("HTTP parsing error: Invalid host",
+                    fields(&[("Host", value)]).host());
+        }
+        assert_parse_error("HTTP parsing error: Multiple Host fields",
+                fields(&[("Host", "a"), ("Host", "a")]).host());
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+use super::{Error, ErrorKind};
+use super::headers::is_token;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Method {
+    GET,
+    HEAD,
+    POST,
+    PUT,
+    DELETE,
+    TRACE,
+    OPTIONS,
+    CONNECT,
+    PATCH,
+    // WebDAV (RFC 4918), which desktop clients send unprompted.
+    PROPFIND,
+    PROPPATCH,
+    MKCOL,
+    COPY,
+    MOVE,
+    LOCK,
+    UNLOCK,
+    // Any other method, which the server may not implement. Methods are
+    // case-sensitive, so "get" is one of these rather than GET.
+    Extension(String),
+}
+
+impl Method {
+    pub fn from(method: &str) -> Result<Method, Error> {
+        // method = token
+        match method {
+            "GET"       => Ok(Method::GET),
+            "HEAD"      => Ok(Method::HEAD),
+            "POST"      => Ok(Method::POST),
+            "PUT"       => Ok(Method::PUT),
+            "DELETE"    => Ok(Method::DELETE),
+            "TRACE"     => Ok(Method::TRACE),
+            "OPTIONS"   => Ok(Method::OPTIONS),
+            "CONNECT"   => Ok(Method::CONNECT),
+            "PATCH"     => Ok(Method::PATCH),
+            "PROPFIND"  => Ok(Method::PROPFIND),
+            "PROPPATCH" => Ok(Method::PROPPATCH),
+            "MKCOL"     => Ok(Method::MKCOL),
+            "COPY"      => Ok(Method::COPY),
+            "MOVE"      => Ok(Method::MOVE),
+            "LOCK"      => Ok(Method::LOCK),
+            "UNLOCK"    => Ok(Method::UNLOCK),
+            _ if is_token(method) => {
+                Ok(Method::Extension(String::from(method)))
+            },
+            _ => Error::err(ErrorKind::StartLine, "Invalid method"),
+        }
+    }
+
+    // Safe methods only ask for information, so can be made without the
+    // user's say-so (RFC 9110 section 9.2.1).
+    pub fn is_safe(&self) -> bool {
+        matches!(*self, Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE |
+                        Method::PROPFIND)
+    }
+
+    // Idempotent methods can be repeated to the same effect, so can be sent
+    // again when it isn't known whether they arrived (RFC 9110 section
+    // 9.2.2). Nothing is assumed of extension methods.
+    pub fn is_idempotent(&self) -> bool {
+        self.is_safe() ||
+            matches!(*self, Method::PUT | Method::DELETE | Method::PROPPATCH | Method::MKCOL |
+                            Method::COPY | Method::MOVE | Method::UNLOCK)
+    }
+
+    // Whether a body sent with the request has any defined meaning. Bodies of
+    // other requests are still read, but may be ignored.
+    pub fn allows_body(&self) -> bool {
+        !matches!(*self, Method::GET | Method::HEAD | Method::DELETE | Method::TRACE |
+                         Method::CONNECT | Method::COPY | Method::MOVE | Method::UNLOCK)
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Method::Extension(ref name) => f.write_str(name),
+            _ => fmt::Debug::fmt(self, f),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    #[test]
+    fn from_valid_string() {
+        let names = ["CONNECT", "DELETE", "GET",
+                     "HEAD", "OPTIONS", "PATCH",
+                     "POST", "PUT", "TRACE",
+                     "PROPFIND", "PROPPATCH", "MKCOL",
+                     "COPY", "MOVE", "LOCK", "UNLOCK"];
+        let mut enums = [Method::CONNECT, Method::DELETE, Method::GET,
+                         Method::HEAD, Method::OPTIONS, Method::PATCH,
+                         Method::POST, Method::PUT, Method::TRACE,
+                         Method::PROPFIND, Method::PROPPATCH, Method::MKCOL,
+                         Method::COPY, Method::MOVE, Method::LOCK, Method::UNLOCK];
+        assert_eq!(names.len(), enums.len());
+        for (value, expected) in names.iter().zip(enums.iter_mut()) {
+            let actual = Method::from(value).unwrap();
+            assert_eq!(expected, &actual);
+            assert_eq!(value, &format!("{}", actual));
+        }
+    }
+
+    #[test]
+    fn from_extension_string() {
+        for name in &["BREW", "Get", "x-custom.method", "M-SEARCH"] {
+            let method = Method::from(name).unwrap();
+            assert_eq!(Method::Extension(String::from(*name)), method);
+            assert_eq!(*name, format!("{}", method));
+        }
+        assert_ne!(Method::GET, Method::from("get").unwrap());
+    }
+
+    #[test]
+    fn from_invalid_string() {
+        // Test empty string.
+        let result = Method::from("");
+        assert_parse_error("HTTP parsing error: Invalid method", result);
+
+        // Methods are tokens.
+        for name in &["GE T", "GET/", "(GET)", "GÉT"] {
+            assert_parse_error("HTTP parsing error: Invalid method", Method::from(name));
+        }
+    }
+
+    #[test]
+    fn properties() {
+        let method = |name| Method::from(name).unwrap();
+        for name in &["GET", "HEAD", "OPTIONS", "TRACE", "PROPFIND"] {
+            assert!(method(name).is_safe(), "{}", name);
+            assert!(method(name).is_idempotent(), "{}", name);
+        }
+        for name in &["PUT", "DELETE", "PROPPATCH", "MKCOL", "COPY", "MOVE", "UNLOCK"] {
+            assert!(!method(name).is_safe(), "{}", name);
+            assert!(method(name).is_idempotent(), "{}", name);
+        }
+        for name in &["POST", "PATCH", "CONNECT", "LOCK", "BREW"] {
+            assert!(!method(name).is_safe(), "{}", name);
+            assert!(!method(name).is_idempotent(), "{}", name);
+        }
+        for name in &["POST", "PUT", "PATCH", "OPTIONS", "PROPFIND", "LOCK", "BREW"] {
+            assert!(method(name).allows_body(), "{}", name);
+        }
+        for name in &["GET", "HEAD", "DELETE", "TRACE", "CONNECT", "MOVE"] {
+            assert!(!method(name).allows_body(), "{}", name);
+        }
+    }
+}
\ No newline at end of file
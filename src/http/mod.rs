@@ -0,0 +1,959 @@
+mod error;
+mod method;
+mod version;
+mod status_code;
+mod field;
+mod request_status;
+mod response_status;
+mod target;
+mod request;
+mod response;
+mod headers;
+mod head;
+mod chunked;
+mod body;
+mod date;
+mod coding;
+
+use std::fmt;
+use std::io;
+use std::io::{Read, Write};
+use std::mem;
+
+pub use self::error::ParseError as Error;
+pub use self::error::ErrorKind;
+pub use self::method::Method;
+pub use self::version::Version;
+pub use self::status_code::{StatusCode, UnknownCode};
+pub use self::field::Field;
+pub use self::headers::{ByteRange, Headers};
+pub use self::head::Limits;
+use self::request_status::RequestStatus;
+use self::response_status::ResponseStatus;
+pub use self::target::{Target, TargetForm};
+pub use self::request::Request;
+pub use self::response::Response;
+pub use self::chunked::ChunkedDecoder;
+pub use self::chunked::ChunkedReader;
+pub use self::chunked::ChunkedWriter;
+pub use self::body::Body;
+pub use self::body::BodyReader;
+pub use self::body::Decoded;
+// The push parsers, for callers which can't block on a reader.
+pub use self::head::{HeadParser, Parsed};
+pub use self::body::BodyDecoder;
+pub use self::body::Framing;
+pub use self::date::DateTime;
+pub use self::coding::{decode_content, ContentCoding};
+
+#[derive(Debug, PartialEq)]
+enum Status {
+    Request(RequestStatus),
+    Response(ResponseStatus),
+}
+
+// The body of a message being built: either held in memory, or read from a
+// stream as the message is written so that large bodies needn't fit in memory.
+enum Content {
+    Bytes(Vec<u8>),
+    Stream(Box<dyn Read>),
+}
+
+impl fmt::Debug for Content {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Content::Bytes(ref bytes) => f.debug_tuple("Bytes").field(bytes).finish(),
+            Content::Stream(_) => f.write_str("Stream"),
+        }
+    }
+}
+
+impl PartialEq for Content {
+    fn eq(&self, other: &Content) -> bool {
+        // Streams can't be compared without consuming them.
+        match (self, other) {
+            (Content::Bytes(a), Content::Bytes(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+// Bodies shorter than this aren't worth compressing.
+const MIN_ENCODED_LENGTH: usize = 256;
+
+// Whether bodies of the media type are worth compressing. Anything else, such
+// as objects, is likely compressed already.
+fn is_compressible(media_type: &str) -> bool {
+    media_type.starts_with("text/") || media_type == "application/json" ||
+        media_type.ends_with("+json")
+}
+
+// Identifies the server in the responses it sends.
+const SERVER: &str = concat!("local-lfs/", env!("CARGO_PKG_VERSION"));
+
+fn invalid_message(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MessageBuilder {
+    status: Status,
+    fields: Vec<Field>,
+    body: Content,
+    body_omitted: bool,
+}
+
+impl MessageBuilder {
+    pub fn request(method: Method, target: String) -> MessageBuilder {
+        let status = Status::Request(RequestStatus::new(method, target));
+        MessageBuilder{ status, fields: Vec::new(), body: Content::Bytes(Vec::new()),
+                        body_omitted: false }
+    }
+
+    pub fn response(code: StatusCode) -> MessageBuilder {
+        let status = Status::Response(ResponseStatus::new(code));
+        MessageBuilder{ status, fields: Vec::new(), body: Content::Bytes(Vec::new()),
+                        body_omitted: false }
+    }
+
+    pub fn add_field(&mut self, field: Field) -> &mut Self {
+        self.fields.push(field);
+        self
+    }
+
+    pub fn add_field2(&mut self, name: &str, value: &str) -> &mut Self {
+        self.fields.push(Field::new(String::from(name), String::from(value)));
+        self
+    }
+
+    // Bodies are sent as given, so needn't be text.
+    pub fn add_body<B: Into<Vec<u8>>>(&mut self, body: B) -> &mut Self {
+        self.body = Content::Bytes(body.into());
+        self
+    }
+
+    // The body is copied from the reader when the message is written. Unless
+    // its length is given with a Content-Length field it is sent chunked.
+    pub fn add_body_reader<R: Read + 'static>(&mut self, reader: R) -> &mut Self {
+        self.body = Content::Stream(Box::new(reader));
+        self
+    }
+
+    // Applies the content coding to the body. Any Content-Length is dropped
+    // to be worked out again, so streamed bodies, whose encoded length isn't
+    // known, are sent chunked.
+    pub fn encode(&mut self, coding: ContentCoding) -> &mut Self {
+        if coding == ContentCoding::Identity {
+            return self;
+        }
+        self.fields.retain(|field| !field.name.eq_ignore_ascii_case("Content-Length"));
+        self.body = match mem::replace(&mut self.body, Content::Bytes(Vec::new())) {
+            Content::Bytes(bytes) => Content::Bytes(coding.encode(&bytes)),
+            Content::Stream(reader) => Content::Stream(coding.encoder(reader)),
+        };
+        self.add_field2("Content-Encoding", coding.name())
+    }
+
+    // Compresses a response to the request if it's worth it and the client
+    // accepts it. Bodies of other types, and ones already encoded, are left
+    // as they are.
+    pub fn encode_for(&mut self, request: &Request) -> &mut Self {
+        let compressible = self.content_type().ok().flatten()
+            .is_some_and(|media_type| is_compressible(&media_type.name));
+        if !compressible || !self.fields.headers("Content-Encoding").is_empty() {
+            return self;
+        }
+        // Caches must tell apart responses to requests accepting different
+        // codings.
+        self.add_field2("Vary", "Accept-Encoding");
+        let long_enough = match self.body {
+            Content::Bytes(ref bytes) => bytes.len() >= MIN_ENCODED_LENGTH,
+            Content::Stream(_) => true,
+        };
+        if long_enough {
+            self.encode(request.preferred_coding());
+        }
+        self
+    }
+
+    // For responses to HEAD requests: the fields still describe the body that
+    // would have been sent, but it isn't.
+    pub fn omit_body(&mut self) -> &mut Self {
+        self.body_omitted = true;
+        self
+    }
+
+    // Adds the fields the message needs and wasn't given: its framing and,
+    // in final responses, Date and Server. Returns how the body is to be
+    // sent, or None if it isn't sent at all. Framing fields which contradict
+    // the body are refused, as the peer would misread whatever follows.
+    fn complete(&mut self) -> io::Result<Option<Framing>> {
+        let code = match self.status {
+            Status::Request(_) => None,
+            Status::Response(ref status) => Some(status.status.code()),
+        };
+        if code.is_some_and(|code| code >= 200) {
+            if self.fields.headers("Date").is_empty() {
+                self.add_field2("Date", &DateTime::now().http_date());
+            }
+            if self.fields.headers("Server").is_empty() {
+                self.add_field2("Server", SERVER);
+            }
+        }
+
+        let framed = !self.fields.headers("Content-Length").is_empty() ||
+            !self.fields.headers("Transfer-Encoding").is_empty();
+        let given = if framed {
+            let framing = Framing::from_fields(&self.fields)
+                .map_err(|error| invalid_message(&format!("{}", error)))?;
+            Some(framing)
+        } else {
+            None
+        };
+        // Informational and 204 (No Content) responses end with their fields.
+        if code.is_some_and(|code| code < 200 || code == 204) {
+            return match given {
+                None | Some(Framing::Length(0)) => Ok(None),
+                Some(_) => {
+                    Err(invalid_message("Framing fields given for a response without a body"))
+                },
+            };
+        }
+
+        // Fields describing a body which isn't sent needn't match it.
+        let sent = !self.body_omitted && code != Some(304);
+        let framing = match (given, &self.body) {
+            (Some(Framing::Length(length)), Content::Bytes(bytes))
+                    if sent && bytes.len() != length => {
+                return Err(invalid_message("Body length does not match its Content-Length"));
+            },
+            (Some(framing), _) => framing,
+            // The fields of a 304 (Not Modified) response describe the object
+            // it stands for, whose length isn't known here.
+            (None, _) if code == Some(304) => Framing::Length(0),
+            (None, Content::Bytes(bytes)) => {
+                // Requests only say they have a body when they do.
+                if code.is_some() || !bytes.is_empty() {
+                    self.fields.push(Field::new_contentlength(bytes.len()));
+                }
+                Framing::Length(bytes.len())
+            },
+            (None, Content::Stream(_)) => {
+                self.fields.push(Field::new(String::from("Transfer-Encoding"),
+                                            String::from("chunked")));
+                Framing::Chunked
+            },
+        };
+        Ok(if sent { Some(framing) } else { None })
+    }
+
+    pub fn write_to<W: Write>(mut self, writer: &mut W) -> io::Result<()> {
+        let framing = match self.complete()? {
+            Some(framing) => framing,
+            None => {
+                self.body = Content::Bytes(Vec::new());
+                Framing::Length(0)
+            },
+        };
+        match self.status {
+            Status::Request(status) => {
+                Request::new(status, self.fields).write_to(writer)?;
+            },
+            Status::Response(status) => {
+                Response::new(status, self.fields).write_to(writer)?;
+            },
+        }
+
+        let streamed = matches!(self.body, Content::Stream(_));
+        let mut reader: Box<dyn Read> = match self.body {
+            Content::Bytes(bytes) => Box::new(io::Cursor::new(bytes)),
+            Content::Stream(reader) => reader,
+        };
+        match framing {
+            Framing::Chunked => {
+                let mut chunked = ChunkedWriter::new(writer);
+                io::copy(&mut reader, &mut chunked)?;
+                chunked.finish().map(|_| ())
+            },
+            Framing::Length(length) if streamed => {
+                // Sending less than promised would leave the peer waiting.
+                let copied = io::copy(&mut reader.take(length as u64), writer)?;
+                if copied < length as u64 {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                            "Body shorter than its Content-Length"));
+                }
+                Ok(())
+            },
+            Framing::Length(_) => io::copy(&mut reader, writer).map(|_| ()),
+        }
+    }
+
+    // Fails as write_to does if the message contradicts itself.
+    pub fn into_bytes(self) -> io::Result<Vec<u8>> {
+        let mut message = Vec::new();
+        self.write_to(&mut message)?;
+        Ok(message)
+    }
+}
+
+impl Headers for MessageBuilder {
+    fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+}
+
+
+#[cfg(test)]
+#[allow(clippy::needless_lifetimes, clippy::redundant_static_lifetimes)]
+mod tests {
+    use std::cmp;
+    use std::fmt;
+    use std::io::{BufRead, Cursor, ErrorKind};
+    use std::io::Result as IoResult;
+    use std::str;
+    use proptest::prelude::*;
+    use super::*;
+
+    pub struct StringReader {
+        content: String,
+        pos: usize,
+    }
+
+    impl StringReader {
+        pub fn new<'a>(content: &'a str) -> StringReader {
+            StringReader{ content: String::from(content), pos: 0 }
+        }
+    }
+
+    impl Read for StringReader {
+        fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+            let len = cmp::min(buf.len(), self.content.len() - self.pos);
+            let end = self.pos + len;
+            buf[..len].clone_from_slice(&self.content.as_bytes()[self.pos..end]);
+            self.pos += len;
+            Ok(len)
+        }
+    }
+
+    impl BufRead for StringReader {
+        fn fill_buf(&mut self) -> IoResult<&[u8]> {
+            Ok(&self.content.as_bytes()[self.pos..])
+        }
+
+        fn consume(&mut self, amt: usize) {
+            self.pos += amt;
+        }
+    }
+
+
+    // Deterministic pseudo-random bytes (xorshift64*), so that failures can
+    // be reproduced.
+    pub fn random_bytes(seed: u64, length: usize) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(0x9e3779b97f4a7c15) | 1;
+        (0..length).map(|_| {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            (state.wrapping_mul(0x2545f4914f6cdd1d) >> 56) as u8
+        }).collect()
+    }
+
+    // Fields as they may validly be sent, leaving out those which frame the
+    // body so that any set of them can be sent with any message.
+    pub fn valid_field() -> impl Strategy<Value = Field> {
+        let name = "[-!#$%&'*+.^_`|~0-9A-Za-z]{1,20}".prop_filter("framing field", |name| {
+            !name.eq_ignore_ascii_case("Content-Length") &&
+            !name.eq_ignore_ascii_case("Transfer-Encoding")
+        });
+        let vchar = prop_oneof![b'!'..=b'~', 0x80u8..=0xff];
+        let content = prop_oneof![vchar.clone(), Just(b' '), Just(b'\t')];
+        let value = (vchar.clone(), prop::collection::vec(content, 0..40), vchar)
+            .prop_map(|(first, mut middle, last)| {
+                middle.insert(0, first);
+                middle.push(last);
+                middle
+            });
+        let value = prop_oneof![Just(Vec::new()), value];
+        (name, value).prop_map(|(name, value)| Field{ name, value })
+    }
+
+    pub fn assert_parse_error<T: fmt::Debug>(message: &str, result: Result<T, Error>) {
+        assert!(result.is_err());
+        let description = format!("{}", result.unwrap_err());
+        assert_eq!(message, description);
+    }
+
+
+    const REQ_METHOD: Method = Method::GET;
+    const REQ_TARGET: &'static str = "/foo/bar";
+    const RSP_CODE: StatusCode = StatusCode::ImATeapot;
+    const FIELD_N1: &'static str = "david";
+    const FIELD_V1: &'static str = "suchet";
+    const FIELD_N2: &'static str = "hello";
+    const FIELD_V2: &'static str = "world";
+    const BODY:     &'static str = "ze little grey cells";
+    const REQUEST_ENCODING_2FB: &'static str = "\
+        GET /foo/bar HTTP/1.1\r\n\
+        david: suchet\r\n\
+        hello: world\r\n\
+        Content-Length: 20\r\n\
+        \r\n\
+        ze little grey cells";
+    const REQUEST_ENCODING_1FB: &'static str = "\
+        GET /foo/bar HTTP/1.1\r\n\
+        david: suchet\r\n\
+        Content-Length: 20\r\n\
+        \r\n\
+        ze little grey cells";
+    const REQUEST_ENCODING_0FB: &'static str = "\
+        GET /foo/bar HTTP/1.1\r\n\
+        Content-Length: 20\r\n\
+        \r\n\
+        ze little grey cells";
+    const REQUEST_ENCODING_0F: &'static str = "\
+        GET /foo/bar HTTP/1.1\r\n\
+        \r\n";
+    const RESPONSE_ENCODING_2FB: &'static str = "\
+        HTTP/1.1 418 I'm a teapot\r\n\
+        david: suchet\r\n\
+        hello: world\r\n\
+        Content-Length: 20\r\n\
+        \r\n\
+        ze little grey cells";
+    const RESPONSE_ENCODING_1FB: &'static str = "\
+        HTTP/1.1 418 I'm a teapot\r\n\
+        david: suchet\r\n\
+        Content-Length: 20\r\n\
+        \r\n\
+        ze little grey cells";
+    const RESPONSE_ENCODING_0FB: &'static str = "\
+        HTTP/1.1 418 I'm a teapot\r\n\
+        Content-Length: 20\r\n\
+        \r\n\
+        ze little grey cells";
+    const RESPONSE_ENCODING_0F: &'static str = "\
+        HTTP/1.1 418 I'm a teapot\r\n\
+        Content-Length: 0\r\n\
+        \r\n";
+
+    // The message with the Date and Server fields added to responses taken
+    // out, once checked, so that the rest of it can be compared.
+    fn without_generated(message: Vec<u8>) -> Vec<u8> {
+        let head_end = message.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 2;
+        let (head, body) = message.split_at(head_end);
+        let mut kept = String::new();
+        let mut generated = 0;
+        for line in str::from_utf8(head).unwrap().split_inclusive("\r\n") {
+            if let Some(date) = line.strip_prefix("Date: ") {
+                assert!(DateTime::from_http_date(date.trim_end()).is_some());
+                generated += 1;
+            } else if let Some(server) = line.strip_prefix("Server: ") {
+                assert_eq!(SERVER, server.trim_end());
+                generated += 1;
+            } else {
+                kept.push_str(line);
+            }
+        }
+        assert_eq!(2, generated);
+        let mut kept = kept.into_bytes();
+        kept.extend(body);
+        kept
+    }
+
+    #[test]
+    fn request() {
+        let mut builder = MessageBuilder::request(REQ_METHOD, String::from(REQ_TARGET));
+        builder.add_field2(FIELD_N1, FIELD_V1)
+               .add_field2(FIELD_N2, FIELD_V2)
+               .add_body(String::from(BODY));
+        assert_eq!(REQUEST_ENCODING_2FB.as_bytes(), builder.into_bytes().unwrap().as_slice());
+
+        let mut builder = MessageBuilder::request(REQ_METHOD, String::from(REQ_TARGET));
+        builder.add_field2(FIELD_N1, FIELD_V1)
+               .add_body(String::from(BODY));
+        assert_eq!(REQUEST_ENCODING_1FB.as_bytes(), builder.into_bytes().unwrap().as_slice());
+
+        let mut builder = MessageBuilder::request(REQ_METHOD, String::from(REQ_TARGET));
+        builder.add_body(String::from(BODY));
+        assert_eq!(REQUEST_ENCODING_0FB.as_bytes(), builder.into_bytes().unwrap().as_slice());
+
+        let builder = MessageBuilder::request(REQ_METHOD, String::from(REQ_TARGET));
+        assert_eq!(REQUEST_ENCODING_0F.as_bytes(), builder.into_bytes().unwrap().as_slice());
+    }
+
+    #[test]
+    fn response() {
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field2(FIELD_N1, FIELD_V1)
+               .add_field2(FIELD_N2, FIELD_V2)
+               .add_body(String::from(BODY));
+        assert_eq!(RESPONSE_ENCODING_2FB.as_bytes(), without_generated(builder.into_bytes().unwrap()).as_slice());
+
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field2(FIELD_N1, FIELD_V1)
+               .add_body(String::from(BODY));
+        assert_eq!(RESPONSE_ENCODING_1FB.as_bytes(), without_generated(builder.into_bytes().unwrap()).as_slice());
+
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_body(String::from(BODY));
+        assert_eq!(RESPONSE_ENCODING_0FB.as_bytes(), without_generated(builder.into_bytes().unwrap()).as_slice());
+
+        let builder = MessageBuilder::response(RSP_CODE);
+        assert_eq!(RESPONSE_ENCODING_0F.as_bytes(), without_generated(builder.into_bytes().unwrap()).as_slice());
+    }
+
+    #[test]
+    fn binary_body() {
+        let body = vec![0x00, 0xff, 0xfe, b'\r', b'\n'];
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_body(body.clone());
+        let mut expected = b"HTTP/1.1 418 I'm a teapot\r\nContent-Length: 5\r\n\r\n".to_vec();
+        expected.extend(&body);
+        assert_eq!(expected, without_generated(builder.into_bytes().unwrap()));
+    }
+
+    #[test]
+    fn chunked_body() {
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field2("Transfer-Encoding", "chunked")
+               .add_body(String::from(BODY));
+        let expected = "\
+            HTTP/1.1 418 I'm a teapot\r\n\
+            Transfer-Encoding: chunked\r\n\
+            \r\n\
+            14\r\n\
+            ze little grey cells\r\n\
+            0\r\n\
+            \r\n";
+        assert_eq!(expected.as_bytes(), without_generated(builder.into_bytes().unwrap()).as_slice());
+    }
+
+    #[test]
+    fn streamed_body_chunked_automatically() {
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_body_reader(Cursor::new(BODY));
+        let mut reader = Cursor::new(builder.into_bytes().unwrap());
+        let response = Response::parse(&mut reader).unwrap();
+        assert_eq!(Framing::Chunked, response.framing().unwrap());
+        let body = Body::parse_framed(&mut reader, &Framing::Chunked).unwrap();
+        assert_eq!(BODY.as_bytes(), body.content());
+    }
+
+    #[test]
+    fn date_and_server() {
+        let before = DateTime::now();
+        let response = Response::parse(&mut Cursor::new(
+                MessageBuilder::response(RSP_CODE).into_bytes().unwrap())).unwrap();
+        let date = DateTime::from_http_date(response.header("Date").unwrap()).unwrap();
+        assert!(before <= date && date <= DateTime::now());
+        assert_eq!(Some(SERVER), response.header("Server"));
+
+        // Fields given aren't replaced.
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field2("Date", "Sun, 06 Nov 1994 08:49:37 GMT")
+               .add_field2("Server", "other");
+        let response = Response::parse(&mut Cursor::new(builder.into_bytes().unwrap())).unwrap();
+        assert_eq!(vec!["Sun, 06 Nov 1994 08:49:37 GMT"], response.headers("Date"));
+        assert_eq!(vec!["other"], response.headers("Server"));
+
+        let request = Request::parse(&mut Cursor::new(
+                MessageBuilder::request(REQ_METHOD, String::from(REQ_TARGET)).into_bytes().unwrap()))
+            .unwrap();
+        assert_eq!(None, request.header("Date"));
+        assert_eq!(None, request.header("Server"));
+    }
+
+    #[test]
+    fn bodies_omitted() {
+        // Responses to HEAD keep the length of the body they'd have had.
+        let mut builder = MessageBuilder::response(StatusCode::Ok);
+        builder.add_body(BODY).omit_body();
+        let message = builder.into_bytes().unwrap();
+        assert!(message.ends_with(b"\r\n\r\n"));
+        let response = Response::parse(&mut Cursor::new(message)).unwrap();
+        assert_eq!(Some(BODY.len()), response.content_length().unwrap());
+
+        let mut builder = MessageBuilder::response(StatusCode::Ok);
+        builder.add_field(Field::new_contentlength(BODY.len()))
+               .add_body_reader(Cursor::new(BODY))
+               .omit_body();
+        let message = builder.into_bytes().unwrap();
+        assert!(message.ends_with(b"\r\n\r\n"));
+        let response = Response::parse(&mut Cursor::new(message)).unwrap();
+        assert_eq!(Some(BODY.len()), response.content_length().unwrap());
+
+        for &code in &[204, 304] {
+            let mut builder = MessageBuilder::response(StatusCode::from(code).unwrap());
+            builder.add_body(BODY);
+            let message = builder.into_bytes().unwrap();
+            assert!(message.ends_with(b"\r\n\r\n"));
+            let response = Response::parse(&mut Cursor::new(message)).unwrap();
+            assert_eq!(None, response.content_length().unwrap());
+            assert!(response.header("Transfer-Encoding").is_none());
+        }
+
+        // A 304 (Not Modified) response may give the length of the object.
+        let mut builder = MessageBuilder::response(StatusCode::NotModified);
+        builder.add_field(Field::new_contentlength(1000));
+        let response = Response::parse(&mut Cursor::new(builder.into_bytes().unwrap())).unwrap();
+        assert_eq!(Some(1000), response.content_length().unwrap());
+    }
+
+    #[test]
+    fn contradictory_framing() {
+        let refused = |builder: MessageBuilder| {
+            builder.write_to(&mut Vec::new()).unwrap_err().kind()
+        };
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field(Field::new_contentlength(BODY.len() + 1))
+               .add_body(BODY);
+        assert_eq!(ErrorKind::InvalidInput, refused(builder));
+
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field(Field::new_contentlength(BODY.len() + 1))
+               .add_body(BODY);
+        assert_eq!(ErrorKind::InvalidInput, builder.into_bytes().unwrap_err().kind());
+
+        let mut builder = MessageBuilder::request(Method::PUT, String::from(REQ_TARGET));
+        builder.add_field(Field::new_contentlength(0))
+               .add_body(BODY);
+        assert_eq!(ErrorKind::InvalidInput, refused(builder));
+
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field(Field::new_contentlength(BODY.len()))
+               .add_field2("Transfer-Encoding", "chunked")
+               .add_body(BODY);
+        assert_eq!(ErrorKind::InvalidInput, refused(builder));
+
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field2("Transfer-Encoding", "gzip")
+               .add_body(BODY);
+        assert_eq!(ErrorKind::InvalidInput, refused(builder));
+
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field2("Content-Length", "20, 21")
+               .add_body(BODY);
+        assert_eq!(ErrorKind::InvalidInput, refused(builder));
+
+        // Responses which can't have a body can't say they do either.
+        for &code in &[100, 204] {
+            let response = || MessageBuilder::response(StatusCode::from(code).unwrap());
+            let mut builder = response();
+            builder.add_field2("Transfer-Encoding", "chunked");
+            assert_eq!(ErrorKind::InvalidInput, refused(builder));
+            let mut builder = response();
+            builder.add_field(Field::new_contentlength(1));
+            assert_eq!(ErrorKind::InvalidInput, refused(builder));
+            let mut builder = response();
+            builder.add_field(Field::new_contentlength(0));
+            assert!(builder.write_to(&mut Vec::new()).is_ok());
+        }
+    }
+
+    #[test]
+    fn encode_body() {
+        let content = BODY.repeat(100);
+        for coding in &[ContentCoding::Gzip, ContentCoding::Deflate] {
+            let mut builder = MessageBuilder::response(RSP_CODE);
+            builder.add_field(Field::new_contentlength(content.len()))
+                   .add_body(content.clone())
+                   .encode(*coding);
+            let mut reader = Cursor::new(builder.into_bytes().unwrap());
+            let response = Response::parse(&mut reader).unwrap();
+            assert_eq!(vec![*coding], response.content_encoding().unwrap());
+            let body = Body::parse_framed(&mut reader, &response.framing().unwrap()).unwrap();
+            assert!(body.content().len() < content.len());
+            let mut decoded = String::new();
+            decode_content(body.content(), &[*coding]).read_to_string(&mut decoded).unwrap();
+            assert_eq!(content, decoded);
+        }
+    }
+
+    #[test]
+    fn encode_streamed_body() {
+        let content = random_bytes(3, 5000);
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field(Field::new_contentlength(content.len()))
+               .add_body_reader(Cursor::new(content.clone()))
+               .encode(ContentCoding::Gzip);
+        let mut reader = Cursor::new(builder.into_bytes().unwrap());
+        let response = Response::parse(&mut reader).unwrap();
+        assert_eq!(Framing::Chunked, response.framing().unwrap());
+        let body = Body::parse_framed(&mut reader, &Framing::Chunked).unwrap();
+        let mut decoded = Vec::new();
+        decode_content(body.content(), &[ContentCoding::Gzip]).read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(content, decoded);
+    }
+
+    #[test]
+    fn encode_for() {
+        let request = |accept: &str| {
+            let message = format!("GET / HTTP/1.1\r\nAccept-Encoding: {}\r\n\r\n", accept);
+            Request::parse(&mut Cursor::new(message)).unwrap()
+        };
+        let response = |media_type: &str, length: usize| {
+            let mut builder = MessageBuilder::response(RSP_CODE);
+            builder.add_field2("Content-Type", media_type)
+                   .add_field(Field::new_contentlength(length))
+                   .add_body(vec![b'x'; length]);
+            builder
+        };
+        let encoded = |mut builder: MessageBuilder, request: &Request| {
+            builder.encode_for(request);
+            let response = Response::parse(&mut Cursor::new(builder.into_bytes().unwrap())).unwrap();
+            (response.content_encoding().unwrap(), response.header("Vary").map(String::from))
+        };
+        let vary = Some(String::from("Accept-Encoding"));
+        for media_type in &["application/vnd.git-lfs+json", "application/json", "text/plain"] {
+            assert_eq!((vec![ContentCoding::Gzip], vary.clone()),
+                       encoded(response(media_type, 1000), &request("gzip, deflate")));
+        }
+        assert_eq!((vec![ContentCoding::Deflate], vary.clone()),
+                   encoded(response("application/json", 1000), &request("deflate")));
+        assert_eq!((vec![], vary.clone()),
+                   encoded(response("application/json", 1000), &request("identity")));
+        // Short bodies aren't worth it.
+        assert_eq!((vec![], vary.clone()),
+                   encoded(response("application/json", 100), &request("gzip")));
+        // Neither are ones which are likely compressed already.
+        for media_type in &["application/octet-stream", "multipart/byteranges; boundary=x"] {
+            assert_eq!((vec![], None), encoded(response(media_type, 1000), &request("gzip")));
+        }
+        let mut builder = response("application/json", 1000);
+        builder.add_field2("Content-Encoding", "deflate");
+        assert_eq!((vec![ContentCoding::Deflate], None), encoded(builder, &request("gzip")));
+    }
+
+    #[test]
+    fn interim_response() {
+        // Interim responses have no body, and precede the final response.
+        let mut message = Vec::new();
+        MessageBuilder::response(StatusCode::Continue).write_to(&mut message).unwrap();
+        assert_eq!(b"HTTP/1.1 100 Continue\r\n\r\n".to_vec(), message);
+        let mut builder = MessageBuilder::response(StatusCode::Ok);
+        builder.add_field(Field::new_contentlength(BODY.len()))
+               .add_body(BODY);
+        builder.write_to(&mut message).unwrap();
+
+        let mut reader = Cursor::new(message);
+        let interim = Response::parse(&mut reader).unwrap();
+        assert_eq!(&StatusCode::Continue, interim.status());
+        assert_eq!(Framing::Length(0), interim.framing().unwrap());
+        let response = Response::parse(&mut reader).unwrap();
+        assert_eq!(&StatusCode::Ok, response.status());
+        let body = Body::parse_framed(&mut reader, &response.framing().unwrap()).unwrap();
+        assert_eq!(BODY.as_bytes(), body.content());
+    }
+
+    #[test]
+    fn obs_text_round_trip() {
+        let mut builder = MessageBuilder::response(StatusCode::Ok);
+        builder.add_field(Field{ name: String::from("X-Name"), value: b"\xff\xfe".to_vec() });
+        let encoded = builder.into_bytes().unwrap();
+        assert!(encoded.windows(12).any(|w| w == b"X-Name: \xff\xfe\r\n"));
+        let response = Response::parse(&mut Cursor::new(encoded)).unwrap();
+        assert_eq!(b"\xff\xfe".to_vec(), response.fields()[0].value);
+    }
+
+    #[test]
+    fn request_round_trip() {
+        for seed in 0..50 {
+            let payload = random_bytes(seed, (seed * seed * 37 % 5000) as usize);
+            let mut builder = MessageBuilder::request(Method::PUT, String::from(REQ_TARGET));
+            builder.add_field(Field::new_contentlength(payload.len()))
+                   .add_body(payload.clone());
+            let mut reader = Cursor::new(builder.into_bytes().unwrap());
+            let request = Request::parse(&mut reader).unwrap();
+            assert_eq!(&Method::PUT, request.method());
+            assert_eq!(REQ_TARGET, request.target());
+            let body = Body::parse_framed(&mut reader, &request.framing().unwrap()).unwrap();
+            assert_eq!(payload.as_slice(), body.content());
+            assert!(reader.fill_buf().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn response_round_trip() {
+        for seed in 0..50 {
+            let payload = random_bytes(seed, (seed * seed * 37 % 5000) as usize);
+            let mut builder = MessageBuilder::response(RSP_CODE);
+            if seed % 2 == 0 {
+                builder.add_field(Field::new_contentlength(payload.len()));
+            } else {
+                builder.add_field2("Transfer-Encoding", "chunked");
+            }
+            builder.add_body(payload.clone());
+            let mut reader = Cursor::new(builder.into_bytes().unwrap());
+            let response = Response::parse(&mut reader).unwrap();
+            assert_eq!(&RSP_CODE, response.status());
+            let body = Body::parse_framed(&mut reader, &response.framing().unwrap()).unwrap();
+            assert_eq!(payload.as_slice(), body.content());
+            assert!(reader.fill_buf().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn streamed_round_trip() {
+        let payload = random_bytes(42, 100_000);
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field2("Transfer-Encoding", "chunked")
+               .add_body_reader(Cursor::new(payload.clone()));
+        let mut reader = Cursor::new(builder.into_bytes().unwrap());
+        let response = Response::parse(&mut reader).unwrap();
+        let mut body = BodyReader::new(&mut reader, &response.framing().unwrap());
+        let mut content = Vec::new();
+        body.read_to_end(&mut content).unwrap();
+        assert_eq!(payload, content);
+    }
+
+    fn any_method() -> impl Strategy<Value = Method> {
+        let known = prop::sample::select(vec![
+            Method::GET, Method::HEAD, Method::POST, Method::PUT, Method::DELETE,
+            Method::TRACE, Method::OPTIONS, Method::CONNECT, Method::PATCH,
+            Method::PROPFIND, Method::MKCOL, Method::LOCK]);
+        let extension = "[-!#$%&'*+.^_`|~0-9A-Za-z]{1,12}"
+            .prop_map(|name| Method::from(&name).unwrap());
+        prop_oneof![known, extension]
+    }
+
+    fn any_status_code() -> impl Strategy<Value = u16> {
+        100u16..1000
+    }
+
+    proptest! {
+        #[test]
+        fn request_round_trip_any(method in any_method(),
+                                  target in "/[-._~%!$&'()*+,;=:@/0-9A-Za-z]{0,40}",
+                                  fields in prop::collection::vec(valid_field(), 0..10),
+                                  payload in prop::collection::vec(any::<u8>(), 0..200)) {
+            let mut builder = MessageBuilder::request(method.clone(), target.clone());
+            for field in &fields {
+                builder.add_field(Field{ name: field.name.clone(), value: field.value.clone() });
+            }
+            builder.add_field(Field::new_contentlength(payload.len()))
+                   .add_body(payload.clone());
+            let mut reader = Cursor::new(builder.into_bytes().unwrap());
+            let request = Request::parse(&mut reader).unwrap();
+            prop_assert_eq!(&method, request.method());
+            prop_assert_eq!(target.as_str(), request.target());
+            prop_assert_eq!(fields.as_slice(), &request.fields()[..fields.len()]);
+            let body = Body::parse_framed(&mut reader, &request.framing().unwrap()).unwrap();
+            prop_assert_eq!(payload.as_slice(), body.content());
+        }
+
+        #[test]
+        fn response_round_trip_any(code in any_status_code(),
+                                   fields in prop::collection::vec(valid_field(), 0..10),
+                                   payload in prop::collection::vec(any::<u8>(), 0..200),
+                                   chunked in any::<bool>()) {
+            let mut builder = MessageBuilder::response(StatusCode::from(code).unwrap());
+            for field in &fields {
+                builder.add_field(Field{ name: field.name.clone(), value: field.value.clone() });
+            }
+            // Bodies of these are dropped, so must not be framed.
+            let bodiless = code < 200 || code == 204 || code == 304;
+            if chunked && !bodiless {
+                builder.add_field2("Transfer-Encoding", "chunked");
+            }
+            builder.add_body(payload.clone());
+            let payload = if bodiless { Vec::new() } else { payload };
+            let mut reader = Cursor::new(builder.into_bytes().unwrap());
+            let response = Response::parse(&mut reader).unwrap();
+            prop_assert_eq!(code, response.status().code());
+            prop_assert_eq!(fields.as_slice(), &response.fields()[..fields.len()]);
+            let body = Body::parse_framed(&mut reader, &response.framing().unwrap()).unwrap();
+            prop_assert_eq!(payload.as_slice(), body.content());
+        }
+
+        #[test]
+        fn parse_never_panics(bytes in prop::collection::vec(any::<u8>(), 0..400)) {
+            let _ = Request::parse(&mut Cursor::new(&bytes));
+            let _ = Response::parse(&mut Cursor::new(&bytes));
+        }
+    }
+
+    // Counts the bytes written to it rather than keeping them.
+    struct CountingWriter {
+        count: u64,
+    }
+
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+            self.count += buf.len() as u64;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> IoResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn streamed_body() {
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field(Field::new_contentlength(BODY.len()))
+               .add_body_reader(Cursor::new(BODY));
+        let expected = format!("\
+            HTTP/1.1 418 I'm a teapot\r\n\
+            Content-Length: 20\r\n\
+            \r\n\
+            {}", BODY);
+        assert_eq!(expected.as_bytes(), without_generated(builder.into_bytes().unwrap()).as_slice());
+    }
+
+    #[test]
+    fn streamed_body_limited_to_length() {
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field(Field::new_contentlength(2))
+               .add_body_reader(Cursor::new(BODY));
+        assert!(builder.into_bytes().unwrap().ends_with(b"\r\n\r\nze"));
+    }
+
+    #[test]
+    fn streamed_body_too_short() {
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field(Field::new_contentlength(100))
+               .add_body_reader(Cursor::new(BODY));
+        let mut message = Vec::new();
+        let error = builder.write_to(&mut message).unwrap_err();
+        assert_eq!(ErrorKind::UnexpectedEof, error.kind());
+    }
+
+    #[test]
+    fn streamed_body_chunked() {
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field2("Transfer-Encoding", "chunked")
+               .add_body_reader(Cursor::new(BODY));
+        let mut reader = Cursor::new(builder.into_bytes().unwrap());
+        let response = Response::parse(&mut reader).unwrap();
+        let body = Body::parse_framed(&mut reader, &response.framing().unwrap());
+        assert_eq!(BODY.as_bytes(), body.unwrap().content());
+    }
+
+    #[test]
+    fn streamed_body_large() {
+        // Far more than any buffer along the way.
+        let length: u64 = 64 * 1024 * 1024;
+        let mut builder = MessageBuilder::response(RSP_CODE);
+        builder.add_field(Field::new_contentlength(length as usize))
+               .add_body_reader(io::repeat(0x55).take(length));
+        let mut writer = CountingWriter{ count: 0 };
+        builder.write_to(&mut writer).unwrap();
+        let head_length = format!("HTTP/1.1 418 I'm a teapot\r\n\
+                                   Content-Length: 67108864\r\n\
+                                   Date: Thu, 01 Jan 1970 00:00:00 GMT\r\n\
+                                   Server: {}\r\n\
+                                   \r\n", SERVER).len();
+        assert_eq!(head_length as u64 + length, writer.count);
+    }
+
+    #[test]
+    fn add_fields_equivalent() {
+        let mut builder1 = MessageBuilder::request(REQ_METHOD, String::from(REQ_TARGET));
+        let mut builder2 = MessageBuilder::request(REQ_METHOD, String::from(REQ_TARGET));
+        builder1.add_field(Field::new(String::from(FIELD_N1), String::from(FIELD_V1)));
+        builder2.add_field2(FIELD_N1, FIELD_V1);
+        assert_eq!(builder1, builder2);
+    }
+}
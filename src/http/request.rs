@@ -1,9 +1,15 @@
 use std::fmt;
-use std::io::BufRead;
+use std::io;
+use std::io::{BufRead, Write};
 use super::Error;
 use super::Field;
+use super::Framing;
+use super::head::{read_head, Head};
+use super::Headers;
+use super::Limits;
 use super::Method;
 use super::RequestStatus;
+use super::Target;
 use super::Version;
 
 #[derive(Debug, PartialEq)]
@@ -17,24 +23,13 @@ impl Request {
         Request{ line, fields }
     }
 
-    pub fn parse<B: BufRead>(reader: &mut B) -> Result<Request, Error>
-    {
-        let mut lines = reader.lines();
-        let first_line = lines.next()
-            .ok_or(Error::new("Unexpected end of stream"))??;
-        let line = RequestStatus::from(first_line)?;
-        let mut fields = Vec::new();
-        loop {
-            let iline = lines.next()
-                .ok_or(Error::new("Unexpected end of stream"))??;
-            if iline.is_empty() {
-                break;  // header finished.
-            }
-            let field = Field::from(iline)?;
-            fields.push(field);
-        }
+    pub fn parse<B: BufRead>(reader: &mut B) -> Result<Request, Error> {
+        Request::parse_with_limits(reader, &Limits::default())
+    }
 
-        Ok(Request{ line, fields })
+    pub fn parse_with_limits<B: BufRead>(reader: &mut B, limits: &Limits)
+            -> Result<Request, Error> {
+        read_head(reader, limits)
     }
 
     pub fn version(&self) -> &Version {
@@ -48,6 +43,54 @@ impl Request {
     pub fn target(&self) -> &str {
         &self.line.target
     }
+
+    pub fn request_target(&self) -> Result<Target, Error> {
+        Target::parse(&self.line.target)
+    }
+
+    pub fn framing(&self) -> Result<Framing, Error> {
+        Framing::from_fields(&self.fields)
+    }
+
+    // Writes the head of the message, up to and including the empty line
+    // which precedes the body.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "{}\r\n", self.line)?;
+        for field in &self.fields {
+            write!(writer, "{}: ", field.name)?;
+            writer.write_all(&field.value)?;
+            writer.write_all(b"\r\n")?;
+        }
+        writer.write_all(b"\r\n")
+    }
+
+    pub fn keep_alive(&self) -> bool {
+        // Connection = 1#connection-option
+        // Connections persist by default from HTTP/1.1 onwards, earlier
+        // versions must ask for it.
+        let options = self.connection();
+        let has_option = |option: &str| options.iter().any(|o| o == option);
+        if has_option("close") {
+            false
+        } else if self.line.version >= Version::new(1, 1).unwrap() {
+            true
+        } else {
+            has_option("keep-alive")
+        }
+    }
+}
+
+impl Head for Request {
+    fn from_head(line: String, fields: Vec<Field>) -> Result<Request, Error> {
+        let line = RequestStatus::from(line)?;
+        Ok(Request{ line, fields })
+    }
+}
+
+impl Headers for Request {
+    fn fields(&self) -> &[Field] {
+        &self.fields
+    }
 }
 
 impl fmt::Display for Request {
@@ -64,6 +107,8 @@ impl fmt::Display for Request {
 
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow, clippy::redundant_static_lifetimes,
+        clippy::vec_init_then_push)]
 mod tests {
     use super::*;
     use super::super::tests::*;
@@ -99,7 +144,7 @@ mod tests {
 
     fn assert_field_eq(name: &str, value: &str, actual: &Field) {
         assert_eq!(String::from(name),  actual.name);
-        assert_eq!(String::from(value), actual.value);
+        assert_eq!(value.as_bytes(), actual.value.as_slice());
     }
 
     fn assert_request(request: &Request, field_num: usize) {
@@ -135,7 +180,7 @@ mod tests {
     fn parse_no_bytes() {
         let mut reader = StringReader::new("");
         let request = Request::parse(&mut reader);
-        assert_parse_error("HTTP parsing error: Unexpected end of stream",
+        assert_parse_error("HTTP parsing error: Unexpected end of stream at byte 0",
                            request);
     }
 
@@ -158,28 +203,28 @@ mod tests {
     fn parse_invalid_status() {
         let mut reader = StringReader::new(REQUEST_ENCODING_INVALID_STATUS);
         let request = Request::parse(&mut reader);
-        assert_parse_error("HTTP parsing error: Invalid request", request);
+        assert_parse_error("HTTP parsing error: Invalid request at byte 0", request);
     }
 
     #[test]
     fn parse_invalid_fields() {
         let mut reader = StringReader::new(REQUEST_ENCODING_INVALID_FIELDS);
         let request = Request::parse(&mut reader);
-        assert_parse_error("HTTP parsing error: Invalid field", request);
+        assert_parse_error("HTTP parsing error: Invalid field at byte 27", request);
     }
 
     #[test]
     fn parse_truncated_status() {
         let mut reader = StringReader::new(&REQUEST_ENCODING_1F[..10]);
         let request = Request::parse(&mut reader);
-        assert_parse_error("HTTP parsing error: Invalid request", request);
+        assert_parse_error("HTTP parsing error: Unexpected end of stream at byte 10", request);
     }
 
     #[test]
     fn parse_truncated_field() {
         let mut reader = StringReader::new(&REQUEST_ENCODING_1F[..30]);
         let request = Request::parse(&mut reader);
-        assert_parse_error("HTTP parsing error: Unexpected end of stream", request);
+        assert_parse_error("HTTP parsing error: Unexpected end of stream at byte 30", request);
     }
 
     #[test]
@@ -191,6 +236,77 @@ mod tests {
         assert_request(&request, 1);
     }
 
+    #[test]
+    fn header() {
+        let mut reader = StringReader::new(REQUEST_ENCODING_2F);
+        let request = Request::parse(&mut reader).unwrap();
+        assert_eq!(Some(FIELD_V1), request.header(FIELD_N1));
+        assert_eq!(Some(FIELD_V2), request.header("HUM"));
+        assert_eq!(None, request.header("bar"));
+    }
+
+    #[test]
+    fn content_length() {
+        let mut reader = StringReader::new(REQUEST_ENCODING_0F);
+        let request = Request::parse(&mut reader).unwrap();
+        assert_eq!(None, request.content_length().unwrap());
+
+        let mut reader = StringReader::new("\
+            PUT / HTTP/1.1\r\n\
+            content-length: 42\r\n\
+            \r\n");
+        let request = Request::parse(&mut reader).unwrap();
+        assert_eq!(Some(42), request.content_length().unwrap());
+
+        let mut reader = StringReader::new("\
+            PUT / HTTP/1.1\r\n\
+            Content-Length: -1\r\n\
+            \r\n");
+        let request = Request::parse(&mut reader).unwrap();
+        assert_parse_error("HTTP parsing error: Invalid content length",
+                           request.content_length());
+    }
+
+    #[test]
+    fn request_target() {
+        let request = parse_str("GET /a%20b?c=d HTTP/1.1\r\n\r\n");
+        let target = request.request_target().unwrap();
+        assert_eq!(vec!["a b"], target.segments());
+        assert_eq!(Some(String::from("d")), target.query_param("c"));
+        let request = parse_str("GET /a/../b HTTP/1.1\r\n\r\n");
+        assert_parse_error("HTTP parsing error: Path traversal not allowed",
+                request.request_target());
+    }
+
+    #[test]
+    fn framing() {
+        let request = parse_str("PUT / HTTP/1.1\r\nContent-Length: 5\r\n\r\n");
+        assert_eq!(Framing::Length(5), request.framing().unwrap());
+        let request = parse_str("PUT / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n");
+        assert_eq!(Framing::Chunked, request.framing().unwrap());
+    }
+
+    fn parse_str(message: &str) -> Request {
+        Request::parse(&mut StringReader::new(message)).unwrap()
+    }
+
+    #[test]
+    fn keep_alive() {
+        assert!(parse_str("GET / HTTP/1.1\r\n\r\n").keep_alive());
+        assert!(parse_str("GET / HTTP/1.1\r\n\
+                           Connection: keep-alive\r\n\r\n").keep_alive());
+        assert!(!parse_str("GET / HTTP/1.1\r\n\
+                            Connection: close\r\n\r\n").keep_alive());
+        assert!(!parse_str("GET / HTTP/1.1\r\n\
+                            connection: Upgrade, CLOSE\r\n\r\n").keep_alive());
+        assert!(!parse_str("GET / HTTP/1.0\r\n\r\n").keep_alive());
+        assert!(parse_str("GET / HTTP/1.0\r\n\
+                           Connection: Keep-Alive\r\n\r\n").keep_alive());
+        assert!(!parse_str("GET / HTTP/1.0\r\n\
+                            Connection: keep-alive, close\r\n\r\n").keep_alive());
+        assert!(parse_str("GET / HTTP/2.0\r\n\r\n").keep_alive());
+    }
+
     #[test]
     fn display() {
         let mut reader = StringReader::new(&REQUEST_ENCODING_0F);
@@ -1,5 +1,5 @@
 use std::fmt;
-use super::Error;
+use super::{Error, ErrorKind};
 use super::Method;
 use super::Version;
 
@@ -21,15 +21,17 @@ impl RequestStatus {
         // Split by space.
         let parts: Vec<&str> = line.split(' ').collect();
         if parts.len() != 3 {
-            return Error::err("Invalid request");
+            return Err(Error::new(ErrorKind::StartLine, "Invalid request").at(0));
         }
-        // Parse method, target and version.
-        let method = Method::from(parts[0])?;
+        // Parse method, target and version, giving the offset of any error.
+        let target_start = parts[0].len() + 1;
+        let version_start = target_start + parts[1].len() + 1;
+        let method = Method::from(parts[0]).map_err(|error| error.at(0))?;
         let target = String::from(parts[1]);
         if target.is_empty() {
-            return Error::err("Invalid target");
+            return Err(Error::new(ErrorKind::StartLine, "Invalid target").at(target_start));
         }
-        let version = Version::from(parts[2])?;
+        let version = Version::from(parts[2]).map_err(|error| error.at(version_start))?;
 
         Ok(RequestStatus{ version, method, target })
     }
@@ -81,52 +83,58 @@ mod tests {
     fn from_invalid_empty_string() {
         // Empty string.
         let status = RequestStatus::from(String::from(""));
-        assert_parse_error("HTTP parsing error: Invalid request", status);
+        assert_parse_error("HTTP parsing error: Invalid request at byte 0", status);
 
         // String of empty parameters.
         let status = RequestStatus::from(String::from("  "));
-        assert_parse_error("HTTP parsing error: Invalid method", status);
+        assert_parse_error("HTTP parsing error: Invalid method at byte 0", status);
     }
 
     #[test]
     fn from_invalid_too_few_args() {
         let status = RequestStatus::from(String::from("CONNECT /"));
-        assert_parse_error("HTTP parsing error: Invalid request", status);
+        assert_parse_error("HTTP parsing error: Invalid request at byte 0", status);
     }
 
     #[test]
     fn from_invalid_too_many_args() {
         let status = RequestStatus::from(String::from("HEAD /a /b HTTP/1.1"));
-        assert_parse_error("HTTP parsing error: Invalid request", status);
+        assert_parse_error("HTTP parsing error: Invalid request at byte 0", status);
+    }
+
+    #[test]
+    fn from_extension_method() {
+        let status = RequestStatus::from(String::from("FOO /foo HTTP/1.1")).unwrap();
+        assert_eq!(Method::Extension(String::from("FOO")), status.method);
     }
 
     #[test]
     fn from_invalid_method_arg() {
-        // Non-existant method.
-        let status = RequestStatus::from(String::from("FOO /foo HTTP/1.1"));
-        assert_parse_error("HTTP parsing error: Invalid method", status);
+        // Not a token.
+        let status = RequestStatus::from(String::from("FO{O} /foo HTTP/1.1"));
+        assert_parse_error("HTTP parsing error: Invalid method at byte 0", status);
 
         // Empty method.
         let status = RequestStatus::from(String::from(" /foo HTTP/1.1"));
-        assert_parse_error("HTTP parsing error: Invalid method", status);
+        assert_parse_error("HTTP parsing error: Invalid method at byte 0", status);
     }
 
     #[test]
     fn from_invalid_target_arg() {
         // Empty target.
         let status = RequestStatus::from(String::from("GET  HTTP/1.1"));
-        assert_parse_error("HTTP parsing error: Invalid target", status);
+        assert_parse_error("HTTP parsing error: Invalid target at byte 4", status);
     }
 
     #[test]
     fn from_invalid_version_arg() {
         // Non-numeric version.
         let status = RequestStatus::from(String::from("GET /foo HTTP/a.1"));
-        assert_parse_error("HTTP parsing error: Invalid version", status);
+        assert_parse_error("HTTP parsing error: Invalid version at byte 9", status);
 
         // Empty version.
         let status = RequestStatus::from(String::from("GET /foo "));
-        assert_parse_error("HTTP parsing error: Invalid version", status);
+        assert_parse_error("HTTP parsing error: Invalid version at byte 9", status);
     }
 
     #[test]
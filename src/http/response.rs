@@ -1,7 +1,12 @@
 use std::fmt;
-use std::io::BufRead;
+use std::io;
+use std::io::{BufRead, Write};
 use super::Error;
 use super::Field;
+use super::Framing;
+use super::head::{read_head, Head};
+use super::Headers;
+use super::Limits;
 use super::ResponseStatus;
 use super::StatusCode;
 use super::Version;
@@ -17,24 +22,13 @@ impl Response {
         Response{ line, fields }
     }
 
-    pub fn parse<B: BufRead>(reader: &mut B) -> Result<Response, Error>
-    {
-        let mut lines = reader.lines();
-        let first_line = lines.next()
-            .ok_or(Error::new("Unexpected end of stream"))??;
-        let line = ResponseStatus::from(first_line)?;
-        let mut fields = Vec::new();
-        loop {
-            let iline = lines.next()
-                .ok_or(Error::new("Unexpected end of stream"))??;
-            if iline.is_empty() {
-                break;  // header finished.
-            }
-            let field = Field::from(iline)?;
-            fields.push(field);
-        }
+    pub fn parse<B: BufRead>(reader: &mut B) -> Result<Response, Error> {
+        Response::parse_with_limits(reader, &Limits::default())
+    }
 
-        Ok(Response{ line, fields })
+    pub fn parse_with_limits<B: BufRead>(reader: &mut B, limits: &Limits)
+            -> Result<Response, Error> {
+        read_head(reader, limits)
     }
 
     pub fn version(&self) -> &Version {
@@ -44,6 +38,53 @@ impl Response {
     pub fn status(&self) -> &StatusCode {
         &self.line.status
     }
+
+    // The reason phrase as received, which may not be the usual one.
+    pub fn reason(&self) -> &str {
+        &self.line.reason
+    }
+
+    pub fn framing(&self) -> Result<Framing, Error> {
+        Framing::from_fields(&self.fields)
+    }
+
+    // Writes the head of the message, up to and including the empty line
+    // which precedes the body.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "{}\r\n", self.line)?;
+        for field in &self.fields {
+            write!(writer, "{}: ", field.name)?;
+            writer.write_all(&field.value)?;
+            writer.write_all(b"\r\n")?;
+        }
+        writer.write_all(b"\r\n")
+    }
+
+    // Whether the server will keep the connection open for another request.
+    pub fn keep_alive(&self) -> bool {
+        let options = self.connection();
+        let has_option = |option: &str| options.iter().any(|o| o == option);
+        if has_option("close") {
+            false
+        } else if self.line.version >= Version::new(1, 1).unwrap() {
+            true
+        } else {
+            has_option("keep-alive")
+        }
+    }
+}
+
+impl Head for Response {
+    fn from_head(line: String, fields: Vec<Field>) -> Result<Response, Error> {
+        let line = ResponseStatus::from(line)?;
+        Ok(Response{ line, fields })
+    }
+}
+
+impl Headers for Response {
+    fn fields(&self) -> &[Field] {
+        &self.fields
+    }
 }
 
 impl fmt::Display for Response {
@@ -60,6 +101,8 @@ impl fmt::Display for Response {
 
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow, clippy::redundant_static_lifetimes,
+        clippy::vec_init_then_push)]
 mod tests {
     use super::*;
     use super::super::tests::*;
@@ -94,7 +137,7 @@ mod tests {
 
     fn assert_field_eq(name: &str, value: &str, actual: &Field) {
         assert_eq!(String::from(name),  actual.name);
-        assert_eq!(String::from(value), actual.value);
+        assert_eq!(value.as_bytes(), actual.value.as_slice());
     }
 
     fn assert_response(response: &Response, field_num: usize) {
@@ -128,7 +171,7 @@ mod tests {
     fn parse_no_bytes() {
         let mut reader = StringReader::new("");
         let response = Response::parse(&mut reader);
-        assert_parse_error("HTTP parsing error: Unexpected end of stream",
+        assert_parse_error("HTTP parsing error: Unexpected end of stream at byte 0",
                            response);
     }
 
@@ -151,28 +194,28 @@ mod tests {
     fn parse_invalid_status() {
         let mut reader = StringReader::new(RESPONSE_ENCODING_INVALID_STATUS);
         let response = Response::parse(&mut reader);
-        assert_parse_error("HTTP parsing error: Invalid status", response);
+        assert_parse_error("HTTP parsing error: Invalid status at byte 9", response);
     }
 
     #[test]
     fn parse_invalid_fields() {
         let mut reader = StringReader::new(RESPONSE_ENCODING_INVALID_FIELDS);
         let response = Response::parse(&mut reader);
-        assert_parse_error("HTTP parsing error: Invalid field", response);
+        assert_parse_error("HTTP parsing error: Invalid field at byte 35", response);
     }
 
     #[test]
     fn parse_truncated_status() {
         let mut reader = StringReader::new(&RESPONSE_ENCODING_1F[..10]);
         let response = Response::parse(&mut reader);
-        assert_parse_error("HTTP parsing error: Invalid response", response);
+        assert_parse_error("HTTP parsing error: Unexpected end of stream at byte 10", response);
     }
 
     #[test]
     fn parse_truncated_field() {
         let mut reader = StringReader::new(&RESPONSE_ENCODING_1F[..30]);
         let response = Response::parse(&mut reader);
-        assert_parse_error("HTTP parsing error: Unexpected end of stream", response);
+        assert_parse_error("HTTP parsing error: Unexpected end of stream at byte 30", response);
     }
 
     #[test]
@@ -184,25 +227,54 @@ mod tests {
         assert_response(&response, 1);
     }
 
+    #[test]
+    fn header() {
+        let mut reader = StringReader::new(RESPONSE_ENCODING_2F);
+        let response = Response::parse(&mut reader).unwrap();
+        assert_eq!(Some(FIELD_V1), response.header("Foo"));
+        assert_eq!(Some(FIELD_V2), response.header(FIELD_N2));
+        assert_eq!(None, response.header("bar"));
+        assert_eq!(None, response.content_length().unwrap());
+    }
+
     #[test]
     fn display() {
         let mut reader = StringReader::new(&RESPONSE_ENCODING_0F);
         let response = Response::parse(&mut reader).unwrap();
         assert_eq!(RESPONSE_ENCODING_0F, format!("{}", response));
 
+        // Reason phrases are kept as they were.
         let mut reader = StringReader::new(RESPONSE_ENCODING_1F);
         let response = Response::parse(&mut reader).unwrap();
-        assert_eq!("HTTP/1.1 413 Payload Too Large\r\n\
+        assert_eq!("HTTP/1.1 413 Payload too girthy\r\n\
                     foo: bar\r\n\
                     \r\n", format!("{}", response));
 
         let mut reader = StringReader::new(RESPONSE_ENCODING_2F);
         let response = Response::parse(&mut reader).unwrap();
-        assert_eq!("HTTP/1.1 413 Payload Too Large\r\n\
+        assert_eq!("HTTP/1.1 413 omg\r\n\
                     foo: bar\r\n\
                     hum: bug\r\n\
                     \r\n", format!("{}", response));
     }
 
+    #[test]
+    fn relayed() {
+        let message = "HTTP/1.1 299 Vendor Okay\r\nfoo: bar\r\n\r\n";
+        let response = Response::parse(&mut StringReader::new(message)).unwrap();
+        assert_eq!(&StatusCode::from(299).unwrap(), response.status());
+        assert_eq!("Vendor Okay", response.reason());
+        let mut relayed = Vec::new();
+        response.write_to(&mut relayed).unwrap();
+        assert_eq!(message.as_bytes(), relayed.as_slice());
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn keep_alive() {
+        let parse = |message: &str| Response::parse(&mut StringReader::new(message)).unwrap();
+        assert!(parse("HTTP/1.1 200 OK\r\n\r\n").keep_alive());
+        assert!(!parse("HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n").keep_alive());
+        assert!(!parse("HTTP/1.0 200 OK\r\n\r\n").keep_alive());
+        assert!(parse("HTTP/1.0 200 OK\r\nConnection: Keep-Alive\r\n\r\n").keep_alive());
+    }
+}
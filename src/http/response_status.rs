@@ -0,0 +1,202 @@
+use std::fmt;
+use super::{Error, ErrorKind};
+use super::StatusCode;
+use super::Version;
+
+#[derive(Debug, PartialEq)]
+pub struct ResponseStatus {
+    pub version: Version,
+    pub status: StatusCode,
+    // The phrase as received, so that it's kept when the response is sent
+    // on. It means nothing in itself.
+    pub reason: String,
+}
+
+impl ResponseStatus {
+    pub fn new(status: StatusCode) -> ResponseStatus {
+        let version = Version::new(1, 1).unwrap();
+        ResponseStatus{ version, status, reason: String::from(status.phrase()) }
+    }
+
+    pub fn from(line: String) -> Result<ResponseStatus, Error> {
+        // status-line = HTTP-version SP status-code SP reason-phrase CRLF
+        // Split by space.
+        let parts: Vec<&str> = line.splitn(3, ' ').collect();
+        if parts.len() != 3 {
+            return Err(Error::new(ErrorKind::StartLine, "Invalid response").at(0));
+        }
+        // Parse version and status, giving the offset of any error.
+        let version = Version::from(parts[0]).map_err(|error| error.at(0))?;
+        let invalid_status = || {
+            Error::new(ErrorKind::StartLine, "Invalid status").at(parts[0].len() + 1)
+        };
+        // status-code = 3DIGIT
+        if parts[1].len() != 3 || !parts[1].bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid_status());
+        }
+        let status: u16 = parts[1].parse().map_err(|_| invalid_status())?;
+        let status = StatusCode::from(status).ok_or_else(invalid_status)?;
+        // reason-phrase = *( HTAB / SP / VCHAR / obs-text )
+        let reason = parts[2];
+        if let Some(offset) = reason.find(|c: char| c.is_ascii_control() && c != '\t') {
+            return Err(Error::new(ErrorKind::StartLine, "Invalid reason phrase")
+                .at(parts[0].len() + parts[1].len() + 2 + offset));
+        }
+
+        Ok(ResponseStatus{ version, status, reason: String::from(reason) })
+    }
+}
+
+impl fmt::Display for ResponseStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(&format!("{} {} {}", self.version, self.status.code(), self.reason))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    fn assert_status_eq(version: &Version, status: &StatusCode,
+            actual: &ResponseStatus) {
+        assert_eq!(version, &actual.version);
+        assert_eq!(status, &actual.status);
+    }
+
+    #[test]
+    fn new() {
+        let status = ResponseStatus::new(StatusCode::Ok);
+        assert_status_eq(&Version::new(1, 1).unwrap(), &StatusCode::Ok,
+            &status);
+        assert_eq!("OK", status.reason);
+        assert_eq!("", ResponseStatus::new(StatusCode::from(299).unwrap()).reason);
+    }
+
+    #[test]
+    fn from_unknown_code() {
+        let status = ResponseStatus::from(String::from("HTTP/1.1 299 Vendor Okay")).unwrap();
+        assert_status_eq(&Version::new(1,1).unwrap(), &StatusCode::from(299).unwrap(), &status);
+        assert!(status.status.is_success());
+        assert_eq!("Vendor Okay", status.reason);
+
+        let status = ResponseStatus::from(String::from("HTTP/1.1 420 Enhance Your Calm"))
+            .unwrap();
+        assert_eq!(StatusCode::from(420).unwrap(), status.status);
+        assert!(status.status.is_client_error());
+    }
+
+    #[test]
+    fn from_valid_string() {
+        // Test an easy one.
+        let input = String::from("HTTP/1.1 200 OK");
+        let status = ResponseStatus::from(input).unwrap();
+        assert_status_eq(&Version::new(1,1).unwrap(), &StatusCode::Ok,
+            &status);
+
+        // Test a slightly harder one.
+        let input = String::from("HTTP/0.2 418 I'm a teapot");
+        let status = ResponseStatus::from(input).unwrap();
+        assert_status_eq(&Version::new(0,2).unwrap(), &StatusCode::ImATeapot,
+            &status);
+    }
+
+    #[test]
+    fn from_invalid_empty_string() {
+        // Empty string.
+        let status = ResponseStatus::from(String::from(""));
+        assert_parse_error("HTTP parsing error: Invalid response at byte 0", status);
+
+        // String of empty parameters.
+        let status = ResponseStatus::from(String::from("  "));
+        assert_parse_error("HTTP parsing error: Invalid version at byte 0", status);
+    }
+
+    #[test]
+    fn from_invalid_too_few_args() {
+        let status = ResponseStatus::from(String::from("HTTP/1.1 200"));
+        assert_parse_error("HTTP parsing error: Invalid response at byte 0", status);
+    }
+
+    #[test]
+    fn from_invalid_version_arg() {
+        // Non-numeric version.
+        let status = ResponseStatus::from(String::from("HTTP/1.I 200 OK"));
+        assert_parse_error("HTTP parsing error: Invalid version at byte 0", status);
+
+        // Empty version.
+        let status = ResponseStatus::from(String::from(" 200 OK"));
+        assert_parse_error("HTTP parsing error: Invalid version at byte 0", status);
+    }
+
+    #[test]
+    fn from_invalid_statuscode_arg() {
+        // Too-long numeric status code.
+        let status = ResponseStatus::from(String::from("HTTP/1.1 1234 OK"));
+        assert_parse_error("HTTP parsing error: Invalid status at byte 9", status);
+
+        // Too-short numeric status code.
+        let status = ResponseStatus::from(String::from("HTTP/1.1 12 OK"));
+        assert_parse_error("HTTP parsing error: Invalid status at byte 9", status);
+
+        // Codes begin with their class.
+        let status = ResponseStatus::from(String::from("HTTP/1.1 020 OK"));
+        assert_parse_error("HTTP parsing error: Invalid status at byte 9", status);
+
+        // Signed status code.
+        let status = ResponseStatus::from(String::from("HTTP/1.1 +20 OK"));
+        assert_parse_error("HTTP parsing error: Invalid status at byte 9", status);
+
+        // Non-numeric status code.
+        let status = ResponseStatus::from(String::from("HTTP/1.1 #420 OK"));
+        assert_parse_error("HTTP parsing error: Invalid status at byte 9", status);
+
+        // Empty status code.
+        let status = ResponseStatus::from(String::from("HTTP/1.1  OK"));
+        assert_parse_error("HTTP parsing error: Invalid status at byte 9", status);
+    }
+
+    #[test]
+    fn from_invalid_phrase_arg() {
+        let status = ResponseStatus::from(String::from("HTTP/1.1 200 O\x07K"));
+        assert_parse_error("HTTP parsing error: Invalid reason phrase at byte 14", status);
+    }
+
+    #[test]
+    fn from_keeps_phrase_arg() {
+        // The phrase doesn't change the meaning of the code, but is kept as
+        // it was.
+        // Empty phrase.
+        let input = String::from("HTTP/1.1 200 ");
+        let status = ResponseStatus::from(input).unwrap();
+        assert_status_eq(&Version::new(1,1).unwrap(), &StatusCode::Ok,
+            &status);
+        assert_eq!("", status.reason);
+
+        // Mismatched phrase and status code.
+        let input = String::from("HTTP/1.1 404 Payload Too Large");
+        let status = ResponseStatus::from(input).unwrap();
+        assert_status_eq(&Version::new(1,1).unwrap(), &StatusCode::NotFound,
+            &status);
+        assert_eq!("Payload Too Large", status.reason);
+
+        // Totally bogus phrase.
+        let input = String::from("HTTP/1.1 410 420\tx  ");
+        let status = ResponseStatus::from(input).unwrap();
+        assert_status_eq(&Version::new(1,1).unwrap(), &StatusCode::Gone,
+            &status);
+        assert_eq!("420\tx  ", status.reason);
+    }
+
+    #[test]
+    fn display() {
+        for input in &["HTTP/1.2 413 Payload Too Large", "HTTP/1.1 200 All good",
+                       "HTTP/1.1 599 "] {
+            let status = ResponseStatus::from(String::from(*input)).unwrap();
+            assert_eq!(*input, format!("{}", status));
+        }
+        assert_eq!("HTTP/1.1 418 I'm a teapot",
+                   format!("{}", ResponseStatus::new(StatusCode::ImATeapot)));
+    }
+}
@@ -0,0 +1,196 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnknownCode(u16);
+
+// The codes which are known, with their canonical reason phrases, from which
+// the enum and its conversions are generated.
+macro_rules! status_codes {
+    ($($name:ident = $code:literal, $phrase:literal;)*) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum StatusCode {
+            $($name,)*
+            // Any other three digit code, such as one of a vendor's own. Only
+            // StatusCode::from makes these, so it's never one of the codes
+            // above.
+            Other(UnknownCode),
+        }
+
+        impl StatusCode {
+            fn known(code: u16) -> Option<StatusCode> {
+                match code {
+                    $($code => Some(StatusCode::$name),)*
+                    _ => None,
+                }
+            }
+
+            pub fn code(&self) -> u16 {
+                match *self {
+                    $(StatusCode::$name => $code,)*
+                    StatusCode::Other(UnknownCode(code)) => code,
+                }
+            }
+
+            // The canonical phrase, which is empty for codes which aren't
+            // known.
+            pub fn phrase(&self) -> &'static str {
+                match *self {
+                    $(StatusCode::$name => $phrase,)*
+                    StatusCode::Other(_) => "",
+                }
+            }
+        }
+    };
+}
+
+status_codes! {
+    // 100: Informational.
+    Continue                    = 100, "Continue";
+    SwitchingProtocols          = 101, "Switching Protocols";
+    Processing                  = 102, "Processing";
+    EarlyHints                  = 103, "Early Hints";
+    // 200: Success.
+    Ok                          = 200, "OK";
+    Created                     = 201, "Created";
+    Accepted                    = 202, "Accepted";
+    NonAuthoritativeInformation = 203, "Non-Authoritative Information";
+    NoContent                   = 204, "No Content";
+    ResetContent                = 205, "Reset Content";
+    PartialContent              = 206, "Partial Content";
+    MultiStatus                 = 207, "Multi-Status";
+    AlreadyReported             = 208, "Already Reported";
+    IMUsed                      = 226, "IM Used";
+    // 300: Redirection.
+    MultipleChoices             = 300, "Multiple Choices";
+    MovedPermanently            = 301, "Moved Permanently";
+    Found                       = 302, "Found";
+    SeeOther                    = 303, "See Other";
+    NotModified                 = 304, "Not Modified";
+    UseProxy                    = 305, "Use Proxy";
+    SwitchProxy                 = 306, "Switch Proxy";
+    TemporaryRedirect           = 307, "Temporary Redirect";
+    PermanentRedirect           = 308, "Permanent Redirect";
+    // 400: Client errors.
+    BadRequest                  = 400, "Bad Request";
+    Unauthorized                = 401, "Unauthorized";
+    PaymentRequired             = 402, "Payment Required";
+    Forbidden                   = 403, "Forbidden";
+    NotFound                    = 404, "Not Found";
+    MethodNotAllowed            = 405, "Method Not Allowed";
+    NotAcceptable               = 406, "Not Acceptable";
+    ProxyAuthenticationRequired = 407, "Proxy Authentication Required";
+    RequestTimeout              = 408, "Request Timeout";
+    Conflict                    = 409, "Conflict";
+    Gone                        = 410, "Gone";
+    LengthRequired              = 411, "Length Required";
+    PreconditionFailed          = 412, "Precondition Failed";
+    PayloadTooLarge             = 413, "Payload Too Large";
+    URITooLong                  = 414, "URI Too Long";
+    UnsupportedMediaType        = 415, "Unsupported Media Type";
+    RangeNotSatisfiable         = 416, "Range Not Satisfiable";
+    ExpectationFailed           = 417, "Expectation Failed";
+    ImATeapot                   = 418, "I'm a teapot";
+    MisdirectedRequest          = 421, "Misdirected Request";
+    UnprocessableEntity         = 422, "Unprocessable Entity";
+    Locked                      = 423, "Locked";
+    FailedDependency            = 424, "Failed Dependency";
+    UpgradeRequired             = 426, "Upgrade Required";
+    PreconditionRequired        = 428, "Precondition Required";
+    TooManyRequests             = 429, "Too Many Requests";
+    RequestHeaderFieldsTooLarge = 431, "Request Header Fields Too Large";
+    UnavailableForLegalReasons  = 451, "Unavailable For Legal Reasons";
+    // 500: Server errors.
+    InternalServerError         = 500, "Internal Server Error";
+    NotImplemented              = 501, "Not Implemented";
+    BadGateway                  = 502, "Bad Gateway";
+    ServiceUnavailable          = 503, "Service Unavailable";
+    GatewayTimeout              = 504, "Gateway Timeout";
+    HTTPVersionNotSupported     = 505, "HTTP Version Not Supported";
+    VariantAlsoNegotiates       = 506, "Variant Also Negotiates";
+    InsufficientStorage         = 507, "Insufficient Storage";
+    LoopDetected                = 508, "Loop Detected";
+    NotExtended                 = 510, "Not Extended";
+    NetworkAuthenticationRequired = 511, "Network Authentication Required";
+}
+
+impl StatusCode {
+    pub fn from(code: u16) -> Option<StatusCode> {
+        // status-code = 3DIGIT
+        if !(100..=999).contains(&code) {
+            return None;
+        }
+        Some(StatusCode::known(code).unwrap_or(StatusCode::Other(UnknownCode(code))))
+    }
+
+    // 1xx: the request was received, and the final response is to follow.
+    pub fn is_informational(&self) -> bool {
+        self.code() / 100 == 1
+    }
+
+    // 2xx: the request was received, understood and accepted.
+    pub fn is_success(&self) -> bool {
+        self.code() / 100 == 2
+    }
+
+    // 3xx: the client must do more to complete the request.
+    pub fn is_redirection(&self) -> bool {
+        self.code() / 100 == 3
+    }
+
+    // 4xx: the request was at fault.
+    pub fn is_client_error(&self) -> bool {
+        self.code() / 100 == 4
+    }
+
+    // 5xx: the server failed to fulfil a valid request.
+    pub fn is_server_error(&self) -> bool {
+        self.code() / 100 == 5
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mappings() {
+        // Every three digit code has a status, and the known ones a phrase
+        // too. Also check the assignment is symmetric.
+        let mut known_count = 0;
+        for i in 100..1000 {
+            let status = StatusCode::from(i).unwrap();
+            assert_eq!(i, status.code());
+            if status.phrase().is_empty() {
+                assert_eq!(StatusCode::Other(UnknownCode(i)), status);
+            } else {
+                known_count += 1;
+            }
+        }
+        assert_eq!(62, known_count);
+        assert_eq!(Some(StatusCode::Ok), StatusCode::from(200));
+        assert_eq!(Some(StatusCode::Other(UnknownCode(299))), StatusCode::from(299));
+        for i in &[0, 1, 99, 1000, 1234] {
+            assert_eq!(None, StatusCode::from(*i));
+        }
+    }
+
+    #[test]
+    fn classes() {
+        let class = |code| {
+            let status = StatusCode::from(code).unwrap();
+            [status.is_informational(), status.is_success(), status.is_redirection(),
+             status.is_client_error(), status.is_server_error()]
+                .iter().position(|&is| is)
+        };
+        assert_eq!(Some(0), class(100));
+        assert_eq!(Some(0), class(199));
+        assert_eq!(Some(1), class(200));
+        assert_eq!(Some(1), class(299));
+        assert_eq!(Some(2), class(304));
+        assert_eq!(Some(3), class(418));
+        assert_eq!(Some(3), class(499));
+        assert_eq!(Some(4), class(500));
+        assert_eq!(Some(4), class(599));
+        assert_eq!(None, class(600));
+        assert_eq!(None, class(999));
+    }
+}
@@ -0,0 +1,290 @@
+use super::{Error, ErrorKind};
+
+#[derive(Debug, PartialEq)]
+pub enum TargetForm {
+    Origin,     // /where?q=now
+    Absolute,   // http://www.example.org/pub/WWW/TheProject.html
+    Authority,  // www.example.com:80 (CONNECT only)
+    Asterisk,   // * (server-wide OPTIONS only)
+}
+
+// A parsed request-target:
+//   request-target = origin-form / absolute-form / authority-form / asterisk-form
+//   origin-form    = absolute-path [ "?" query ]
+//   absolute-form  = absolute-URI
+//   authority-form = authority
+//   asterisk-form  = "*"
+// Path segments are percent-decoded. Targets with dot-segments ("." or "..",
+// however they are encoded) are rejected outright, as are segments which
+// decode to contain a path separator, so that no segment can be used to
+// reach outside of wherever it is looked up.
+#[derive(Debug, PartialEq)]
+pub struct Target {
+    form: TargetForm,
+    scheme: Option<String>,
+    authority: Option<String>,
+    path: String,
+    query: Option<String>,
+    segments: Vec<String>,
+}
+
+impl Target {
+    pub fn parse(target: &str) -> Result<Target, Error> {
+        // Fragments are never sent, and nor is whitespace or anything
+        // outside of ASCII.
+        if target.is_empty() ||
+                !target.bytes().all(|b| b.is_ascii_graphic() && b != b'#') {
+            return Error::err(ErrorKind::StartLine, "Invalid target");
+        }
+
+        if target == "*" {
+            return Ok(Target{ form: TargetForm::Asterisk, scheme: None,
+                              authority: None, path: String::new(),
+                              query: None, segments: Vec::new() });
+        }
+
+        let (form, scheme, authority, rest) = if target.starts_with('/') {
+            (TargetForm::Origin, None, None, target)
+        } else if let Some(sep) = target.find("://") {
+            // absolute-URI = scheme ":" hier-part [ "?" query ]
+            // hier-part    = "//" authority path-abempty
+            let scheme = &target[..sep];
+            let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic()) &&
+                scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+            if !valid_scheme {
+                return Error::err(ErrorKind::StartLine, "Invalid target");
+            }
+            let rest = &target[sep+3..];
+            let end = rest.find(['/', '?']).unwrap_or(rest.len());
+            if end == 0 {
+                return Error::err(ErrorKind::StartLine, "Invalid target");
+            }
+            (TargetForm::Absolute, Some(scheme.to_ascii_lowercase()),
+             Some(String::from(&rest[..end])), &rest[end..])
+        } else {
+            // authority-form = uri-host ":" port
+            let valid_authority = target.rfind(':').is_some_and(|sep| {
+                sep > 0 && sep + 1 < target.len() &&
+                    target[sep+1..].bytes().all(|b| b.is_ascii_digit())
+            }) && !target.contains(['/', '?', '@']);
+            if !valid_authority {
+                return Error::err(ErrorKind::StartLine, "Invalid target");
+            }
+            return Ok(Target{ form: TargetForm::Authority, scheme: None,
+                              authority: Some(String::from(target)),
+                              path: String::new(), query: None,
+                              segments: Vec::new() });
+        };
+
+        let (path, query) = match rest.find('?') {
+            Some(sep) => (&rest[..sep], Some(String::from(&rest[sep+1..]))),
+            None => (rest, None),
+        };
+        // An absolute-URI may have an empty path, which means "/".
+        let path = if path.is_empty() { "/" } else { path };
+        let segments = path[1..].split('/')
+            .map(|segment| {
+                let segment = percent_decode(segment, false)
+                    .ok_or(Error::new(ErrorKind::StartLine, "Invalid target"))?;
+                if segment == "." || segment == ".." {
+                    return Error::err(ErrorKind::StartLine, "Path traversal not allowed");
+                }
+                if segment.contains(['/', '\\', '\0']) {
+                    return Error::err(ErrorKind::StartLine, "Invalid target");
+                }
+                Ok(segment)
+            })
+            .collect::<Result<Vec<String>, Error>>()?;
+
+        Ok(Target{ form, scheme, authority, path: String::from(path), query,
+                   segments })
+    }
+
+    pub fn form(&self) -> &TargetForm {
+        &self.form
+    }
+
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.as_deref()
+    }
+
+    pub fn authority(&self) -> Option<&str> {
+        self.authority.as_deref()
+    }
+
+    // The path as sent, still percent-encoded.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // The query as sent, still percent-encoded.
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    // The decoded path segments, e.g. ["a b", "c"] for /a%20b/c
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    // The decoded query parameters, as sent by HTML forms:
+    //   name=value&name=value
+    // with "+" standing for a space. Parameters which cannot be decoded are
+    // dropped.
+    pub fn query_params(&self) -> Vec<(String, String)> {
+        let query = self.query.as_deref().unwrap_or("");
+        query.split('&')
+            .filter(|param| !param.is_empty())
+            .filter_map(|param| {
+                let (name, value) = match param.find('=') {
+                    Some(sep) => (&param[..sep], &param[sep+1..]),
+                    None => (param, ""),
+                };
+                Some((percent_decode(name, true)?, percent_decode(value, true)?))
+            })
+            .collect()
+    }
+
+    // The value of the first query parameter with the name.
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        self.query_params().into_iter()
+            .find(|param| param.0 == name)
+            .map(|param| param.1)
+    }
+}
+
+// Decodes %XX escapes, and "+" as a space if plus_as_space is set. Returns
+// None if an escape is invalid or the result isn't UTF-8.
+fn percent_decode(component: &str, plus_as_space: bool) -> Option<String> {
+    let mut bytes = Vec::with_capacity(component.len());
+    let mut iter = component.bytes();
+    while let Some(b) = iter.next() {
+        match b {
+            b'+' if plus_as_space => bytes.push(b' '),
+            b'%' => {
+                let hi = (iter.next()? as char).to_digit(16)?;
+                let lo = (iter.next()? as char).to_digit(16)?;
+                bytes.push((hi * 16 + lo) as u8);
+            },
+            _ => bytes.push(b),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    fn segments(target: &str) -> Vec<String> {
+        Target::parse(target).unwrap().segments().to_vec()
+    }
+
+    #[test]
+    fn origin_form() {
+        let target = Target::parse("/foo/bar.git/info/lfs?a=1").unwrap();
+        assert_eq!(&TargetForm::Origin, target.form());
+        assert_eq!(None, target.scheme());
+        assert_eq!(None, target.authority());
+        assert_eq!("/foo/bar.git/info/lfs", target.path());
+        assert_eq!(Some("a=1"), target.query());
+        assert_eq!(vec!["foo", "bar.git", "info", "lfs"], target.segments());
+
+        assert_eq!(vec![""], segments("/"));
+        assert_eq!(vec!["a", ""], segments("/a/"));
+        assert_eq!(vec!["", "a"], segments("//a"));
+        assert_eq!(None, Target::parse("/a").unwrap().query());
+        assert_eq!(Some(""), Target::parse("/a?").unwrap().query());
+    }
+
+    #[test]
+    fn absolute_form() {
+        let target = Target::parse("HTTP://example.com:8080/a/b?c=d").unwrap();
+        assert_eq!(&TargetForm::Absolute, target.form());
+        assert_eq!(Some("http"), target.scheme());
+        assert_eq!(Some("example.com:8080"), target.authority());
+        assert_eq!("/a/b", target.path());
+        assert_eq!(Some("c=d"), target.query());
+
+        let target = Target::parse("http://example.com?x").unwrap();
+        assert_eq!("/", target.path());
+        assert_eq!(Some("x"), target.query());
+
+        for target in &["http:///a", "1http://a/", "ht tp://a/", "://a/"] {
+            assert_parse_error("HTTP parsing error: Invalid target",
+                    Target::parse(target));
+        }
+    }
+
+    #[test]
+    fn authority_form() {
+        let target = Target::parse("www.example.com:80").unwrap();
+        assert_eq!(&TargetForm::Authority, target.form());
+        assert_eq!(Some("www.example.com:80"), target.authority());
+        assert_eq!("", target.path());
+        assert!(target.segments().is_empty());
+
+        for target in &["www.example.com", "example.com:", ":80", "a:b",
+                        "user@example.com:80", "example.com:80/"] {
+            assert_parse_error("HTTP parsing error: Invalid target",
+                    Target::parse(target));
+        }
+    }
+
+    #[test]
+    fn asterisk_form() {
+        let target = Target::parse("*").unwrap();
+        assert_eq!(&TargetForm::Asterisk, target.form());
+        assert!(target.segments().is_empty());
+    }
+
+    #[test]
+    fn invalid_characters() {
+        for target in &["", "/a b", "/a#b", "/a\tb", "/caf\u{e9}"] {
+            assert_parse_error("HTTP parsing error: Invalid target",
+                    Target::parse(target));
+        }
+    }
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(vec!["a b", "c+d", "\u{e9}"], segments("/a%20b/c+d/%C3%A9"));
+        for target in &["/a%2", "/a%zz", "/%ff", "/a%2Fb", "/a%5Cb", "/%00"] {
+            assert_parse_error("HTTP parsing error: Invalid target",
+                    Target::parse(target));
+        }
+    }
+
+    #[test]
+    fn dot_segments() {
+        for target in &["/..", "/a/../b", "/a/./b", "/a/.", "/%2e%2E/etc/passwd",
+                        "/a/%2e", "http://example.com/a/../b"] {
+            assert_parse_error("HTTP parsing error: Path traversal not allowed",
+                    Target::parse(target));
+        }
+        // Only whole segments count.
+        assert_eq!(vec!["..a", "b.."], segments("/..a/b.."));
+    }
+
+    #[test]
+    fn query_params() {
+        let target = Target::parse("/locks?path=a%2Fb+c.bin&limit=10&x&id=&&refspec=refs%2Fheads%2Fmain").unwrap();
+        assert_eq!(vec![(String::from("path"), String::from("a/b c.bin")),
+                        (String::from("limit"), String::from("10")),
+                        (String::from("x"), String::new()),
+                        (String::from("id"), String::new()),
+                        (String::from("refspec"), String::from("refs/heads/main"))],
+                   target.query_params());
+        assert_eq!(Some(String::from("10")), target.query_param("limit"));
+        assert_eq!(None, target.query_param("cursor"));
+        assert!(Target::parse("/locks").unwrap().query_params().is_empty());
+    }
+
+    #[test]
+    fn invalid_query_params() {
+        let target = Target::parse("/locks?path=%2&a=%zz&b=%ff&c=1").unwrap();
+        assert_eq!(vec![(String::from("c"), String::from("1"))], target.query_params());
+    }
+}
@@ -1,7 +1,7 @@
 use std::fmt;
-use super::Error;
+use super::{Error, ErrorKind};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Version {
     major: u8,
     minor: u8,
@@ -10,7 +10,7 @@ pub struct Version {
 impl Version {
     pub fn new(major: u8, minor: u8) -> Result<Version, Error> {
         if major > 9 || minor > 9 {
-            Error::err("Invalid version")
+            Error::err(ErrorKind::StartLine, "Invalid version")
         } else {
             Ok(Version{ major, minor })
         }
@@ -26,15 +26,14 @@ impl Version {
            version[2] != b'T' ||
            version[3] != b'P' ||
            version[4] != b'/' ||
-           version[6] != b'.' {
-            return Error::err("Invalid version");
+           version[6] != b'.' ||
+           !version[5].is_ascii_digit() ||
+           !version[7].is_ascii_digit() {
+            return Error::err(ErrorKind::StartLine, "Invalid version");
         }
         // Parse major and minor versions.
         let major: u8 = version[5] - b'0';
         let minor: u8 = version[7] - b'0';
-        if major > 9 || minor > 9 {
-            return Error::err("Invalid version");
-        }
 
         Ok(Version{ major, minor })
     }
@@ -59,6 +58,7 @@ impl fmt::Display for Version {
 mod tests {
     use super::*;
     use super::super::tests::*;
+    use proptest::prelude::*;
 
     fn assert_version_eq(major: u8, minor: u8, actual: &Version) {
         assert_eq!(major, actual.major);
@@ -161,6 +161,43 @@ mod tests {
         assert_parse_error("HTTP parsing error: Invalid version", v);
     }
 
+    #[test]
+    fn from_invalid_below_digits() {
+        // Bytes which sort below '0' must not underflow.
+        let v = Version::from("HTTP/!.1");
+        assert_parse_error("HTTP parsing error: Invalid version", v);
+
+        let v = Version::from("HTTP/1. ");
+        assert_parse_error("HTTP parsing error: Invalid version", v);
+
+        let v = Version::from("HTTP/\0.\0");
+        assert_parse_error("HTTP parsing error: Invalid version", v);
+    }
+
+    proptest! {
+        #[test]
+        fn from_any_string(s in "\\PC{0,12}") {
+            let _ = Version::from(&s);
+        }
+
+        #[test]
+        fn from_any_numbers(ma in any::<char>(), mi in any::<char>()) {
+            let v = Version::from(&format!("HTTP/{}.{}", ma, mi));
+            prop_assert_eq!(ma.is_ascii_digit() && mi.is_ascii_digit(), v.is_ok());
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        let v1_0 = Version::new(1, 0).unwrap();
+        let v1_1 = Version::new(1, 1).unwrap();
+        let v2_0 = Version::new(2, 0).unwrap();
+        assert!(v1_0 < v1_1);
+        assert!(v1_1 < v2_0);
+        assert!(Version::new(0, 9).unwrap() < v1_0);
+        assert!(v1_1 >= Version::new(1, 1).unwrap());
+    }
+
     #[test]
     fn display() {
         let s = "HTTP/4.2";
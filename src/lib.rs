@@ -0,0 +1,7 @@
+extern crate flate2;
+#[cfg(test)] extern crate proptest;
+
+// The http module is a general implementation of the protocol, not all of
+// which is needed by the server.
+pub mod http;
+pub mod client;
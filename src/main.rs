@@ -1,4 +1,8 @@
-#[macro_use] extern crate num_derive;
+extern crate local_lfs;
+#[macro_use] extern crate serde_derive;
+extern crate serde;
+extern crate sha2;
+#[cfg_attr(test, macro_use)] extern crate serde_json;
 
 mod server;
 
@@ -9,12 +13,15 @@ use std::process;
 struct Args {
     port: Option<u16>,
     store_path: Option<String>,
+    threads: Option<usize>,
+    max_object_size: Option<u64>,
 }
 
 impl fmt::Debug for Args {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Args {{ port: {:?}, store_path: {:?} }}",
-                self.port, self.store_path)
+        write!(f, "Args {{ port: {:?}, store_path: {:?}, threads: {:?}, \
+                   max_object_size: {:?} }}",
+                self.port, self.store_path, self.threads, self.max_object_size)
     }
 }
 
@@ -27,7 +34,7 @@ impl Args {
             Err(_) => "local-lfs",
         };
         println!("\
-usage: {} [-h] [-p PORT] [-s PATH]
+usage: {} [-h] [-p PORT] [-s PATH] [-t THREADS] [-m BYTES]
 
 A simple git-lfs server which can echo git commits to an external server and
 store large file objects in a separate local store.
@@ -38,6 +45,12 @@ optional arguments:
     -s PATH, --store PATH   Path to a directory in which the large file object
             store will be created. This may be a folder backed by cloud storage
             client (e.g. Dropbox, Google Drive etc). Defaults to './lfo-store'.
+    -t THREADS, --threads THREADS
+                            The number of connections which may be handled
+            concurrently. Defaults to 4.
+    -m BYTES, --max-object-size BYTES
+                            The largest object which may be uploaded. Uploads
+            must then give their size up front. Defaults to no limit.
     -h, --help              Print this message and exit.",
         prog);
         process::exit(0);
@@ -49,6 +62,8 @@ optional arguments:
 
         let mut port: Option<u16> = None;
         let mut store_path: Option<String> = None;
+        let mut threads: Option<usize> = None;
+        let mut max_object_size: Option<u64> = None;
         while let Some(arg) = iter.next() {
             match arg.as_ref() {
                 "-h" | "--help" => Args::_usage(),
@@ -58,19 +73,34 @@ optional arguments:
                                 .expect("Port number must be numeric")),
                 "-s" | "--store" => store_path = Some(iter.next()
                                 .expect("Store path must be given")),
+                "-t" | "--threads" => threads = Some(iter.next()
+                                .expect("Thread count must be given")
+                                .parse()
+                                .expect("Thread count must be numeric")),
+                "-m" | "--max-object-size" => max_object_size = Some(iter.next()
+                                .expect("Maximum object size must be given")
+                                .parse()
+                                .expect("Maximum object size must be numeric")),
                 _ => panic!("Unexpected argument"),
             }
         }
 
-        Args { port, store_path }
+        if threads == Some(0) {
+            panic!("Thread count must be at least 1");
+        }
+
+        Args { port, store_path, threads, max_object_size }
     }
 }
 
 fn main() {
     let args = Args::parse_cmdline();
     let port = args.port.unwrap_or(9090);
+    let store_path = args.store_path.clone()
+        .unwrap_or_else(|| String::from("./lfo-store"));
+    let threads = args.threads.unwrap_or(4);
 
     println!("{:?}", args);
 
-    server::accept_connections(port);
+    server::accept_connections(port, &store_path, threads, args.max_object_size);
 }
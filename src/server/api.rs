@@ -0,0 +1,163 @@
+use serde::Serialize;
+use serde_json;
+use super::http::{Error, Headers, MessageBuilder, Request, StatusCode};
+
+pub const MEDIA_TYPE: &str = "application/vnd.git-lfs+json";
+
+#[derive(Debug, Serialize)]
+struct ErrorMessage<'a> {
+    message: &'a str,
+}
+
+pub fn json_response<T: Serialize>(code: StatusCode, value: &T) -> MessageBuilder {
+    let body = serde_json::to_string(value).expect("JSON serialization failed");
+    let mut response = MessageBuilder::response(code);
+    response.add_field2("Content-Type", MEDIA_TYPE)
+            .add_body(body);
+    response
+}
+
+pub fn error_response(code: StatusCode, message: &str) -> MessageBuilder {
+    json_response(code, &ErrorMessage{ message })
+}
+
+// The response to a request which could not be parsed.
+pub fn parse_error_response(error: Error) -> MessageBuilder {
+    error_response(error.status_code(), &format!("{}", error))
+}
+
+pub fn is_valid_oid(oid: &str) -> bool {
+    // oid = 64 lowercase hex digits (a SHA-256 hash).
+    oid.len() == 64 &&
+        oid.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+// Returns the user name given with HTTP Basic authentication. The password is
+// not checked: authentication is expected to be handled by the network the
+// server is hosted on, this only identifies the user.
+pub fn basic_auth_user(request: &Request) -> Option<String> {
+    let authorization = request.authorization()?;
+    if !authorization.scheme.eq_ignore_ascii_case("Basic") {
+        return None;
+    }
+    let decoded = String::from_utf8(base64_decode(authorization.credentials)?).ok()?;
+    let user = &decoded[..decoded.find(':')?];
+    if user.is_empty() {
+        None
+    } else {
+        Some(String::from(user))
+    }
+}
+
+pub fn unauthorized_response() -> MessageBuilder {
+    let mut response = error_response(StatusCode::Unauthorized,
+            "Credentials needed");
+    response.add_field2("LFS-Authenticate", "Basic realm=\"Git LFS\"");
+    response
+}
+
+fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.trim_end_matches('=');
+    let mut decoded = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for b in encoded.bytes() {
+        let value = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    #[test]
+    fn json_response_fields() {
+        let (response, json) = parse_response(
+            json_response(StatusCode::Ok, &vec![1, 2, 3]));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!([1, 2, 3]), json);
+    }
+
+    #[test]
+    fn error_response_message() {
+        let (response, json) = parse_response(
+            error_response(StatusCode::NotFound, "Not found"));
+        assert_eq!(&StatusCode::NotFound, response.status());
+        assert_eq!(json!({ "message": "Not found" }), json);
+    }
+
+    #[test]
+    fn valid_oid() {
+        assert!(is_valid_oid(OID));
+        assert!(is_valid_oid(&"0".repeat(64)));
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(Some(Vec::new()), base64_decode(""));
+        assert_eq!(Some(b"f".to_vec()), base64_decode("Zg=="));
+        assert_eq!(Some(b"fo".to_vec()), base64_decode("Zm8="));
+        assert_eq!(Some(b"foo".to_vec()), base64_decode("Zm9v"));
+        assert_eq!(Some(b"alice:secret".to_vec()), base64_decode("YWxpY2U6c2VjcmV0"));
+        assert_eq!(Some(vec![0xfb, 0xff]), base64_decode("+/8="));
+        assert_eq!(None, base64_decode("Zm9v!"));
+    }
+
+    fn auth_request(authorization: &str) -> Request {
+        let message = format!("GET / HTTP/1.1\r\nAuthorization: {}\r\n\r\n",
+                              authorization);
+        Request::parse(&mut message.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn basic_auth() {
+        assert_eq!(Some(String::from("alice")),
+                   basic_auth_user(&auth_request("Basic YWxpY2U6c2VjcmV0")));
+        assert_eq!(Some(String::from("alice")),
+                   basic_auth_user(&auth_request("basic YWxpY2U6")));
+        // No user.
+        assert_eq!(None, basic_auth_user(&auth_request("Basic OnNlY3JldA==")));
+        // No password separator.
+        assert_eq!(None, basic_auth_user(&auth_request("Basic YWxpY2U=")));
+        // Other schemes.
+        assert_eq!(None, basic_auth_user(&auth_request("Bearer YWxpY2U6c2VjcmV0")));
+        assert_eq!(None, basic_auth_user(&auth_request("Basic")));
+        // Missing.
+        let request = Request::parse(&mut &b"GET / HTTP/1.1\r\n\r\n"[..]).unwrap();
+        assert_eq!(None, basic_auth_user(&request));
+    }
+
+    #[test]
+    fn unauthorized() {
+        let (response, _) = parse_response(unauthorized_response());
+        assert_eq!(&StatusCode::Unauthorized, response.status());
+        assert_eq!(Some("Basic realm=\"Git LFS\""),
+                   response.header("LFS-Authenticate"));
+    }
+
+    #[test]
+    fn invalid_oid() {
+        assert!(!is_valid_oid(""));
+        assert!(!is_valid_oid(&OID[1..]));
+        assert!(!is_valid_oid(&OID.to_uppercase()));
+        assert!(!is_valid_oid(&format!("{}0", OID)));
+        assert!(!is_valid_oid(&"g".repeat(64)));
+        assert!(!is_valid_oid(&"../".repeat(22)[..64]));
+    }
+}
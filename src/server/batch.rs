@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use serde_json;
+use super::api;
+use super::http::{Body, MessageBuilder, StatusCode};
+use super::store::Store;
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Operation {
+    Download,
+    Upload,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObjectSpec {
+    oid: String,
+    size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchRequest {
+    operation: Operation,
+    #[serde(default)]
+    transfers: Vec<String>,
+    objects: Vec<ObjectSpec>,
+    hash_algo: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Action {
+    href: String,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    header: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Actions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    download: Option<Action>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upload: Option<Action>,
+}
+
+#[derive(Debug, Serialize)]
+struct ObjectError {
+    code: u16,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ObjectResponse {
+    oid: String,
+    size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    authenticated: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actions: Option<Actions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ObjectError>,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchResponse {
+    transfer: &'static str,
+    objects: Vec<ObjectResponse>,
+    hash_algo: &'static str,
+}
+
+const TRANSFER: &str = "basic";
+const HASH_ALGO: &str = "sha256";
+
+// Handles a batch request for the repository at the given URL, returning
+// actions which point back at that repository's object endpoints.
+pub fn handle(store: &Store, repo_url: &str, body: &Body) -> MessageBuilder {
+    let request: BatchRequest = match serde_json::from_slice(body.content()) {
+        Ok(request) => request,
+        Err(error) => return api::error_response(StatusCode::UnprocessableEntity,
+                &format!("Invalid batch request: {}", error)),
+    };
+    if request.hash_algo.as_ref().is_some_and(|algo| algo != HASH_ALGO) {
+        return api::error_response(StatusCode::Conflict,
+                "Unsupported hash algorithm");
+    }
+    if !request.transfers.is_empty() &&
+       !request.transfers.iter().any(|transfer| transfer == TRANSFER) {
+        return api::error_response(StatusCode::UnprocessableEntity,
+                "Unsupported transfer adapter");
+    }
+
+    let operation = request.operation;
+    let objects = request.objects.into_iter()
+        .map(|object| handle_object(store, repo_url, &operation, object))
+        .collect();
+    api::json_response(StatusCode::Ok, &BatchResponse{
+        transfer: TRANSFER, objects, hash_algo: HASH_ALGO })
+}
+
+fn handle_object(store: &Store, repo_url: &str, operation: &Operation,
+        object: ObjectSpec) -> ObjectResponse {
+    let ObjectSpec{ oid, size } = object;
+    if !api::is_valid_oid(&oid) {
+        return object_error(oid, size, StatusCode::UnprocessableEntity,
+                "Invalid object ID");
+    }
+
+    let action = Action{ href: format!("{}/info/lfs/objects/{}", repo_url, oid),
+                         header: HashMap::new() };
+    let mut actions = Actions::default();
+    match *operation {
+        Operation::Download => {
+            if !store.contains(&oid) {
+                return object_error(oid, size, StatusCode::NotFound,
+                        "Object does not exist");
+            }
+            actions.download = Some(action);
+        },
+        Operation::Upload => {
+            // Objects which are already present need no action at all.
+            if !store.contains(&oid) {
+                actions.upload = Some(action);
+            }
+        },
+    }
+    let actions = if actions.download.is_none() && actions.upload.is_none() {
+        None
+    } else {
+        Some(actions)
+    };
+    ObjectResponse{ oid, size, authenticated: Some(true), actions, error: None }
+}
+
+fn object_error(oid: String, size: u64, code: StatusCode, message: &str)
+        -> ObjectResponse {
+    let error = ObjectError{ code: code.code(), message: String::from(message) };
+    ObjectResponse{ oid, size, authenticated: None, actions: None,
+                    error: Some(error) }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+    use super::super::http::Response;
+
+    const REPO_URL: &str = "http://localhost:9090/foo/bar.git";
+
+    fn batch(store: &Store, request: serde_json::Value)
+            -> (Response, serde_json::Value) {
+        let body = Body::from(request.to_string());
+        parse_response(handle(store, REPO_URL, &body))
+    }
+
+    fn empty_store(name: &str) -> (TempDir, Store) {
+        let dir = TempDir::new(name);
+        let store = Store::open(dir.path()).unwrap();
+        (dir, store)
+    }
+
+    fn populated_store(name: &str) -> (TempDir, Store) {
+        let (dir, store) = empty_store(name);
+        store.write_object(OID, &mut &b"hello"[..], Some(5)).unwrap();
+        (dir, store)
+    }
+
+    #[test]
+    fn download() {
+        let (_dir, store) = populated_store("batch_download");
+        let (response, json) = batch(&store, json!({
+            "operation": "download",
+            "transfers": ["basic"],
+            "ref": { "name": "refs/heads/master" },
+            "objects": [{ "oid": OID, "size": 123 }],
+        }));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!({
+            "transfer": "basic",
+            "hash_algo": "sha256",
+            "objects": [{
+                "oid": OID,
+                "size": 123,
+                "authenticated": true,
+                "actions": { "download": {
+                    "href": format!("{}/info/lfs/objects/{}", REPO_URL, OID),
+                }},
+            }],
+        }), json);
+    }
+
+    #[test]
+    fn download_missing() {
+        let (_dir, store) = empty_store("batch_download_missing");
+        let (response, json) = batch(&store, json!({
+            "operation": "download",
+            "objects": [{ "oid": OID, "size": 123 }],
+        }));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!({
+            "oid": OID,
+            "size": 123,
+            "error": { "code": 404, "message": "Object does not exist" },
+        }), json["objects"][0]);
+    }
+
+    #[test]
+    fn upload() {
+        let (_dir, store) = empty_store("batch_upload");
+        let (response, json) = batch(&store, json!({
+            "operation": "upload",
+            "objects": [{ "oid": OID, "size": 123 }],
+        }));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!({ "upload": {
+            "href": format!("{}/info/lfs/objects/{}", REPO_URL, OID),
+        }}), json["objects"][0]["actions"]);
+    }
+
+    #[test]
+    fn upload_existing() {
+        let (_dir, store) = populated_store("batch_upload_existing");
+        let (response, json) = batch(&store, json!({
+            "operation": "upload",
+            "objects": [{ "oid": OID, "size": 5 }],
+        }));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!({ "oid": OID, "size": 5, "authenticated": true }),
+                   json["objects"][0]);
+    }
+
+    #[test]
+    fn invalid_oid() {
+        let (_dir, store) = empty_store("batch_invalid_oid");
+        let (response, json) = batch(&store, json!({
+            "operation": "download",
+            "objects": [{ "oid": "../../etc/passwd", "size": 123 }],
+        }));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!({
+            "oid": "../../etc/passwd",
+            "size": 123,
+            "error": { "code": 422, "message": "Invalid object ID" },
+        }), json["objects"][0]);
+    }
+
+    #[test]
+    fn invalid_json() {
+        let (_dir, store) = empty_store("batch_invalid_json");
+        let (response, _) = batch(&store, json!({ "operation": "download" }));
+        assert_eq!(&StatusCode::UnprocessableEntity, response.status());
+
+        let (response, _) = batch(&store,
+            json!({ "operation": "delete", "objects": [] }));
+        assert_eq!(&StatusCode::UnprocessableEntity, response.status());
+    }
+
+    #[test]
+    fn unsupported_transfer() {
+        let (_dir, store) = empty_store("batch_unsupported_transfer");
+        let (response, _) = batch(&store, json!({
+            "operation": "download",
+            "transfers": ["tus"],
+            "objects": [],
+        }));
+        assert_eq!(&StatusCode::UnprocessableEntity, response.status());
+    }
+
+    #[test]
+    fn unsupported_hash_algo() {
+        let (_dir, store) = empty_store("batch_unsupported_hash_algo");
+        let (response, json) = batch(&store, json!({
+            "operation": "download",
+            "objects": [],
+            "hash_algo": "md5",
+        }));
+        assert_eq!(&StatusCode::Conflict, response.status());
+        assert_eq!(json!({ "message": "Unsupported hash algorithm" }), json);
+    }
+}
@@ -1,185 +0,0 @@
-extern crate num_traits;
-
-use self::num_traits::FromPrimitive;
-use self::num_traits::ToPrimitive;
-
-#[derive(Debug, FromPrimitive, ToPrimitive, PartialEq)]
-pub enum StatusCode {
-    // 100: Informational.
-    Continue                    = 100,
-    SwitchingProtocols          = 101,
-    Processing                  = 102,
-    EarlyHints                  = 103,
-    // 200: Success.
-    Ok                          = 200,
-    Created                     = 201,
-    Accepted                    = 202,
-    NonAuthoritativeInformation = 203,
-    NoContent                   = 204,
-    ResetContent                = 205,
-    PartialContent              = 206,
-    MultiStatus                 = 207,
-    AlreadyReported             = 208,
-    IMUsed                      = 226,
-    // 300: Redirection.
-    MultipleChoices             = 300,
-    MovedPermanently            = 301,
-    Found                       = 302,
-    SeeOther                    = 303,
-    NotModified                 = 304,
-    UseProxy                    = 305,
-    SwitchProxy                 = 306,
-    TemporaryRedirect           = 307,
-    PermanentRedirect           = 308,
-    // 400: Client errors.
-    BadRequest                  = 400,
-    Unauthorized                = 401,
-    PaymentRequired             = 402,
-    Forbidden                   = 403,
-    NotFound                    = 404,
-    MethodNotAllowed            = 405,
-    NotAcceptable               = 406,
-    ProxyAuthenticationRequired = 407,
-    RequestTimeout              = 408,
-    Conflict                    = 409,
-    Gone                        = 410,
-    LengthRequired              = 411,
-    PreconditionFailed          = 412,
-    PayloadTooLarge             = 413,
-    URITooLong                  = 414,
-    UnsupportedMediaType        = 415,
-    RangeNotSatisfiable         = 416,
-    ExpectationFailed           = 417,
-    ImATeapot                   = 418,
-    MisdirectedRequest          = 421,
-    UnprocessableEntity         = 422,
-    Locked                      = 423,
-    FailedDependency            = 424,
-    UpgradeRequired             = 426,
-    PreconditionRequired        = 428,
-    TooManyRequests             = 429,
-    RequestHeaderFieldsTooLarge = 431,
-    UnavailableForLegalReasons  = 451,
-    // 500: Server errors.
-    InternalServerError         = 500,
-    NotImplemented              = 501,
-    BadGateway                  = 502,
-    ServiceUnavailable          = 503,
-    GatewayTimeout              = 504,
-    HTTPVersionNotSupported     = 505,
-    VariantAlsoNegotiates       = 506,
-    InsufficientStorage         = 507,
-    LoopDetected                = 508,
-    NotExtended                 = 510,
-    NetworkAuthenticationRequired = 511,
-}
-
-impl StatusCode {
-    pub fn from(code: u16) -> Option<StatusCode> {
-        StatusCode::from_u16(code)
-    }
-
-    pub fn code(&self) -> u16 {
-        StatusCode::to_u16(self).unwrap()
-    }
-
-    pub fn phrase(&self) -> &'static str {
-        match self {
-            // 100: Informational.
-            StatusCode::Continue => "Continue",
-            StatusCode::SwitchingProtocols => "Switching Protocols",
-            StatusCode::Processing => "Processing",
-            StatusCode::EarlyHints => "Early Hints",
-            // 200: Success.
-            StatusCode::Ok => "OK",
-            StatusCode::Created => "Created",
-            StatusCode::Accepted => "Accepted",
-            StatusCode::NonAuthoritativeInformation => "Non-Authoritative Information",
-            StatusCode::NoContent => "No Content",
-            StatusCode::ResetContent => "Reset Content",
-            StatusCode::PartialContent => "Partial Content",
-            StatusCode::MultiStatus => "Multi-Status",
-            StatusCode::AlreadyReported => "Already Reported",
-            StatusCode::IMUsed => "IM Used",
-            // 300: Redirection.
-            StatusCode::MultipleChoices => "Multiple Choices",
-            StatusCode::MovedPermanently => "Moved Permanently",
-            StatusCode::Found => "Found",
-            StatusCode::SeeOther => "See Other",
-            StatusCode::NotModified => "Not Modified",
-            StatusCode::UseProxy => "Use Proxy",
-            StatusCode::SwitchProxy => "Switch Proxy",
-            StatusCode::TemporaryRedirect => "Temporary Redirect",
-            StatusCode::PermanentRedirect => "Permanent Redirect",
-            // 400: Client errors.
-            StatusCode::BadRequest => "Bad Request",
-            StatusCode::Unauthorized => "Unauthorized",
-            StatusCode::PaymentRequired => "Payment Required",
-            StatusCode::Forbidden => "Forbidden",
-            StatusCode::NotFound => "Not Found",
-            StatusCode::MethodNotAllowed => "Method Not Allowed",
-            StatusCode::NotAcceptable => "Not Acceptable",
-            StatusCode::ProxyAuthenticationRequired => "Proxy Authentication Required",
-            StatusCode::RequestTimeout => "Request Timeout",
-            StatusCode::Conflict => "Conflict",
-            StatusCode::Gone => "Gone",
-            StatusCode::LengthRequired => "Length Required",
-            StatusCode::PreconditionFailed => "Precondition Failed",
-            StatusCode::PayloadTooLarge => "Payload Too Large",
-            StatusCode::URITooLong => "URI Too Long",
-            StatusCode::UnsupportedMediaType => "Unsupported Media Type",
-            StatusCode::RangeNotSatisfiable => "Range Not Satisfiable",
-            StatusCode::ExpectationFailed => "Expectation Failed",
-            StatusCode::ImATeapot => "I'm a teapot",
-            StatusCode::MisdirectedRequest => "Misdirected Request",
-            StatusCode::UnprocessableEntity => "Unprocessable Entity",
-            StatusCode::Locked => "Locked",
-            StatusCode::FailedDependency => "Failed Dependency",
-            StatusCode::UpgradeRequired => "Upgrade Required",
-            StatusCode::PreconditionRequired => "Precondition Required",
-            StatusCode::TooManyRequests => "Too Many Requests",
-            StatusCode::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
-            StatusCode::UnavailableForLegalReasons => "Unavailable For Legal Reasons",
-            // 500: Server errors.
-            StatusCode::InternalServerError => "Internal Server Error",
-            StatusCode::NotImplemented => "Not Implemented",
-            StatusCode::BadGateway => "Bad Gateway",
-            StatusCode::ServiceUnavailable => "Service Unavailable",
-            StatusCode::GatewayTimeout => "Gateway Timeout",
-            StatusCode::HTTPVersionNotSupported => "HTTP Version Not Supported",
-            StatusCode::VariantAlsoNegotiates => "Variant Also Negotiates",
-            StatusCode::InsufficientStorage => "Insufficient Storage",
-            StatusCode::LoopDetected => "Loop Detected",
-            StatusCode::NotExtended => "Not Extended",
-            StatusCode::NetworkAuthenticationRequired => "Network Authentication Required",
-        }
-    }
-}
-
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn mappings() {
-        // There's no sane way to test this without re-stating the enum values
-        // a third time. Instead just loop through all possible values and check
-        // it either produces valid output or errors appropriately. Also check
-        // the assignment is symmetric.
-        let mut some_count = 0;
-        for i in 100..999 {
-            match StatusCode::from(i) {
-                Some(status) => {
-                    assert!(!status.phrase().is_empty());
-                    assert_eq!(i, status.code());
-                    assert_eq!(i, status as u16);
-                    some_count += 1;
-                },
-                None => {
-                },
-            }
-        }
-        assert_eq!(62, some_count);
-    }
-}
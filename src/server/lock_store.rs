@@ -0,0 +1,295 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde_json;
+use super::http::DateTime;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Owner {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Lock {
+    pub id: String,
+    pub path: String,
+    pub locked_at: String,
+    pub owner: Owner,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct StoredLock {
+    repo: String,
+    #[serde(rename = "ref")]
+    reference: Option<String>,
+    #[serde(flatten)]
+    lock: Lock,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct LockState {
+    next_id: u64,
+    locks: Vec<StoredLock>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Created {
+    New(Lock),
+    Existing(Lock),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Unlocked {
+    Removed(Lock),
+    NotFound,
+    NotOwner(Lock),
+}
+
+#[derive(Debug, Default)]
+pub struct ListFilter<'a> {
+    pub path: Option<&'a str>,
+    pub id: Option<&'a str>,
+    pub reference: Option<&'a str>,
+}
+
+impl<'a> ListFilter<'a> {
+    fn matches(&self, lock: &StoredLock) -> bool {
+        // Locks created without a ref apply to all refs.
+        self.path.is_none_or(|path| path == lock.lock.path) &&
+        self.id.is_none_or(|id| id == lock.lock.id) &&
+        self.reference.is_none_or(|reference|
+            lock.reference.as_ref().is_none_or(|r| r == reference))
+    }
+}
+
+// The set of file locks for every repository served, persisted as JSON in
+// a single file. Lock IDs are unique across all repositories and increase
+// monotonically, which allows them to be used as pagination cursors.
+#[derive(Debug)]
+pub struct LockStore {
+    path: PathBuf,
+    state: Mutex<LockState>,
+}
+
+impl LockStore {
+    pub fn open<P: AsRef<Path>>(root: P) -> io::Result<LockStore> {
+        let path = root.as_ref().join("locks.json");
+        let state = match File::open(&path) {
+            Ok(file) => serde_json::from_reader(file)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?,
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => {
+                LockState::default()
+            },
+            Err(error) => return Err(error),
+        };
+        Ok(LockStore{ path, state: Mutex::new(state) })
+    }
+
+    fn save(&self, state: &LockState) -> io::Result<()> {
+        // Replace the file atomically so it is never seen half-written.
+        let temp_path = self.path.with_extension("json.tmp");
+        let mut file = File::create(&temp_path)?;
+        file.write_all(&serde_json::to_vec_pretty(state)?)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, &self.path)
+    }
+
+    pub fn create(&self, repo: &str, path: &str, reference: Option<&str>,
+            owner: &str) -> io::Result<Created> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(existing) = state.locks.iter()
+                .find(|lock| lock.repo == repo && lock.lock.path == path) {
+            return Ok(Created::Existing(existing.lock.clone()));
+        }
+
+        state.next_id += 1;
+        let lock = Lock{ id: state.next_id.to_string(),
+                         path: String::from(path),
+                         locked_at: DateTime::now().rfc3339(),
+                         owner: Owner{ name: String::from(owner) } };
+        state.locks.push(StoredLock{ repo: String::from(repo),
+                                     reference: reference.map(String::from),
+                                     lock: lock.clone() });
+        if let Err(error) = self.save(&state) {
+            state.locks.pop();
+            return Err(error);
+        }
+        Ok(Created::New(lock))
+    }
+
+    // Returns up to limit locks in the repository matching the filter, starting
+    // from the cursor, along with the cursor for the next page (if any).
+    pub fn list(&self, repo: &str, filter: &ListFilter, cursor: Option<u64>,
+            limit: usize) -> (Vec<Lock>, Option<String>) {
+        let state = self.state.lock().unwrap();
+        let cursor = cursor.unwrap_or(0);
+        let mut matching = state.locks.iter()
+            .filter(|lock| lock.repo == repo && filter.matches(lock))
+            .filter(|lock| lock.lock.id.parse::<u64>().unwrap_or(0) >= cursor);
+        let page: Vec<Lock> = matching.by_ref().take(limit)
+            .map(|lock| lock.lock.clone())
+            .collect();
+        let next_cursor = matching.next().map(|lock| lock.lock.id.clone());
+        (page, next_cursor)
+    }
+
+    // Removes a lock. Unless force is set, the lock must belong to owner.
+    pub fn unlock(&self, repo: &str, id: &str, owner: &str, force: bool)
+            -> io::Result<Unlocked> {
+        let mut state = self.state.lock().unwrap();
+        let index = match state.locks.iter()
+                .position(|lock| lock.repo == repo && lock.lock.id == id) {
+            Some(index) => index,
+            None => return Ok(Unlocked::NotFound),
+        };
+        if !force && state.locks[index].lock.owner.name != owner {
+            return Ok(Unlocked::NotOwner(state.locks[index].lock.clone()));
+        }
+
+        let removed = state.locks.remove(index);
+        if let Err(error) = self.save(&state) {
+            state.locks.insert(index, removed);
+            return Err(error);
+        }
+        Ok(Unlocked::Removed(removed.lock))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    const REPO: &str = "/foo/bar.git";
+
+    fn new_lock(locks: &LockStore, path: &str, owner: &str) -> Lock {
+        match locks.create(REPO, path, None, owner).unwrap() {
+            Created::New(lock) => lock,
+            Created::Existing(lock) => panic!("Lock already exists {:?}", lock),
+        }
+    }
+
+    fn ids(locks: &[Lock]) -> Vec<&str> {
+        locks.iter().map(|lock| lock.id.as_str()).collect()
+    }
+
+    #[test]
+    fn create() {
+        let dir = TempDir::new("lock_store_create");
+        let locks = LockStore::open(dir.path()).unwrap();
+        let lock = new_lock(&locks, "a.bin", "alice");
+        assert_eq!("1", lock.id);
+        assert_eq!("a.bin", lock.path);
+        assert_eq!("alice", lock.owner.name);
+        assert_eq!(20, lock.locked_at.len());
+        assert_eq!("2", new_lock(&locks, "b.bin", "alice").id);
+    }
+
+    #[test]
+    fn create_existing() {
+        let dir = TempDir::new("lock_store_create_existing");
+        let locks = LockStore::open(dir.path()).unwrap();
+        let lock = new_lock(&locks, "a.bin", "alice");
+        assert_eq!(Created::Existing(lock),
+                   locks.create(REPO, "a.bin", None, "bob").unwrap());
+        // The same path in a different repository is a different file.
+        match locks.create("/other.git", "a.bin", None, "bob").unwrap() {
+            Created::New(lock) => assert_eq!("2", lock.id),
+            Created::Existing(_) => panic!("Lock shared between repos"),
+        }
+    }
+
+    #[test]
+    fn persistence() {
+        let dir = TempDir::new("lock_store_persistence");
+        let lock = {
+            let locks = LockStore::open(dir.path()).unwrap();
+            new_lock(&locks, "a.bin", "alice");
+            new_lock(&locks, "b.bin", "alice")
+        };
+        let locks = LockStore::open(dir.path()).unwrap();
+        let (page, _) = locks.list(REPO, &ListFilter::default(), None, 100);
+        assert_eq!(2, page.len());
+        assert_eq!(lock, page[1]);
+        // IDs are not reused.
+        assert_eq!("3", new_lock(&locks, "c.bin", "alice").id);
+    }
+
+    #[test]
+    fn open_corrupt() {
+        let dir = TempDir::new("lock_store_corrupt");
+        fs::write(dir.path().join("locks.json"), "{").unwrap();
+        let error = LockStore::open(dir.path()).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    }
+
+    #[test]
+    fn list_filters() {
+        let dir = TempDir::new("lock_store_list_filters");
+        let locks = LockStore::open(dir.path()).unwrap();
+        new_lock(&locks, "a.bin", "alice");
+        locks.create(REPO, "b.bin", Some("refs/heads/b"), "bob").unwrap();
+        locks.create("/other.git", "c.bin", None, "bob").unwrap();
+
+        let (page, _) = locks.list(REPO, &ListFilter::default(), None, 100);
+        assert_eq!(vec!["1", "2"], ids(&page));
+
+        let filter = ListFilter{ path: Some("b.bin"), ..Default::default() };
+        let (page, _) = locks.list(REPO, &filter, None, 100);
+        assert_eq!(vec!["2"], ids(&page));
+
+        let filter = ListFilter{ id: Some("1"), ..Default::default() };
+        let (page, _) = locks.list(REPO, &filter, None, 100);
+        assert_eq!(vec!["1"], ids(&page));
+
+        let filter = ListFilter{ reference: Some("refs/heads/a"), ..Default::default() };
+        let (page, _) = locks.list(REPO, &filter, None, 100);
+        assert_eq!(vec!["1"], ids(&page));
+    }
+
+    #[test]
+    fn list_pagination() {
+        let dir = TempDir::new("lock_store_list_pagination");
+        let locks = LockStore::open(dir.path()).unwrap();
+        for path in &["a", "b", "c", "d", "e"] {
+            new_lock(&locks, path, "alice");
+        }
+        let filter = ListFilter::default();
+        let (page, next) = locks.list(REPO, &filter, None, 2);
+        assert_eq!((vec!["1", "2"], Some(String::from("3"))), (ids(&page), next));
+        let (page, next) = locks.list(REPO, &filter, Some(3), 2);
+        assert_eq!((vec!["3", "4"], Some(String::from("5"))), (ids(&page), next));
+        let (page, next) = locks.list(REPO, &filter, Some(5), 2);
+        assert_eq!((vec!["5"], None), (ids(&page), next));
+    }
+
+    #[test]
+    fn unlock() {
+        let dir = TempDir::new("lock_store_unlock");
+        let locks = LockStore::open(dir.path()).unwrap();
+        let lock = new_lock(&locks, "a.bin", "alice");
+        assert_eq!(Unlocked::NotFound, locks.unlock(REPO, "2", "alice", false).unwrap());
+        assert_eq!(Unlocked::NotFound, locks.unlock("/other.git", "1", "alice", false).unwrap());
+        assert_eq!(Unlocked::NotOwner(lock.clone()),
+                   locks.unlock(REPO, "1", "bob", false).unwrap());
+        assert_eq!(Unlocked::Removed(lock),
+                   locks.unlock(REPO, "1", "alice", false).unwrap());
+        assert_eq!(Unlocked::NotFound, locks.unlock(REPO, "1", "alice", false).unwrap());
+    }
+
+    #[test]
+    fn force_unlock() {
+        let dir = TempDir::new("lock_store_force_unlock");
+        let locks = LockStore::open(dir.path()).unwrap();
+        let lock = new_lock(&locks, "a.bin", "alice");
+        assert_eq!(Unlocked::Removed(lock),
+                   locks.unlock(REPO, "1", "bob", true).unwrap());
+        let locks = LockStore::open(dir.path()).unwrap();
+        let (page, _) = locks.list(REPO, &ListFilter::default(), None, 100);
+        assert!(page.is_empty());
+    }
+}
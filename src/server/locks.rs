@@ -0,0 +1,340 @@
+use serde::de::DeserializeOwned;
+use serde_json;
+use super::api;
+use super::http::{Body, MessageBuilder, StatusCode};
+use super::lock_store::{Created, ListFilter, Lock, LockStore, Unlocked};
+
+const DEFAULT_LIMIT: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct Ref {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateRequest {
+    path: String,
+    #[serde(rename = "ref")]
+    reference: Option<Ref>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifyRequest {
+    cursor: Option<String>,
+    limit: Option<usize>,
+    #[serde(rename = "ref")]
+    reference: Option<Ref>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnlockRequest {
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct LockResponse {
+    lock: Lock,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+struct ListResponse {
+    locks: Vec<Lock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct VerifyResponse {
+    ours: Vec<Lock>,
+    theirs: Vec<Lock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+fn parse_json<T: DeserializeOwned>(body: &Body) -> Result<T, MessageBuilder> {
+    serde_json::from_slice(body.content()).map_err(|error| {
+        api::error_response(StatusCode::UnprocessableEntity,
+                &format!("Invalid request: {}", error))
+    })
+}
+
+fn parse_cursor(cursor: Option<&str>) -> Result<Option<u64>, MessageBuilder> {
+    match cursor {
+        Some(cursor) => cursor.parse().map(Some).map_err(|_| {
+            api::error_response(StatusCode::BadRequest, "Invalid cursor")
+        }),
+        None => Ok(None),
+    }
+}
+
+fn check_limit(limit: usize) -> Result<usize, MessageBuilder> {
+    // A zero limit would never allow the client to make progress.
+    if limit == 0 {
+        Err(api::error_response(StatusCode::BadRequest, "Invalid limit"))
+    } else {
+        Ok(limit)
+    }
+}
+
+fn storage_error(error: &::std::io::Error) -> MessageBuilder {
+    api::error_response(StatusCode::InternalServerError,
+            &format!("Failed to store locks: {}", error))
+}
+
+pub fn create(locks: &LockStore, repo: &str, user: &str, body: &Body)
+        -> MessageBuilder {
+    let request: CreateRequest = match parse_json(body) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    if request.path.is_empty() {
+        return api::error_response(StatusCode::UnprocessableEntity,
+                "Lock path must not be empty");
+    }
+
+    let reference = request.reference.as_ref().map(|r| r.name.as_str());
+    match locks.create(repo, &request.path, reference, user) {
+        Ok(Created::New(lock)) => api::json_response(StatusCode::Created,
+                &LockResponse{ lock, message: None }),
+        Ok(Created::Existing(lock)) => api::json_response(StatusCode::Conflict,
+                &LockResponse{ lock, message: Some("already created lock") }),
+        Err(error) => storage_error(&error),
+    }
+}
+
+pub fn list(locks: &LockStore, repo: &str, params: &[(String, String)])
+        -> MessageBuilder {
+    let param = |name: &str| params.iter()
+        .find(|param| param.0 == name)
+        .map(|param| param.1.as_str());
+    let filter = ListFilter{ path: param("path"), id: param("id"),
+                             reference: param("refspec") };
+    let cursor = match parse_cursor(param("cursor")) {
+        Ok(cursor) => cursor,
+        Err(response) => return response,
+    };
+    let limit = param("limit").map(|limit| limit.parse().unwrap_or(0));
+    let limit = match check_limit(limit.unwrap_or(DEFAULT_LIMIT)) {
+        Ok(limit) => limit,
+        Err(response) => return response,
+    };
+
+    let (page, next_cursor) = locks.list(repo, &filter, cursor, limit);
+    api::json_response(StatusCode::Ok, &ListResponse{ locks: page, next_cursor })
+}
+
+pub fn verify(locks: &LockStore, repo: &str, user: &str, body: &Body)
+        -> MessageBuilder {
+    let request: VerifyRequest = match parse_json(body) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    let cursor = match parse_cursor(request.cursor.as_deref()) {
+        Ok(cursor) => cursor,
+        Err(response) => return response,
+    };
+    let limit = match check_limit(request.limit.unwrap_or(DEFAULT_LIMIT)) {
+        Ok(limit) => limit,
+        Err(response) => return response,
+    };
+
+    let filter = ListFilter{
+        reference: request.reference.as_ref().map(|r| r.name.as_str()),
+        ..Default::default() };
+    let (page, next_cursor) = locks.list(repo, &filter, cursor, limit);
+    let (ours, theirs) = page.into_iter()
+        .partition(|lock| lock.owner.name == user);
+    api::json_response(StatusCode::Ok,
+            &VerifyResponse{ ours, theirs, next_cursor })
+}
+
+// Users are only identified, not authenticated (see api::basic_auth_user), so
+// there's no telling who may override whom: anyone may force the removal of
+// another user's lock, as git lfs unlock --force asks. Each time is logged.
+pub fn unlock(locks: &LockStore, repo: &str, id: &str, user: &str, body: &Body)
+        -> MessageBuilder {
+    let request: UnlockRequest = match parse_json(body) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+
+    match locks.unlock(repo, id, user, request.force) {
+        Ok(Unlocked::Removed(lock)) => {
+            if lock.owner.name != user {
+                println!("{} forced the removal of lock {} on {} owned by {}",
+                         user, lock.id, lock.path, lock.owner.name);
+            }
+            api::json_response(StatusCode::Ok, &LockResponse{ lock, message: None })
+        },
+        Ok(Unlocked::NotFound) => api::error_response(StatusCode::NotFound,
+                "Lock does not exist"),
+        Ok(Unlocked::NotOwner(_)) => api::error_response(StatusCode::Forbidden,
+                "Lock is owned by another user"),
+        Err(error) => storage_error(&error),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+    use super::super::http::Response;
+
+    const REPO: &str = "/foo/bar.git";
+
+    fn json_body(value: serde_json::Value) -> Body {
+        Body::from(value.to_string())
+    }
+
+    fn create_lock(locks: &LockStore, path: &str, user: &str)
+            -> (Response, serde_json::Value) {
+        parse_response(create(locks, REPO, user, &json_body(json!({
+            "path": path, "ref": { "name": "refs/heads/master" } }))))
+    }
+
+    fn list_locks(locks: &LockStore, query: &[(&str, &str)])
+            -> (Response, serde_json::Value) {
+        let params: Vec<(String, String)> = query.iter()
+            .map(|&(name, value)| (String::from(name), String::from(value)))
+            .collect();
+        parse_response(list(locks, REPO, &params))
+    }
+
+    #[test]
+    fn create_new() {
+        let dir = TempDir::new("locks_create");
+        let locks = LockStore::open(dir.path()).unwrap();
+        let (response, json) = create_lock(&locks, "foo/bar.zip", "alice");
+        assert_eq!(&StatusCode::Created, response.status());
+        assert_eq!(json!("1"), json["lock"]["id"]);
+        assert_eq!(json!("foo/bar.zip"), json["lock"]["path"]);
+        assert_eq!(json!({ "name": "alice" }), json["lock"]["owner"]);
+        assert!(json["lock"]["locked_at"].is_string());
+        assert!(json.get("message").is_none());
+    }
+
+    #[test]
+    fn create_conflict() {
+        let dir = TempDir::new("locks_create_conflict");
+        let locks = LockStore::open(dir.path()).unwrap();
+        create_lock(&locks, "foo/bar.zip", "alice");
+        let (response, json) = create_lock(&locks, "foo/bar.zip", "bob");
+        assert_eq!(&StatusCode::Conflict, response.status());
+        assert_eq!(json!("already created lock"), json["message"]);
+        assert_eq!(json!({ "name": "alice" }), json["lock"]["owner"]);
+    }
+
+    #[test]
+    fn create_invalid() {
+        let dir = TempDir::new("locks_create_invalid");
+        let locks = LockStore::open(dir.path()).unwrap();
+        let (response, _) = parse_response(create(&locks, REPO, "alice",
+            &json_body(json!({ "ref": "refs/heads/master" }))));
+        assert_eq!(&StatusCode::UnprocessableEntity, response.status());
+        let (response, _) = parse_response(create(&locks, REPO, "alice",
+            &json_body(json!({ "path": "" }))));
+        assert_eq!(&StatusCode::UnprocessableEntity, response.status());
+    }
+
+    #[test]
+    fn list_all() {
+        let dir = TempDir::new("locks_list");
+        let locks = LockStore::open(dir.path()).unwrap();
+        create_lock(&locks, "a.bin", "alice");
+        create_lock(&locks, "b.bin", "bob");
+        let (response, json) = list_locks(&locks, &[]);
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(2, json["locks"].as_array().unwrap().len());
+        assert!(json.get("next_cursor").is_none());
+
+        let (_, json) = list_locks(&locks, &[("path", "b.bin")]);
+        assert_eq!(json!("2"), json["locks"][0]["id"]);
+        assert_eq!(1, json["locks"].as_array().unwrap().len());
+
+        let (_, json) = list_locks(&locks, &[("refspec", "refs/heads/other")]);
+        assert!(json["locks"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn list_pages() {
+        let dir = TempDir::new("locks_list_pages");
+        let locks = LockStore::open(dir.path()).unwrap();
+        create_lock(&locks, "a.bin", "alice");
+        create_lock(&locks, "b.bin", "alice");
+        create_lock(&locks, "c.bin", "alice");
+        let (_, json) = list_locks(&locks, &[("limit", "2")]);
+        assert_eq!(2, json["locks"].as_array().unwrap().len());
+        assert_eq!(json!("3"), json["next_cursor"]);
+
+        let (_, json) = list_locks(&locks, &[("limit", "2"), ("cursor", "3")]);
+        assert_eq!(json!("c.bin"), json["locks"][0]["path"]);
+        assert!(json.get("next_cursor").is_none());
+    }
+
+    #[test]
+    fn list_invalid() {
+        let dir = TempDir::new("locks_list_invalid");
+        let locks = LockStore::open(dir.path()).unwrap();
+        let (response, _) = list_locks(&locks, &[("limit", "0")]);
+        assert_eq!(&StatusCode::BadRequest, response.status());
+        let (response, _) = list_locks(&locks, &[("limit", "lots")]);
+        assert_eq!(&StatusCode::BadRequest, response.status());
+        let (response, _) = list_locks(&locks, &[("cursor", "abc")]);
+        assert_eq!(&StatusCode::BadRequest, response.status());
+    }
+
+    #[test]
+    fn verify_ours_and_theirs() {
+        let dir = TempDir::new("locks_verify");
+        let locks = LockStore::open(dir.path()).unwrap();
+        create_lock(&locks, "a.bin", "alice");
+        create_lock(&locks, "b.bin", "bob");
+        create_lock(&locks, "c.bin", "alice");
+        let (response, json) = parse_response(verify(&locks, REPO, "alice",
+            &json_body(json!({ "ref": { "name": "refs/heads/master" } }))));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(2, json["ours"].as_array().unwrap().len());
+        assert_eq!(1, json["theirs"].as_array().unwrap().len());
+        assert_eq!(json!("b.bin"), json["theirs"][0]["path"]);
+
+        let (_, json) = parse_response(verify(&locks, REPO, "alice",
+            &json_body(json!({ "limit": 1, "cursor": "2" }))));
+        assert!(json["ours"].as_array().unwrap().is_empty());
+        assert_eq!(1, json["theirs"].as_array().unwrap().len());
+        assert_eq!(json!("3"), json["next_cursor"]);
+    }
+
+    #[test]
+    fn unlock_owned() {
+        let dir = TempDir::new("locks_unlock");
+        let locks = LockStore::open(dir.path()).unwrap();
+        create_lock(&locks, "a.bin", "alice");
+        let (response, json) = parse_response(
+            unlock(&locks, REPO, "1", "alice", &json_body(json!({}))));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!("a.bin"), json["lock"]["path"]);
+
+        let (response, _) = parse_response(
+            unlock(&locks, REPO, "1", "alice", &json_body(json!({}))));
+        assert_eq!(&StatusCode::NotFound, response.status());
+    }
+
+    #[test]
+    fn unlock_not_owned() {
+        let dir = TempDir::new("locks_unlock_not_owned");
+        let locks = LockStore::open(dir.path()).unwrap();
+        create_lock(&locks, "a.bin", "alice");
+        let (response, _) = parse_response(
+            unlock(&locks, REPO, "1", "bob", &json_body(json!({ "force": false }))));
+        assert_eq!(&StatusCode::Forbidden, response.status());
+
+        let (response, json) = parse_response(
+            unlock(&locks, REPO, "1", "bob", &json_body(json!({ "force": true }))));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!({ "name": "alice" }), json["lock"]["owner"]);
+    }
+}
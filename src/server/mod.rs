@@ -1,22 +1,88 @@
-mod http;
+mod api;
+mod batch;
+mod lock_store;
+mod locks;
+mod objects;
+mod pool;
+mod router;
+mod store;
 
+use std::cell::Cell;
+use std::cmp;
 use std::io;
 use std::io::prelude::*;
 use std::io::{BufReader, BufWriter};
 use std::net::{TcpListener, TcpStream, SocketAddr};
-use self::http::StatusCode;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use local_lfs::http;
+use self::http::{decode_content, Body, BodyReader, Error, ErrorKind, Headers,
+                 Limits, MessageBuilder, Method, Request, StatusCode, Target};
+use self::lock_store::LockStore;
+use self::pool::{Demand, ThreadPool};
+use self::router::{Params, Routed, Router};
+use self::store::Store;
 
-pub fn accept_connections(port: u16) {
+// How long a persistent connection may wait for its next request.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+// How often a persistent connection waiting for its next request checks
+// whether its worker is wanted for another connection.
+const IDLE_POLL: Duration = Duration::from_millis(100);
+// How long a single read or write may take once a request has begun.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// The server state shared by all connections.
+struct Context {
+    store: Store,
+    locks: LockStore,
+    router: Router<Handler>,
+    limits: Limits,
+    idle_timeout: Duration,
+    read_timeout: Duration,
+    write_timeout: Duration,
+    max_object_size: Option<u64>,
+    // Whether connections are waiting for a worker.
+    demand: Demand,
+}
+
+impl Context {
+    fn open<P: AsRef<Path>>(store_path: P) -> io::Result<Context> {
+        let store = Store::open(&store_path)?;
+        let locks = LockStore::open(&store_path)?;
+        Ok(Context{ store, locks, router: routes(), limits: Limits::default(),
+                    idle_timeout: IDLE_TIMEOUT, read_timeout: READ_TIMEOUT,
+                    write_timeout: WRITE_TIMEOUT, max_object_size: None,
+                    demand: Demand::default() })
+    }
+}
+
+pub fn accept_connections(port: u16, store_path: &str, threads: usize,
+        max_object_size: Option<u64>) {
+    let mut context = Context::open(store_path).unwrap_or_else(|error| panic!(
+        "Failed to open store {}: {}", store_path, error));
+    context.max_object_size = max_object_size;
     let listen_addr = SocketAddr::from(([127,0,0,1], port));
-    let listener = TcpListener::bind(listen_addr).expect(&format!(
+    let listener = TcpListener::bind(listen_addr).unwrap_or_else(|_| panic!(
         "Failed to bind to {}", listen_addr));
+    let pool = ThreadPool::new(threads);
+    context.demand = pool.demand();
+
+    println!("Listening on {} with {} workers", listen_addr, pool.size());
+    serve_connections(Arc::new(context), &listener, &pool);
+}
 
-    println!("Listening on {}", listen_addr);
+fn serve_connections(context: Arc<Context>, listener: &TcpListener, pool: &ThreadPool) {
     loop {
         match listener.accept() {
             Ok((stream, addr)) => {
-                // TODO: Threadify.
-                handle_connection(addr, stream).expect("failed to handle");
+                let context = Arc::clone(&context);
+                pool.execute(move || {
+                    if let Err(error) = handle_connection(&context, addr, stream) {
+                        println!("Connection to {} failed {}", addr, error);
+                    }
+                });
             }
             Err(error) => {
                 // TODO: Log.
@@ -27,24 +93,1289 @@ pub fn accept_connections(port: u16) {
     }
 }
 
-fn handle_connection(addr: SocketAddr, stream: TcpStream) -> io::Result<()> {
+// Waits for the next request on a persistent connection to begin. Returns
+// false if the connection should be closed instead, because the client
+// closed it or it was idle for too long. An idle connection also gives up
+// its worker as soon as another connection is waiting for one.
+fn await_request(context: &Context, addr: SocketAddr, stream: &TcpStream,
+        reader: &mut BufReader<&TcpStream>) -> io::Result<bool> {
+    let start = Instant::now();
+    loop {
+        let idle = start.elapsed();
+        if idle < context.idle_timeout {
+            stream.set_read_timeout(Some(cmp::min(IDLE_POLL, context.idle_timeout - idle)))?;
+            match reader.fill_buf() {
+                Ok(buffer) => return Ok(!buffer.is_empty()),
+                Err(ref error) if error.kind() == io::ErrorKind::WouldBlock ||
+                                  error.kind() == io::ErrorKind::TimedOut => {},
+                Err(error) => return Err(error),
+            }
+            if !context.demand.try_claim() {
+                continue;
+            }
+        }
+        println!("Closing idle connection to {}", addr);
+        return Ok(false);
+    }
+}
+
+fn handle_connection(context: &Context, addr: SocketAddr, stream: TcpStream)
+        -> io::Result<()> {
     println!("New client: {}", addr);
+    let local_addr = stream.local_addr()?;
+    stream.set_write_timeout(Some(context.write_timeout))?;
 
     let mut reader = BufReader::new(&stream);
     let mut writer = BufWriter::new(&stream);
 
-    let request = http::Request::parse(&mut reader).expect("couldn't parse");
-    println!("First Request:\n  {}", request);
-    let body = http::Body::parse(&mut reader, 0).expect("couldn't parse");
-    println!("First Body:\n  {}", body);
+    // Serve requests until either side asks to close the connection. Requests
+    // may be pipelined, in which case they are waiting in the reader already.
+    loop {
+        if !await_request(context, addr, &stream, &mut reader)? {
+            break;
+        }
 
-    let response_body = String::from("hello world");
-    let mut response = http::MessageBuilder::response(StatusCode::Ok);
-    response.add_field(http::Field::new_contentlength(response_body.len()))
-            .add_body(response_body);
-    // println!("First Response:\n  {:?}", response);
-    // let response = format!("{}\r\n{}", response_head, response_body);
-    writer.write(&response.into_bytes())?;
+        // A request has begun, and must keep arriving.
+        stream.set_read_timeout(Some(context.read_timeout))?;
+        let (mut response, keep_alive) = match Request::parse_with_limits(&mut reader,
+                &context.limits) {
+            // Only HTTP/1.x messages are framed the way they are parsed here.
+            Ok(ref request) if request.version().major() != 1 => {
+                (api::parse_error_response(Error::new(ErrorKind::UnsupportedVersion,
+                        "Unsupported version")), false)
+            },
+            Ok(request) => {
+                // Only the request line, as fields may carry credentials.
+                println!("Request: {} {} {}", request.method(), request.target(),
+                         request.version());
+                let (response, reusable) = respond(context, &local_addr,
+                        &request, &mut reader, &mut writer);
+                (response, reusable && request.keep_alive())
+            },
+            Err(error) => (api::parse_error_response(error), false),
+        };
+        if keep_alive {
+            response.add_field2("Connection", "keep-alive");
+        } else {
+            response.add_field2("Connection", "close");
+        }
+        response.write_to(&mut writer)?;
+        writer.flush()?;
+        if !keep_alive {
+            break;
+        }
+    }
 
     Ok(())
 }
+
+// Handles a request whose body follows it in the reader, leaving the reader
+// at the start of the next request. Interim responses are written to the
+// writer as they're needed. Returns the response along with whether the
+// connection can still be used.
+fn respond<B: BufRead>(context: &Context, local_addr: &SocketAddr,
+        request: &Request, reader: &mut B, writer: &mut dyn Write)
+        -> (MessageBuilder, bool) {
+    let framing = match request.framing() {
+        Ok(framing) => framing,
+        // Without framing the end of the request can't be found.
+        Err(error) => return (api::parse_error_response(error), false),
+    };
+    // Expectations are ignored from HTTP/1.0 clients, which don't know them.
+    let expect = if request.version().minor() >= 1 { request.expect() } else { Vec::new() };
+    let waiting = Cell::new(expect.iter().any(|expectation| expectation == "100-continue"));
+    let mut continuing = Continue{ inner: reader, writer, waiting: &waiting };
+    let mut body = BodyReader::with_limits(&mut continuing as &mut dyn BufRead, &framing,
+                                           &context.limits);
+    let mut response = if expect.iter().all(|expectation| expectation == "100-continue") {
+        handle_request(context, local_addr, request, &mut body)
+    } else {
+        api::error_response(StatusCode::ExpectationFailed, "Unsupported expectation")
+    };
+    response.encode_for(request);
+    if *request.method() == Method::HEAD {
+        response.omit_body();
+    }
+    // A client which was never asked for the body may or may not send it, so
+    // where the next request starts isn't known.
+    if waiting.get() {
+        return (response, body.remaining() == Some(0));
+    }
+    // Discard any part of the body the handler didn't read so the next
+    // request starts in the right place. Past a point it is cheaper to close
+    // the connection instead.
+    let drained = io::copy(&mut (&mut body).take(context.limits.body), &mut io::sink())
+        .and_then(|_| body.read(&mut [0]))
+        .is_ok_and(|len| len == 0);
+    (response, drained)
+}
+
+// Reads the body of a request whose client waits to be asked for it with a
+// 100 (Continue) response. That's only sent once the body is first read, so a
+// request turned away beforehand is never sent.
+struct Continue<'a> {
+    inner: &'a mut dyn BufRead,
+    writer: &'a mut dyn Write,
+    waiting: &'a Cell<bool>,
+}
+
+impl<'a> Read for Continue<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = cmp::min(buf.len(), available.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<'a> BufRead for Continue<'a> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.waiting.get() {
+            self.waiting.set(false);
+            MessageBuilder::response(StatusCode::Continue).write_to(&mut self.writer)?;
+            self.writer.flush()?;
+        }
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+// The body of the request being handled.
+type RequestBody<'a> = BodyReader<&'a mut dyn BufRead>;
+
+fn handle_request(context: &Context, local_addr: &SocketAddr,
+        request: &Request, body: &mut RequestBody) -> MessageBuilder {
+    route(context, local_addr, request, body)
+        .unwrap_or_else(|response| response)
+}
+
+fn route(context: &Context, local_addr: &SocketAddr, request: &Request,
+        body: &mut RequestBody) -> Result<MessageBuilder, MessageBuilder> {
+    // Decoding the target also rejects any attempt at path traversal.
+    let target = request.request_target().map_err(api::parse_error_response)?;
+    match context.router.route(request.method(), &target) {
+        Routed::Found(handler, params) => {
+            handler(Call{ context, local_addr, request, target: &target, params,
+                          body })
+        },
+        Routed::Options(allowed) => {
+            let mut response = MessageBuilder::response(StatusCode::Ok);
+            response.add_field2("Allow", &router::allow_value(&allowed));
+            Ok(response)
+        },
+        Routed::MethodNotAllowed(allowed) => {
+            let mut response = api::error_response(StatusCode::MethodNotAllowed,
+                    "Method not allowed");
+            response.add_field2("Allow", &router::allow_value(&allowed));
+            Ok(response)
+        },
+        Routed::NotFound => Ok(api::error_response(StatusCode::NotFound, "Not found")),
+        Routed::NotImplemented => {
+            Ok(api::error_response(StatusCode::NotImplemented, "Method not implemented"))
+        },
+    }
+}
+
+// A request being handled, as passed to the handler it was routed to.
+struct Call<'a, 'b: 'a> {
+    context: &'a Context,
+    local_addr: &'a SocketAddr,
+    request: &'a Request,
+    target: &'a Target,
+    params: Params,
+    body: &'a mut RequestBody<'b>,
+}
+
+impl<'a, 'b> Call<'a, 'b> {
+    fn param(&self, name: &str) -> &str {
+        self.params.get(name).expect("Handler used with the wrong route")
+    }
+
+    // The request body, with any content codings undone.
+    fn decoded_body(&mut self) -> Result<Box<dyn Read + '_>, MessageBuilder> {
+        let codings = self.request.content_encoding().map_err(|error| {
+            let mut response = api::parse_error_response(error);
+            response.add_field2("Accept-Encoding", "gzip, deflate");
+            response
+        })?;
+        Ok(decode_content(&mut *self.body, &codings))
+    }
+
+    // The limit applies to the decoded body, so that it can't be sent
+    // compressed past it.
+    fn read_body(&mut self) -> Result<Body, MessageBuilder> {
+        let limit = self.context.limits.body;
+        Body::read_limited(&mut self.decoded_body()?, limit)
+            .map_err(api::parse_error_response)
+    }
+
+    fn user(&self) -> Result<String, MessageBuilder> {
+        api::basic_auth_user(self.request).ok_or_else(api::unauthorized_response)
+    }
+}
+
+type Handler = fn(Call) -> Result<MessageBuilder, MessageBuilder>;
+
+fn routes() -> Router<Handler> {
+    // Requests are made relative to the LFS server URL, which git-lfs derives
+    // from the repository's URL: <repo>/info/lfs. The repository is
+    // identified by its path as sent.
+    let mut router: Router<Handler> = Router::new();
+    router.add(Method::POST, "/{repo*}/info/lfs/objects/batch", handle_batch)
+          .add(Method::GET, "/{repo*}/info/lfs/objects/{oid}", handle_download)
+          .add(Method::PUT, "/{repo*}/info/lfs/objects/{oid}", handle_upload)
+          .add(Method::GET, "/{repo*}/info/lfs/locks", handle_list_locks)
+          .add(Method::POST, "/{repo*}/info/lfs/locks", handle_create_lock)
+          .add(Method::POST, "/{repo*}/info/lfs/locks/verify", handle_verify_locks)
+          .add(Method::POST, "/{repo*}/info/lfs/locks/{id}/unlock", handle_unlock);
+    router
+}
+
+fn handle_batch(mut call: Call) -> Result<MessageBuilder, MessageBuilder> {
+    // An absolute-form target's authority takes precedence over Host (RFC
+    // 7230 section 5.4).
+    let host = match call.target.authority() {
+        Some(authority) => String::from(authority),
+        None => call.request.host().map_err(api::parse_error_response)?
+            .map(String::from)
+            .unwrap_or_else(|| format!("{}", call.local_addr)),
+    };
+    let repo_url = format!("http://{}{}", host, call.param("repo"));
+    let body = call.read_body()?;
+    Ok(batch::handle(&call.context.store, &repo_url, &body))
+}
+
+fn handle_download(call: Call) -> Result<MessageBuilder, MessageBuilder> {
+    Ok(objects::download(&call.context.store, call.param("oid"), call.request.fields()))
+}
+
+fn handle_upload(mut call: Call) -> Result<MessageBuilder, MessageBuilder> {
+    let context = call.context;
+    let oid = String::from(call.param("oid"));
+    // Only the encoded size is known of an encoded object.
+    let size = call.body.remaining()
+        .filter(|_| call.request.content_encoding().is_ok_and(|codings| codings.is_empty()));
+    Ok(objects::upload(&context.store, &oid, &mut call.decoded_body()?, size,
+                       context.max_object_size))
+}
+
+fn handle_list_locks(call: Call) -> Result<MessageBuilder, MessageBuilder> {
+    call.user()?;
+    let params = call.target.query_params();
+    Ok(locks::list(&call.context.locks, call.param("repo"), &params))
+}
+
+fn handle_create_lock(mut call: Call) -> Result<MessageBuilder, MessageBuilder> {
+    let user = call.user()?;
+    let body = call.read_body()?;
+    Ok(locks::create(&call.context.locks, call.param("repo"), &user, &body))
+}
+
+fn handle_verify_locks(mut call: Call) -> Result<MessageBuilder, MessageBuilder> {
+    let user = call.user()?;
+    let body = call.read_body()?;
+    Ok(locks::verify(&call.context.locks, call.param("repo"), &user, &body))
+}
+
+fn handle_unlock(mut call: Call) -> Result<MessageBuilder, MessageBuilder> {
+    let user = call.user()?;
+    let body = call.read_body()?;
+    Ok(locks::unlock(&call.context.locks, call.param("repo"), call.param("id"),
+                     &user, &body))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+    use std::process;
+    use std::thread;
+    use serde_json;
+    use local_lfs::client::{Client, ClientRequest};
+    use super::*;
+    use super::http::{ContentCoding, Response};
+
+    // sha256("hello")
+    pub const OID: &str =
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    // A directory which is removed along with its contents when dropped.
+    pub struct TempDir {
+        path: PathBuf,
+    }
+
+    impl TempDir {
+        pub fn new(name: &str) -> TempDir {
+            let path = env::temp_dir().join(
+                format!("local-lfs-{}-{}", name, process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir{ path }
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    pub fn parse_response(builder: MessageBuilder)
+            -> (Response, serde_json::Value) {
+        let mut reader = Cursor::new(builder.into_bytes().unwrap());
+        let response = Response::parse(&mut reader).unwrap();
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).unwrap();
+        let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+        (response, json)
+    }
+
+    fn request(context: &Context, message: &str) -> (Response, serde_json::Value) {
+        let local_addr = SocketAddr::from(([127,0,0,1], 9090));
+        let mut reader = Cursor::new(message.as_bytes());
+        let request = Request::parse(&mut reader).unwrap();
+        let (response, _) = respond(context, &local_addr, &request, &mut reader, &mut io::sink());
+        parse_response(response)
+    }
+
+    #[test]
+    fn batch_request() {
+        let dir = TempDir::new("server_batch");
+        let context = Context::open(dir.path()).unwrap();
+        let body = json!({
+            "operation": "upload",
+            "objects": [{ "oid": OID, "size": 5 }],
+        }).to_string();
+        let (response, json) = request(&context, &format!("\
+            POST /foo/bar.git/info/lfs/objects/batch HTTP/1.1\r\n\
+            Host: example.com:8080\r\n\
+            Accept: application/vnd.git-lfs+json\r\n\
+            Content-Type: application/vnd.git-lfs+json\r\n\
+            Content-Length: {}\r\n\
+            \r\n\
+            {}", body.len(), body));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!(format!(
+            "http://example.com:8080/foo/bar.git/info/lfs/objects/{}", OID)),
+            json["objects"][0]["actions"]["upload"]["href"]);
+    }
+
+    // Responds to a request made of the head and body as given.
+    fn request_bytes(context: &Context, head: &str, body: &[u8]) -> (Response, Vec<u8>) {
+        let local_addr = SocketAddr::from(([127,0,0,1], 9090));
+        let mut message = head.as_bytes().to_vec();
+        message.extend_from_slice(body);
+        let mut reader = Cursor::new(message);
+        let request = Request::parse(&mut reader).unwrap();
+        let (response, _) = respond(context, &local_addr, &request, &mut reader,
+                                    &mut io::sink());
+        let mut reader = Cursor::new(response.into_bytes().unwrap());
+        let response = Response::parse(&mut reader).unwrap();
+        let body = Body::parse_framed(&mut reader, &response.framing().unwrap()).unwrap();
+        (response, body.content().to_vec())
+    }
+
+    #[test]
+    fn compressed_batch_request() {
+        let dir = TempDir::new("server_compressed_batch");
+        let context = Context::open(dir.path()).unwrap();
+        let objects: Vec<serde_json::Value> = (0..50)
+            .map(|i| json!({ "oid": format!("{:064x}", i), "size": i }))
+            .collect();
+        let body = json!({ "operation": "download", "objects": objects }).to_string();
+        let encoded = ContentCoding::Gzip.encode(body.as_bytes());
+        let (response, content) = request_bytes(&context, &format!("\
+            POST /info/lfs/objects/batch HTTP/1.1\r\n\
+            Accept-Encoding: gzip\r\n\
+            Content-Type: application/vnd.git-lfs+json\r\n\
+            Content-Encoding: gzip\r\n\
+            Content-Length: {}\r\n\
+            \r\n", encoded.len()), &encoded);
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(vec![ContentCoding::Gzip], response.content_encoding().unwrap());
+        assert_eq!(Some("Accept-Encoding"), response.header("Vary"));
+        let json: serde_json::Value = serde_json::from_reader(
+            decode_content(content.as_slice(), &[ContentCoding::Gzip])).unwrap();
+        assert_eq!(50, json["objects"].as_array().unwrap().len());
+    }
+
+    #[test]
+    fn invalid_content_coding() {
+        let dir = TempDir::new("server_invalid_coding");
+        let context = Context::open(dir.path()).unwrap();
+        let (response, content) = request_bytes(&context, "\
+            POST /info/lfs/objects/batch HTTP/1.1\r\n\
+            Content-Encoding: br\r\n\
+            Content-Length: 2\r\n\
+            \r\n", b"{}");
+        assert_eq!(&StatusCode::UnsupportedMediaType, response.status());
+        assert_eq!(Some("gzip, deflate"), response.header("Accept-Encoding"));
+        assert_eq!(json!({ "message": "HTTP parsing error: Unsupported content coding" }),
+                   serde_json::from_slice::<serde_json::Value>(&content).unwrap());
+
+        let (response, content) = request_bytes(&context, "\
+            POST /info/lfs/objects/batch HTTP/1.1\r\n\
+            Content-Encoding: gzip\r\n\
+            Content-Length: 2\r\n\
+            \r\n", b"{}");
+        assert_eq!(&StatusCode::BadRequest, response.status());
+        assert_eq!(json!({ "message": "HTTP parsing error: Invalid content coding" }),
+                   serde_json::from_slice::<serde_json::Value>(&content).unwrap());
+    }
+
+    #[test]
+    fn compressed_objects() {
+        let dir = TempDir::new("server_compressed_objects");
+        let context = Context::open(dir.path()).unwrap();
+        // Objects may be uploaded compressed, but are held as they are.
+        let encoded = ContentCoding::Deflate.encode(b"hello");
+        let (response, _) = request_bytes(&context, &format!("\
+            PUT /info/lfs/objects/{} HTTP/1.1\r\n\
+            Content-Encoding: deflate\r\n\
+            Content-Length: {}\r\n\
+            \r\n", OID, encoded.len()), &encoded);
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(5, context.store.size(OID).unwrap());
+
+        // They are never compressed again when downloaded.
+        let (response, content) = request_bytes(&context, &format!("\
+            GET /info/lfs/objects/{} HTTP/1.1\r\n\
+            Accept-Encoding: gzip, deflate\r\n\
+            \r\n", OID), b"");
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(None, response.header("Content-Encoding"));
+        assert_eq!(b"hello".to_vec(), content);
+
+        // So ranges are of the decoded content.
+        let (response, content) = request_bytes(&context, &format!("\
+            GET /info/lfs/objects/{} HTTP/1.1\r\n\
+            Range: bytes=1-3\r\n\
+            \r\n", OID), b"");
+        assert_eq!(&StatusCode::PartialContent, response.status());
+        assert_eq!(Some("bytes 1-3/5"), response.header("Content-Range"));
+        assert_eq!(b"ell".to_vec(), content);
+    }
+
+    #[test]
+    fn batch_request_without_host() {
+        let dir = TempDir::new("server_batch_without_host");
+        let context = Context::open(dir.path()).unwrap();
+        let body = json!({
+            "operation": "upload",
+            "objects": [{ "oid": OID, "size": 5 }],
+        }).to_string();
+        let (response, json) = request(&context, &format!("\
+            POST /info/lfs/objects/batch HTTP/1.1\r\n\
+            Content-Length: {}\r\n\
+            \r\n\
+            {}", body.len(), body));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!(format!(
+            "http://127.0.0.1:9090/info/lfs/objects/{}", OID)),
+            json["objects"][0]["actions"]["upload"]["href"]);
+    }
+
+    #[test]
+    fn batch_request_absolute_form() {
+        let dir = TempDir::new("server_batch_absolute_form");
+        let context = Context::open(dir.path()).unwrap();
+        let body = json!({
+            "operation": "upload",
+            "objects": [{ "oid": OID, "size": 5 }],
+        }).to_string();
+        let (response, json) = request(&context, &format!("\
+            POST http://example.com:8080/foo/bar.git/info/lfs/objects/batch HTTP/1.1\r\n\
+            Host: other.example.com\r\n\
+            Content-Length: {}\r\n\
+            \r\n\
+            {}", body.len(), body));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!(format!(
+            "http://example.com:8080/foo/bar.git/info/lfs/objects/{}", OID)),
+            json["objects"][0]["actions"]["upload"]["href"]);
+    }
+
+    #[test]
+    fn batch_wrong_method() {
+        let dir = TempDir::new("server_batch_wrong_method");
+        let context = Context::open(dir.path()).unwrap();
+        let (response, _) = request(&context, "\
+            GET /foo/info/lfs/objects/batch HTTP/1.1\r\n\
+            \r\n");
+        assert_eq!(&StatusCode::MethodNotAllowed, response.status());
+        assert_eq!(Some("POST, OPTIONS"), response.header("Allow"));
+    }
+
+    #[test]
+    fn truncated_body() {
+        let dir = TempDir::new("server_truncated_body");
+        let context = Context::open(dir.path()).unwrap();
+        let (response, _) = request(&context, "\
+            POST /foo/info/lfs/objects/batch HTTP/1.1\r\n\
+            Content-Length: 100\r\n\
+            \r\n\
+            {}");
+        assert_eq!(&StatusCode::BadRequest, response.status());
+    }
+
+    #[test]
+    fn invalid_content_length() {
+        let dir = TempDir::new("server_invalid_content_length");
+        let context = Context::open(dir.path()).unwrap();
+        let (response, _) = request(&context, "\
+            POST /foo/info/lfs/objects/batch HTTP/1.1\r\n\
+            Content-Length: lots\r\n\
+            \r\n");
+        assert_eq!(&StatusCode::BadRequest, response.status());
+    }
+
+    #[test]
+    fn conflicting_content_length() {
+        let dir = TempDir::new("server_conflicting_content_length");
+        let context = Context::open(dir.path()).unwrap();
+        let (response, json) = request(&context, "\
+            POST /foo/info/lfs/objects/batch HTTP/1.1\r\n\
+            Content-Length: 2\r\n\
+            Content-Length: 3\r\n\
+            \r\n\
+            {}");
+        assert_eq!(&StatusCode::BadRequest, response.status());
+        assert_eq!(json!("HTTP parsing error: Conflicting content lengths"),
+                   json["message"]);
+    }
+
+    #[test]
+    fn invalid_host() {
+        let dir = TempDir::new("server_invalid_host");
+        let context = Context::open(dir.path()).unwrap();
+        let (response, _) = request(&context, "\
+            POST /foo/info/lfs/objects/batch HTTP/1.1\r\n\
+            Host: a.example.com\r\n\
+            Host: b.example.com\r\n\
+            Content-Length: 2\r\n\
+            \r\n\
+            {}");
+        assert_eq!(&StatusCode::BadRequest, response.status());
+    }
+
+    #[test]
+    fn upload_then_download() {
+        let dir = TempDir::new("server_upload_download");
+        let context = Context::open(dir.path()).unwrap();
+        let target = format!("/foo/bar.git/info/lfs/objects/{}", OID);
+        let (response, _) = request(&context, &format!("\
+            GET {} HTTP/1.1\r\n\
+            \r\n", target));
+        assert_eq!(&StatusCode::NotFound, response.status());
+
+        let (response, _) = request(&context, &format!("\
+            PUT {} HTTP/1.1\r\n\
+            Content-Type: application/octet-stream\r\n\
+            Content-Length: 5\r\n\
+            \r\n\
+            hello", target));
+        assert_eq!(&StatusCode::Ok, response.status());
+
+        let local_addr = SocketAddr::from(([127,0,0,1], 9090));
+        let message = format!("GET {} HTTP/1.1\r\n\r\n", target);
+        let mut reader = Cursor::new(message.as_bytes());
+        let request = Request::parse(&mut reader).unwrap();
+        let (response, _) = respond(&context, &local_addr, &request, &mut reader, &mut io::sink());
+        assert!(response.into_bytes().unwrap().ends_with(b"\r\n\r\nhello"));
+
+        // HEAD describes the object without sending it.
+        let message = format!("HEAD {} HTTP/1.1\r\n\r\n", target);
+        let mut reader = Cursor::new(message.as_bytes());
+        let request = Request::parse(&mut reader).unwrap();
+        let (response, _) = respond(&context, &local_addr, &request, &mut reader, &mut io::sink());
+        let response = response.into_bytes().unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 200 OK\r\n"));
+        assert!(response.windows(19).any(|w| w == b"Content-Length: 5\r\n"));
+        assert!(response.ends_with(b"\r\n\r\n"));
+
+        // Interrupted downloads can be resumed.
+        let message = format!("GET {} HTTP/1.1\r\nRange: bytes=3-\r\n\r\n", target);
+        let mut reader = Cursor::new(message.as_bytes());
+        let request = Request::parse(&mut reader).unwrap();
+        let (response, _) = respond(&context, &local_addr, &request, &mut reader, &mut io::sink());
+        let response = response.into_bytes().unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 206 Partial Content\r\n"));
+        assert!(response.ends_with(b"\r\n\r\nlo"));
+    }
+
+    #[test]
+    fn chunked_upload() {
+        let dir = TempDir::new("server_chunked_upload");
+        let context = Context::open(dir.path()).unwrap();
+        let (response, _) = request(&context, &format!("\
+            PUT /info/lfs/objects/{} HTTP/1.1\r\n\
+            Transfer-Encoding: chunked\r\n\
+            \r\n\
+            2\r\n\
+            he\r\n\
+            3;ext=1\r\n\
+            llo\r\n\
+            0\r\n\
+            \r\n", OID));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(5, context.store.size(OID).unwrap());
+    }
+
+    #[test]
+    fn chunked_batch_request() {
+        let dir = TempDir::new("server_chunked_batch");
+        let context = Context::open(dir.path()).unwrap();
+        let body = json!({
+            "operation": "download",
+            "objects": [{ "oid": OID, "size": 5 }],
+        }).to_string();
+        let (response, json) = request(&context, &format!("\
+            POST /info/lfs/objects/batch HTTP/1.1\r\n\
+            Transfer-Encoding: chunked\r\n\
+            \r\n\
+            {:x}\r\n\
+            {}\r\n\
+            0\r\n\
+            \r\n", body.len(), body));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!(404), json["objects"][0]["error"]["code"]);
+    }
+
+    #[test]
+    fn malformed_chunked_request() {
+        let dir = TempDir::new("server_malformed_chunked");
+        let context = Context::open(dir.path()).unwrap();
+        let (response, _) = request(&context, "\
+            POST /info/lfs/objects/batch HTTP/1.1\r\n\
+            Transfer-Encoding: chunked\r\n\
+            \r\n\
+            zz\r\n\
+            {}\r\n\
+            0\r\n\
+            \r\n");
+        assert_eq!(&StatusCode::BadRequest, response.status());
+
+        let (response, _) = request(&context, "\
+            POST /info/lfs/objects/batch HTTP/1.1\r\n\
+            Transfer-Encoding: chunked\r\n\
+            Content-Length: 2\r\n\
+            \r\n\
+            {}");
+        assert_eq!(&StatusCode::BadRequest, response.status());
+    }
+
+    #[test]
+    fn locks_requests() {
+        let dir = TempDir::new("server_locks");
+        let context = Context::open(dir.path()).unwrap();
+        let body = json!({ "path": "a b.bin" }).to_string();
+        let (response, _) = request(&context, &format!("\
+            POST /foo/bar.git/info/lfs/locks HTTP/1.1\r\n\
+            Content-Length: {}\r\n\
+            \r\n\
+            {}", body.len(), body));
+        assert_eq!(&StatusCode::Unauthorized, response.status());
+
+        // alice:secret
+        let (response, json) = request(&context, &format!("\
+            POST /foo/bar.git/info/lfs/locks HTTP/1.1\r\n\
+            Authorization: Basic YWxpY2U6c2VjcmV0\r\n\
+            Content-Length: {}\r\n\
+            \r\n\
+            {}", body.len(), body));
+        assert_eq!(&StatusCode::Created, response.status());
+        assert_eq!(json!("alice"), json["lock"]["owner"]["name"]);
+
+        let (response, _) = request(&context, "\
+            GET /foo/bar.git/info/lfs/locks HTTP/1.1\r\n\
+            \r\n");
+        assert_eq!(&StatusCode::Unauthorized, response.status());
+        let (response, json) = request(&context, "\
+            GET /foo/bar.git/info/lfs/locks?path=a%20b.bin&limit=5 HTTP/1.1\r\n\
+            Authorization: Basic YWxpY2U6c2VjcmV0\r\n\
+            \r\n");
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!("1"), json["locks"][0]["id"]);
+
+        // Locks are per-repository.
+        let (_, json) = request(&context, "\
+            GET /foo/baz.git/info/lfs/locks HTTP/1.1\r\n\
+            Authorization: Basic YWxpY2U6c2VjcmV0\r\n\
+            \r\n");
+        assert_eq!(json!([]), json["locks"]);
+
+        let (response, json) = request(&context, "\
+            POST /foo/bar.git/info/lfs/locks/verify HTTP/1.1\r\n\
+            Authorization: Basic YWxpY2U6c2VjcmV0\r\n\
+            Content-Length: 2\r\n\
+            \r\n\
+            {}");
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!("1"), json["ours"][0]["id"]);
+
+        let (response, _) = request(&context, "\
+            POST /foo/bar.git/info/lfs/locks/1/unlock HTTP/1.1\r\n\
+            Authorization: Basic YWxpY2U6c2VjcmV0\r\n\
+            Content-Length: 2\r\n\
+            \r\n\
+            {}");
+        assert_eq!(&StatusCode::Ok, response.status());
+
+        let (response, _) = request(&context, "\
+            GET /foo/bar.git/info/lfs/locks/1/unlock HTTP/1.1\r\n\
+            \r\n");
+        assert_eq!(&StatusCode::MethodNotAllowed, response.status());
+    }
+
+    #[test]
+    fn object_wrong_method() {
+        let dir = TempDir::new("server_object_wrong_method");
+        let context = Context::open(dir.path()).unwrap();
+        let (response, _) = request(&context, &format!("\
+            POST /info/lfs/objects/{} HTTP/1.1\r\n\
+            \r\n", OID));
+        assert_eq!(&StatusCode::MethodNotAllowed, response.status());
+        assert_eq!(Some("GET, HEAD, PUT, OPTIONS"), response.header("Allow"));
+    }
+
+    #[test]
+    fn unknown_method() {
+        let dir = TempDir::new("server_unknown_method");
+        let context = Context::open(dir.path()).unwrap();
+        let local_addr = SocketAddr::from(([127,0,0,1], 9090));
+        for method in &["PROPFIND", "DELETE", "BREW"] {
+            let message = format!("\
+                {} /foo.git/info/lfs/locks HTTP/1.1\r\n\
+                Content-Length: 5\r\n\
+                \r\n\
+                hello", method);
+            let mut reader = Cursor::new(message.as_bytes());
+            let request = Request::parse(&mut reader).unwrap();
+            let (response, reusable) = respond(&context, &local_addr, &request, &mut reader,
+                                               &mut io::sink());
+            let (response, json) = parse_response(response);
+            assert_eq!(&StatusCode::NotImplemented, response.status());
+            assert_eq!(json!({ "message": "Method not implemented" }), json);
+            // The request was understood well enough to carry on.
+            assert!(reusable);
+        }
+    }
+
+    #[test]
+    fn options_request() {
+        let dir = TempDir::new("server_options");
+        let context = Context::open(dir.path()).unwrap();
+        let (response, _) = request(&context, "\
+            OPTIONS /foo.git/info/lfs/locks HTTP/1.1\r\n\
+            \r\n");
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(Some("GET, HEAD, POST, OPTIONS"), response.header("Allow"));
+
+        let (response, _) = request(&context, "\
+            OPTIONS * HTTP/1.1\r\n\
+            \r\n");
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(Some("POST, GET, HEAD, PUT, OPTIONS"), response.header("Allow"));
+
+        let (response, _) = request(&context, "\
+            OPTIONS /foo HTTP/1.1\r\n\
+            \r\n");
+        assert_eq!(&StatusCode::NotFound, response.status());
+    }
+
+    #[test]
+    fn path_traversal() {
+        let dir = TempDir::new("server_path_traversal");
+        let context = Context::open(dir.path()).unwrap();
+        for target in &["/info/lfs/objects/..", "/info/lfs/objects/%2e%2e",
+                        "/foo/../info/lfs/objects/batch",
+                        "/info/lfs/objects/..%2F..%2Fetc%2Fpasswd"] {
+            let (response, _) = request(&context, &format!("\
+                GET {} HTTP/1.1\r\n\
+                \r\n", target));
+            assert_eq!(&StatusCode::BadRequest, response.status());
+        }
+    }
+
+    #[test]
+    fn absolute_form_target() {
+        let dir = TempDir::new("server_absolute_form");
+        let context = Context::open(dir.path()).unwrap();
+        let body = json!({
+            "operation": "upload",
+            "objects": [{ "oid": OID, "size": 5 }],
+        }).to_string();
+        let (response, json) = request(&context, &format!("\
+            POST http://example.com/foo%20bar.git/info/lfs/objects/batch HTTP/1.1\r\n\
+            Host: example.com\r\n\
+            Content-Length: {}\r\n\
+            \r\n\
+            {}", body.len(), body));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(json!(format!(
+            "http://example.com/foo%20bar.git/info/lfs/objects/{}", OID)),
+            json["objects"][0]["actions"]["upload"]["href"]);
+    }
+
+    #[test]
+    fn unknown_target() {
+        let dir = TempDir::new("server_unknown_target");
+        let context = Context::open(dir.path()).unwrap();
+        let (response, json) = request(&context, "\
+            GET /foo HTTP/1.1\r\n\
+            \r\n");
+        assert_eq!(&StatusCode::NotFound, response.status());
+        assert_eq!(json!({ "message": "Not found" }), json);
+    }
+
+    // Serves a single connection on a background thread.
+    fn serve(context: Context) -> (SocketAddr, thread::JoinHandle<io::Result<()>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (stream, addr) = listener.accept()?;
+            handle_connection(&context, addr, stream)
+        });
+        (addr, handle)
+    }
+
+    fn read_response<R: BufRead>(reader: &mut R) -> (Response, Vec<u8>) {
+        let response = Response::parse(reader).unwrap();
+        let body = Body::parse_framed(reader, &response.framing().unwrap()).unwrap();
+        (response, body.content().to_vec())
+    }
+
+    #[test]
+    fn persistent_connection() {
+        let dir = TempDir::new("server_persistent");
+        let (addr, handle) = serve(Context::open(dir.path()).unwrap());
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(&stream);
+        let mut writer = &stream;
+
+        // Two pipelined requests, the first with a body nobody reads.
+        writer.write_all(format!("\
+            PUT /info/lfs/objects/{} HTTP/1.1\r\n\
+            Content-Length: 5\r\n\
+            \r\n\
+            hello\
+            GET /info/lfs/objects/{} HTTP/1.1\r\n\
+            Content-Length: 6\r\n\
+            \r\n\
+            unread", OID, OID).as_bytes()).unwrap();
+        let (response, _) = read_response(&mut reader);
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(Some("keep-alive"), response.header("Connection"));
+        let (response, body) = read_response(&mut reader);
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(b"hello", body.as_slice());
+
+        writer.write_all(b"\
+            GET /foo HTTP/1.1\r\n\
+            Connection: close\r\n\
+            \r\n").unwrap();
+        let (response, _) = read_response(&mut reader);
+        assert_eq!(&StatusCode::NotFound, response.status());
+        assert_eq!(Some("close"), response.header("Connection"));
+        let mut rest = Vec::new();
+        assert_eq!(0, reader.read_to_end(&mut rest).unwrap());
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn http_1_0_connection() {
+        let dir = TempDir::new("server_http_1_0");
+        let (addr, handle) = serve(Context::open(dir.path()).unwrap());
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(&stream);
+        let mut writer = &stream;
+
+        writer.write_all(b"\
+            GET /foo HTTP/1.0\r\n\
+            Connection: keep-alive\r\n\
+            \r\n\
+            GET /foo HTTP/1.0\r\n\
+            \r\n").unwrap();
+        let (response, _) = read_response(&mut reader);
+        assert_eq!(Some("keep-alive"), response.header("Connection"));
+        let (response, _) = read_response(&mut reader);
+        assert_eq!(Some("close"), response.header("Connection"));
+        let mut rest = Vec::new();
+        assert_eq!(0, reader.read_to_end(&mut rest).unwrap());
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn malformed_request_closes() {
+        let dir = TempDir::new("server_malformed");
+        let (addr, handle) = serve(Context::open(dir.path()).unwrap());
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(&stream);
+        let mut writer = &stream;
+
+        writer.write_all(b"\
+            GET /foo HTTP/1.1\r\n\
+            Content-Length: lots\r\n\
+            \r\n\
+            GET /foo HTTP/1.1\r\n\
+            \r\n").unwrap();
+        let (response, _) = read_response(&mut reader);
+        assert_eq!(&StatusCode::BadRequest, response.status());
+        assert_eq!(Some("close"), response.header("Connection"));
+        let mut rest = Vec::new();
+        assert_eq!(0, reader.read_to_end(&mut rest).unwrap());
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn idle_timeout() {
+        let dir = TempDir::new("server_idle_timeout");
+        let mut context = Context::open(dir.path()).unwrap();
+        context.idle_timeout = Duration::from_millis(50);
+        let (addr, handle) = serve(context);
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(&stream);
+
+        (&stream).write_all(b"GET /foo HTTP/1.1\r\n\r\n").unwrap();
+        read_response(&mut reader);
+        // The server gives up waiting for another request.
+        handle.join().unwrap().unwrap();
+        let mut rest = Vec::new();
+        assert_eq!(0, reader.read_to_end(&mut rest).unwrap());
+    }
+
+    #[test]
+    fn idle_connections_make_way() {
+        let dir = TempDir::new("server_idle_make_way");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let pool = ThreadPool::new(2);
+        let mut context = Context::open(dir.path()).unwrap();
+        context.demand = pool.demand();
+        thread::spawn(move || serve_connections(Arc::new(context), &listener, &pool));
+
+        // More connections than workers, each left idle after a request. The
+        // later ones are served well before the earlier ones would time out.
+        let start = Instant::now();
+        let streams: Vec<TcpStream> = (0..4).map(|_| {
+            let stream = TcpStream::connect(addr).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            (&stream).write_all(b"GET /foo HTTP/1.1\r\n\r\n").unwrap();
+            let (response, _) = read_response(&mut BufReader::new(&stream));
+            assert_eq!(&StatusCode::NotFound, response.status());
+            assert_eq!(Some("keep-alive"), response.header("Connection"));
+            stream
+        }).collect();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // One idle connection was closed to let in each of the later ones.
+        assert_eq!(2, streams[..3].iter().filter(|stream| closed(stream)).count());
+        assert!(!closed(&streams[3]));
+    }
+
+    #[test]
+    fn one_idle_connection_makes_way() {
+        let dir = TempDir::new("server_idle_make_way_once");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let pool = ThreadPool::new(3);
+        let mut context = Context::open(dir.path()).unwrap();
+        context.demand = pool.demand();
+        thread::spawn(move || serve_connections(Arc::new(context), &listener, &pool));
+
+        // Three idle connections, and only one more waiting for a worker.
+        let streams: Vec<TcpStream> = (0..4).map(|_| {
+            let stream = TcpStream::connect(addr).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            (&stream).write_all(b"GET /foo HTTP/1.1\r\n\r\n").unwrap();
+            read_response(&mut BufReader::new(&stream));
+            stream
+        }).collect();
+        assert_eq!(1, streams[..3].iter().filter(|stream| closed(stream)).count());
+        assert!(!closed(&streams[3]));
+    }
+
+    // Whether the server has closed an idle connection, giving it long enough
+    // to notice any demand for its worker.
+    fn closed(stream: &TcpStream) -> bool {
+        stream.set_read_timeout(Some(IDLE_POLL * 3)).unwrap();
+        match (&*stream).read(&mut [0]) {
+            Ok(0) => true,
+            Ok(_) => panic!("Unexpected data from the server"),
+            Err(ref error) if error.kind() == io::ErrorKind::WouldBlock ||
+                              error.kind() == io::ErrorKind::TimedOut => false,
+            Err(error) => panic!("Read failed {}", error),
+        }
+    }
+
+    #[test]
+    fn incomplete_request_times_out() {
+        let dir = TempDir::new("server_request_timeout");
+        let mut context = Context::open(dir.path()).unwrap();
+        context.read_timeout = Duration::from_millis(50);
+        let (addr, handle) = serve(context);
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(&stream);
+
+        (&stream).write_all(b"GET /foo HTTP/1.1\r\nHost: exa").unwrap();
+        let (response, _) = read_response(&mut reader);
+        assert_eq!(&StatusCode::RequestTimeout, response.status());
+        assert_eq!(Some("close"), response.header("Connection"));
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let dir = TempDir::new("server_unsupported_version");
+        let (addr, handle) = serve(Context::open(dir.path()).unwrap());
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(&stream);
+
+        (&stream).write_all(b"GET /foo HTTP/2.0\r\n\r\n").unwrap();
+        let (response, body) = read_response(&mut reader);
+        assert_eq!(&StatusCode::HTTPVersionNotSupported, response.status());
+        assert_eq!(json!({ "message": "HTTP parsing error: Unsupported version" }),
+                   serde_json::from_slice::<serde_json::Value>(&body).unwrap());
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn body_too_large() {
+        let dir = TempDir::new("server_body_too_large");
+        let mut context = Context::open(dir.path()).unwrap();
+        context.limits.body = 10;
+        let local_addr = SocketAddr::from(([127,0,0,1], 9090));
+        let message = format!("\
+            POST /foo/info/lfs/objects/batch HTTP/1.1\r\n\
+            Content-Length: 100\r\n\
+            \r\n\
+            {}", "x".repeat(100));
+        let mut reader = Cursor::new(message.as_bytes());
+        let request = Request::parse(&mut reader).unwrap();
+        let (response, reusable) = respond(&context, &local_addr, &request, &mut reader, &mut io::sink());
+        let (response, json) = parse_response(response);
+        assert_eq!(&StatusCode::PayloadTooLarge, response.status());
+        assert_eq!(json!({ "message": "HTTP parsing error: Body too large" }), json);
+        // Too much is left to be worth draining.
+        assert!(!reusable);
+    }
+
+    #[test]
+    fn head_too_large() {
+        let dir = TempDir::new("server_head_too_large");
+        let send = |head: &str| {
+            let mut context = Context::open(dir.path()).unwrap();
+            context.limits.start_line = 30;
+            context.limits.header_count = 2;
+            let (addr, handle) = serve(context);
+            let stream = TcpStream::connect(addr).unwrap();
+            let mut reader = BufReader::new(&stream);
+            (&stream).write_all(head.as_bytes()).unwrap();
+            let (response, _) = read_response(&mut reader);
+            assert_eq!(Some("close"), response.header("Connection"));
+            handle.join().unwrap().unwrap();
+            response
+        };
+
+        let response = send("GET /foo HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n");
+        assert_eq!(&StatusCode::RequestHeaderFieldsTooLarge, response.status());
+        let response = send("GET /foo/bar/baz/qux/quux HTTP/1.1\r\n\r\n");
+        assert_eq!(&StatusCode::URITooLong, response.status());
+    }
+
+    // Responds to a request whose body is waiting in the reader, returning
+    // whatever interim responses were sent along with the final one.
+    fn respond_expecting(context: &Context, message: &str) -> (Vec<u8>, Response, bool) {
+        let local_addr = SocketAddr::from(([127,0,0,1], 9090));
+        let mut reader = Cursor::new(message.as_bytes());
+        let request = Request::parse(&mut reader).unwrap();
+        let mut interim = Vec::new();
+        let (response, reusable) = respond(context, &local_addr, &request, &mut reader,
+                                           &mut interim);
+        (interim, parse_response(response).0, reusable)
+    }
+
+    #[test]
+    fn expect_continue() {
+        let dir = TempDir::new("server_expect_continue");
+        let context = Context::open(dir.path()).unwrap();
+        let message = format!("\
+            PUT /info/lfs/objects/{} HTTP/1.1\r\n\
+            Expect: 100-continue\r\n\
+            Content-Length: 5\r\n\
+            \r\n\
+            hello", OID);
+        let (interim, response, reusable) = respond_expecting(&context, &message);
+        assert_eq!(b"HTTP/1.1 100 Continue\r\n\r\n".to_vec(), interim);
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert!(reusable);
+        assert!(context.store.contains(OID));
+
+        // Once the object is there it needn't be sent again.
+        let (interim, response, reusable) = respond_expecting(&context, &message);
+        assert!(interim.is_empty());
+        assert_eq!(&StatusCode::Ok, response.status());
+        // The client may or may not send the body after all.
+        assert!(!reusable);
+    }
+
+    #[test]
+    fn expect_continue_rejected() {
+        let dir = TempDir::new("server_expect_continue_rejected");
+        let mut context = Context::open(dir.path()).unwrap();
+        context.max_object_size = Some(4);
+        for &(target, ref code) in &[("/info/lfs/objects/nonsense", StatusCode::UnprocessableEntity),
+                                 ("/info/lfs/objects/", StatusCode::NotFound)] {
+            let (interim, response, reusable) = respond_expecting(&context, &format!("\
+                PUT {} HTTP/1.1\r\n\
+                Expect: 100-continue\r\n\
+                Content-Length: 5\r\n\
+                \r\n", target));
+            assert!(interim.is_empty());
+            assert_eq!(code, response.status());
+            assert!(!reusable);
+        }
+        let (interim, response, _) = respond_expecting(&context, &format!("\
+            PUT /info/lfs/objects/{} HTTP/1.1\r\n\
+            Expect: 100-continue\r\n\
+            Content-Length: 5\r\n\
+            \r\n", OID));
+        assert!(interim.is_empty());
+        assert_eq!(&StatusCode::PayloadTooLarge, response.status());
+        assert!(!context.store.contains(OID));
+    }
+
+    #[test]
+    fn expect_continue_without_body() {
+        let dir = TempDir::new("server_expect_continue_without_body");
+        let context = Context::open(dir.path()).unwrap();
+        let (interim, response, reusable) = respond_expecting(&context, "\
+            GET /foo HTTP/1.1\r\n\
+            Expect: 100-continue\r\n\
+            \r\n");
+        assert!(interim.is_empty());
+        assert_eq!(&StatusCode::NotFound, response.status());
+        assert!(reusable);
+    }
+
+    #[test]
+    fn expectations_ignored_from_http_1_0() {
+        let dir = TempDir::new("server_expect_http_1_0");
+        let context = Context::open(dir.path()).unwrap();
+        let (interim, response, reusable) = respond_expecting(&context, &format!("\
+            PUT /info/lfs/objects/{} HTTP/1.0\r\n\
+            Expect: 100-continue, something-else\r\n\
+            Content-Length: 5\r\n\
+            \r\n\
+            hello", OID));
+        assert!(interim.is_empty());
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert!(reusable);
+        assert!(context.store.contains(OID));
+    }
+
+    #[test]
+    fn unsupported_expectation() {
+        let dir = TempDir::new("server_unsupported_expectation");
+        let context = Context::open(dir.path()).unwrap();
+        let (interim, response, reusable) = respond_expecting(&context, &format!("\
+            PUT /info/lfs/objects/{} HTTP/1.1\r\n\
+            Expect: something-else\r\n\
+            Content-Length: 5\r\n\
+            \r\n\
+            hello", OID));
+        assert!(interim.is_empty());
+        assert_eq!(&StatusCode::ExpectationFailed, response.status());
+        assert!(reusable);
+        assert!(!context.store.contains(OID));
+    }
+
+    #[test]
+    fn expect_continue_over_connection() {
+        let dir = TempDir::new("server_expect_continue_connection");
+        let (addr, handle) = serve(Context::open(dir.path()).unwrap());
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(&stream);
+        let mut writer = &stream;
+
+        // The body is only sent once the server asks for it.
+        writer.write_all(format!("\
+            PUT /info/lfs/objects/{} HTTP/1.1\r\n\
+            Expect: 100-continue\r\n\
+            Content-Length: 5\r\n\
+            \r\n", OID).as_bytes()).unwrap();
+        let (response, _) = read_response(&mut reader);
+        assert_eq!(&StatusCode::Continue, response.status());
+        writer.write_all(b"hello").unwrap();
+        let (response, _) = read_response(&mut reader);
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(Some("keep-alive"), response.header("Connection"));
+
+        writer.write_all(b"GET /foo HTTP/1.1\r\nConnection: close\r\n\r\n").unwrap();
+        let (response, _) = read_response(&mut reader);
+        assert_eq!(&StatusCode::NotFound, response.status());
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn client_over_connection() {
+        let dir = TempDir::new("server_client");
+        let (addr, handle) = serve(Context::open(dir.path()).unwrap());
+        let repo_url = format!("http://{}/foo/bar.git", addr);
+        let mut client = Client::new();
+
+        // Objects are uploaded and downloaded where the batch API says.
+        let batch = |client: &mut Client, operation: &str| {
+            let mut request = ClientRequest::new(Method::POST,
+                    &format!("{}/info/lfs/objects/batch", repo_url));
+            request.add_field2("Accept", api::MEDIA_TYPE)
+                   .add_field2("Content-Type", api::MEDIA_TYPE)
+                   .add_body(json!({
+                       "operation": operation,
+                       "objects": [{ "oid": OID, "size": 5 }],
+                   }).to_string());
+            let response = client.send(&request).unwrap();
+            assert_eq!(&StatusCode::Ok, response.status());
+            let json: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+            String::from(json["objects"][0]["actions"][operation]["href"].as_str().unwrap())
+        };
+        let href = batch(&mut client, "upload");
+        let mut request = ClientRequest::new(Method::PUT, &href);
+        request.add_field2("Content-Type", "application/octet-stream")
+               .add_body("hello");
+        assert_eq!(&StatusCode::Ok, client.send(&request).unwrap().status());
+
+        let href = batch(&mut client, "download");
+        let response = client.get(&href).unwrap();
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(b"hello", response.body());
+        assert!(response.header("Date").is_some());
+
+        let response = client.send(&ClientRequest::new(Method::HEAD, &href)).unwrap();
+        assert_eq!(Some(5), response.content_length().unwrap());
+        assert!(response.body().is_empty());
+
+        let mut request = ClientRequest::new(Method::GET, &href);
+        request.add_field2("Range", "bytes=1-2");
+        let response = client.send(&request).unwrap();
+        assert_eq!(&StatusCode::PartialContent, response.status());
+        assert_eq!(b"el", response.body());
+
+        let response = client.get(&format!("http://{}/foo", addr)).unwrap();
+        assert_eq!(&StatusCode::NotFound, response.status());
+
+        // Every request went over the one connection, which is closed once
+        // the client is done with it.
+        drop(client);
+        handle.join().unwrap().unwrap();
+    }
+}
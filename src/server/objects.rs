@@ -0,0 +1,521 @@
+use std::io;
+use std::io::{Cursor, ErrorKind, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use super::api;
+use super::http::{ByteRange, DateTime, Error, Field, Headers, MessageBuilder, StatusCode};
+use super::store::Store;
+
+// Asking for more ranges than this sends the whole object instead, rather
+// than opening it for each of them.
+const MAX_RANGES: usize = 64;
+
+static BOUNDARY_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Objects never change, so their oid identifies their content exactly.
+pub fn entity_tag(oid: &str) -> String {
+    format!("\"{}\"", oid)
+}
+
+pub fn download(store: &Store, oid: &str, fields: &[Field]) -> MessageBuilder {
+    if !api::is_valid_oid(oid) || !store.contains(oid) {
+        return api::error_response(StatusCode::NotFound, "Object does not exist");
+    }
+    let (size, last_modified) = match store.size(oid)
+            .and_then(|size| Ok((size, DateTime::from(store.modified(oid)?)))) {
+        Ok(metadata) => metadata,
+        Err(error) => return read_error(error),
+    };
+    let tag = entity_tag(oid);
+
+    // Preconditions are evaluated in the order RFC 9110 gives, before ranges.
+    if fields.if_match(&tag) == Some(false) {
+        return api::error_response(StatusCode::PreconditionFailed,
+                "Precondition failed");
+    }
+    let not_modified = fields.if_none_match(&tag).unwrap_or_else(|| {
+        fields.if_modified_since().is_some_and(|since| last_modified <= since)
+    });
+    if not_modified {
+        let mut response = MessageBuilder::response(StatusCode::NotModified);
+        add_validators(&mut response, &tag, &last_modified);
+        return response;
+    }
+
+    let response = match requested_ranges(fields, &tag, &last_modified) {
+        None => whole_object(store, oid, size),
+        Some(ranges) => {
+            let ranges: Vec<(u64, u64)> = ranges.iter()
+                .filter_map(|range| range.resolve(size))
+                .collect();
+            match ranges.as_slice() {
+                [] => {
+                    let mut response = api::error_response(StatusCode::RangeNotSatisfiable,
+                            "Range not satisfiable");
+                    response.add_field2("Accept-Ranges", "bytes")
+                            .add_field2("Content-Range", &format!("bytes */{}", size));
+                    return response;
+                },
+                [range] => single_range(store, oid, size, *range),
+                _ => multiple_ranges(store, oid, size, &ranges),
+            }
+        },
+    };
+    match response {
+        Ok(mut response) => {
+            add_validators(&mut response, &tag, &last_modified);
+            response
+        },
+        Err(error) => read_error(error),
+    }
+}
+
+// Lets caches keep the object for as long as they like, and revalidate it.
+fn add_validators(response: &mut MessageBuilder, tag: &str, last_modified: &DateTime) {
+    response.add_field2("ETag", tag)
+            .add_field2("Last-Modified", &last_modified.http_date())
+            .add_field2("Cache-Control", "max-age=31536000, immutable");
+}
+
+// The ranges of the object asked for, if they should be sent. Ranges which
+// can't be understood are ignored, as are any at all if If-Range doesn't
+// match the object.
+fn requested_ranges(fields: &[Field], tag: &str, last_modified: &DateTime)
+        -> Option<Vec<ByteRange>> {
+    let ranges = fields.range().ok()??;
+    let matched = match fields.header("If-Range") {
+        None => true,
+        Some(validator) if validator.starts_with('"') || validator.starts_with("W/") => {
+            validator == tag
+        },
+        // A date only matches if it's exactly when the object was added.
+        Some(date) => DateTime::from_http_date(date).as_ref() == Some(last_modified),
+    };
+    if !matched || ranges.len() > MAX_RANGES {
+        return None;
+    }
+    Some(ranges)
+}
+
+fn read_error(error: io::Error) -> MessageBuilder {
+    api::error_response(StatusCode::InternalServerError,
+            &format!("Failed to read object: {}", error))
+}
+
+fn content_range((first, last): (u64, u64), size: u64) -> String {
+    format!("bytes {}-{}/{}", first, last, size)
+}
+
+// The object is streamed from disk as the response is sent.
+fn whole_object(store: &Store, oid: &str, size: u64) -> io::Result<MessageBuilder> {
+    let file = store.open_object(oid)?;
+    let mut response = MessageBuilder::response(StatusCode::Ok);
+    response.add_field2("Content-Type", "application/octet-stream")
+            .add_field2("Accept-Ranges", "bytes")
+            .add_field(Field::new_contentlength(size as usize))
+            .add_body_reader(file);
+    Ok(response)
+}
+
+fn single_range(store: &Store, oid: &str, size: u64, range: (u64, u64))
+        -> io::Result<MessageBuilder> {
+    let length = range.1 - range.0 + 1;
+    let reader = store.open_range(oid, range.0, length)?;
+    let mut response = MessageBuilder::response(StatusCode::PartialContent);
+    response.add_field2("Content-Type", "application/octet-stream")
+            .add_field2("Accept-Ranges", "bytes")
+            .add_field2("Content-Range", &content_range(range, size))
+            .add_field(Field::new_contentlength(length as usize))
+            .add_body_reader(reader);
+    Ok(response)
+}
+
+// Several ranges are sent as the parts of a multipart/byteranges body, each
+// with its own Content-Range.
+fn multiple_ranges(store: &Store, oid: &str, size: u64, ranges: &[(u64, u64)])
+        -> io::Result<MessageBuilder> {
+    let boundary = boundary();
+    let mut body: Box<dyn Read> = Box::new(io::empty());
+    let mut length = 0;
+    for (index, &range) in ranges.iter().enumerate() {
+        let head = format!("{}--{}\r\n\
+                            Content-Type: application/octet-stream\r\n\
+                            Content-Range: {}\r\n\
+                            \r\n",
+                           if index == 0 { "" } else { "\r\n" }, boundary,
+                           content_range(range, size));
+        let range_length = range.1 - range.0 + 1;
+        let reader = store.open_range(oid, range.0, range_length)?;
+        length += head.len() as u64 + range_length;
+        body = Box::new(body.chain(Cursor::new(head)).chain(reader));
+    }
+    let tail = format!("\r\n--{}--\r\n", boundary);
+    length += tail.len() as u64;
+    body = Box::new(body.chain(Cursor::new(tail)));
+
+    let mut response = MessageBuilder::response(StatusCode::PartialContent);
+    response.add_field2("Content-Type",
+                        &format!("multipart/byteranges; boundary={}", boundary))
+            .add_field2("Accept-Ranges", "bytes")
+            .add_field(Field::new_contentlength(length as usize))
+            .add_body_reader(body);
+    Ok(response)
+}
+
+// Boundaries only need to be unlikely to appear in the object.
+fn boundary() -> String {
+    let count = BOUNDARY_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|time| time.as_nanos()).unwrap_or(0);
+    format!("local-lfs-{:x}-{:x}", nanos, count)
+}
+
+// Everything which can be checked before the object is read is, so that a
+// client waiting to send it can be turned away first.
+pub fn upload<R: Read>(store: &Store, oid: &str, reader: &mut R,
+        size: Option<u64>, max_size: Option<u64>) -> MessageBuilder {
+    if !api::is_valid_oid(oid) {
+        return api::error_response(StatusCode::UnprocessableEntity,
+                "Invalid object ID");
+    }
+    match (size, max_size) {
+        (None, Some(_)) => {
+            return api::error_response(StatusCode::LengthRequired,
+                    "Object size must be given");
+        },
+        (Some(size), Some(max_size)) if size > max_size => {
+            return api::error_response(StatusCode::PayloadTooLarge,
+                    &format!("Object is larger than {} bytes", max_size));
+        },
+        _ => {},
+    }
+    // Objects with the same oid have the same content, so needn't be sent.
+    if store.contains(oid) {
+        return MessageBuilder::response(StatusCode::Ok);
+    }
+    match store.write_object(oid, reader, size) {
+        Ok(()) => {},
+        // The request body itself was malformed.
+        Err(error) if error.get_ref().is_some_and(|inner| inner.is::<Error>()) => {
+            return api::parse_error_response(Error::from(error));
+        },
+        Err(ref error) if error.kind() == ErrorKind::UnexpectedEof => {
+            return api::error_response(StatusCode::UnprocessableEntity,
+                    &format!("Object size does not match: {}", error));
+        },
+        Err(ref error) if error.kind() == ErrorKind::InvalidData => {
+            return api::error_response(StatusCode::UnprocessableEntity,
+                    &format!("Object hash does not match oid: {}", error));
+        },
+        Err(error) => {
+            return api::error_response(StatusCode::InternalServerError,
+                    &format!("Failed to write object: {}", error));
+        },
+    }
+
+    MessageBuilder::response(StatusCode::Ok)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::*;
+    use super::super::tests::*;
+    use super::super::http::{Body, ChunkedReader, Headers, Response};
+
+    // sha256([0x00, 0xff, 0x0a, 0x80])
+    const BINARY_OID: &str =
+        "2d75f84de96be6dba9b1e48d2994df26ede680ad1f7cfb0263d1252f08655694";
+
+    #[test]
+    fn download_existing() {
+        let dir = TempDir::new("objects_download");
+        let store = Store::open(dir.path()).unwrap();
+        let content = vec![0x00, 0xff, b'\n', 0x80];
+        store.write_object(BINARY_OID, &mut content.as_slice(), Some(4)).unwrap();
+
+        let mut reader = Cursor::new(download(&store, BINARY_OID, &[]).into_bytes().unwrap());
+        let response = Response::parse(&mut reader).unwrap();
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(Some(4), response.content_length().unwrap());
+        let body = Body::parse(&mut reader, 4).unwrap();
+        assert_eq!(content.as_slice(), body.content());
+    }
+
+    // Downloads "hello" with the given fields, returning the response and its
+    // body.
+    fn download_hello(name: &str, fields: &[(&str, &str)]) -> (Response, Vec<u8>) {
+        let dir = TempDir::new(name);
+        let store = Store::open(dir.path()).unwrap();
+        store.write_object(OID, &mut &b"hello"[..], Some(5)).unwrap();
+        download_with(&store, fields)
+    }
+
+    fn download_with(store: &Store, fields: &[(&str, &str)]) -> (Response, Vec<u8>) {
+        let fields: Vec<Field> = fields.iter()
+            .map(|&(name, value)| Field::new(String::from(name), String::from(value)))
+            .collect();
+        let mut reader = Cursor::new(download(store, OID, &fields).into_bytes().unwrap());
+        let response = Response::parse(&mut reader).unwrap();
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).unwrap();
+        (response, body)
+    }
+
+    #[test]
+    fn download_accepts_ranges() {
+        let (response, body) = download_hello("objects_accept_ranges", &[]);
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(Some("bytes"), response.header("Accept-Ranges"));
+        assert_eq!(b"hello", body.as_slice());
+    }
+
+    #[test]
+    fn download_range() {
+        for &(range, content_range, expected) in &[
+                ("bytes=1-3", "bytes 1-3/5", "ell"),
+                ("bytes=2-", "bytes 2-4/5", "llo"),
+                ("bytes=-2", "bytes 3-4/5", "lo"),
+                ("bytes=0-100", "bytes 0-4/5", "hello"),
+                ("bytes=-100", "bytes 0-4/5", "hello"),
+                // Unsatisfiable ranges are left out.
+                ("bytes=10-20, 4-4", "bytes 4-4/5", "o")] {
+            let (response, body) = download_hello("objects_range", &[("Range", range)]);
+            assert_eq!(&StatusCode::PartialContent, response.status());
+            assert_eq!(Some(content_range), response.header("Content-Range"));
+            assert_eq!(Some(expected.len()), response.content_length().unwrap());
+            assert_eq!(expected.as_bytes(), body.as_slice());
+        }
+    }
+
+    #[test]
+    fn download_multiple_ranges() {
+        let (response, body) = download_hello("objects_multiple_ranges",
+                &[("Range", "bytes=0-0,-2")]);
+        assert_eq!(&StatusCode::PartialContent, response.status());
+        let content_type = response.content_type().unwrap().unwrap();
+        assert_eq!("multipart/byteranges", content_type.name);
+        let boundary = content_type.param("boundary").unwrap();
+        let expected = format!("\
+            --{0}\r\n\
+            Content-Type: application/octet-stream\r\n\
+            Content-Range: bytes 0-0/5\r\n\
+            \r\n\
+            h\r\n\
+            --{0}\r\n\
+            Content-Type: application/octet-stream\r\n\
+            Content-Range: bytes 3-4/5\r\n\
+            \r\n\
+            lo\r\n\
+            --{0}--\r\n", boundary);
+        assert_eq!(expected.as_bytes(), body.as_slice());
+        assert_eq!(Some(body.len()), response.content_length().unwrap());
+    }
+
+    #[test]
+    fn download_range_not_satisfiable() {
+        let (response, body) = download_hello("objects_range_not_satisfiable",
+                &[("Range", "bytes=5-, -0")]);
+        assert_eq!(&StatusCode::RangeNotSatisfiable, response.status());
+        assert_eq!(Some("bytes */5"), response.header("Content-Range"));
+        assert_eq!(json!({ "message": "Range not satisfiable" }),
+                   serde_json::from_slice::<serde_json::Value>(&body).unwrap());
+    }
+
+    #[test]
+    fn download_range_ignored() {
+        let if_range = entity_tag(OID);
+        let many = format!("bytes={}", vec!["0-0"; MAX_RANGES + 1].join(","));
+        for fields in &[vec![("Range", "bytes=3-1")],
+                        vec![("Range", "lines=1-2")],
+                        vec![("Range", many.as_str())],
+                        vec![("Range", "bytes=1-"), ("If-Range", "\"other\"")],
+                        vec![("Range", "bytes=1-"), ("If-Range", "W/\"other\"")],
+                        vec![("Range", "bytes=1-"),
+                             ("If-Range", "Tue, 15 Nov 1994 08:12:31 GMT")]] {
+            let (response, body) = download_hello("objects_range_ignored", fields);
+            assert_eq!(&StatusCode::Ok, response.status());
+            assert_eq!(b"hello", body.as_slice());
+        }
+        let (response, body) = download_hello("objects_if_range",
+                &[("Range", "bytes=1-"), ("If-Range", &if_range)]);
+        assert_eq!(&StatusCode::PartialContent, response.status());
+        assert_eq!(b"ello", body.as_slice());
+    }
+
+    #[test]
+    fn download_validators() {
+        let dir = TempDir::new("objects_validators");
+        let store = Store::open(dir.path()).unwrap();
+        store.write_object(OID, &mut &b"hello"[..], Some(5)).unwrap();
+        let last_modified = DateTime::from(store.modified(OID).unwrap()).http_date();
+        for fields in &[vec![], vec![("Range", "bytes=1-")], vec![("Range", "bytes=1-1,3-")]] {
+            let (response, _) = download_with(&store, fields);
+            assert_eq!(Some(entity_tag(OID).as_str()), response.header("ETag"));
+            assert_eq!(Some(last_modified.as_str()), response.header("Last-Modified"));
+            assert_eq!(Some("max-age=31536000, immutable"), response.header("Cache-Control"));
+        }
+    }
+
+    #[test]
+    fn download_not_modified() {
+        let dir = TempDir::new("objects_not_modified");
+        let store = Store::open(dir.path()).unwrap();
+        store.write_object(OID, &mut &b"hello"[..], Some(5)).unwrap();
+        let tag = entity_tag(OID);
+        let weak = format!("W/{}", tag);
+        let last_modified = DateTime::from(store.modified(OID).unwrap()).http_date();
+        for fields in &[vec![("If-None-Match", tag.as_str())],
+                        vec![("If-None-Match", "\"other\", *")],
+                        vec![("If-None-Match", weak.as_str())],
+                        vec![("If-Modified-Since", last_modified.as_str())],
+                        vec![("If-Modified-Since", "Fri, 01 Jan 9999 00:00:00 GMT")],
+                        vec![("If-None-Match", tag.as_str()), ("Range", "bytes=1-")]] {
+            let (response, body) = download_with(&store, fields);
+            assert_eq!(&StatusCode::NotModified, response.status());
+            assert_eq!(Some(tag.as_str()), response.header("ETag"));
+            assert!(body.is_empty());
+        }
+        // If-None-Match takes precedence over If-Modified-Since.
+        for fields in &[vec![("If-None-Match", "\"other\"")],
+                        vec![("If-Modified-Since", "Sun, 06 Nov 1994 08:49:37 GMT")],
+                        vec![("If-Modified-Since", "yesterday")],
+                        vec![("If-None-Match", "\"other\""),
+                             ("If-Modified-Since", last_modified.as_str())]] {
+            let (response, body) = download_with(&store, fields);
+            assert_eq!(&StatusCode::Ok, response.status());
+            assert_eq!(b"hello", body.as_slice());
+        }
+    }
+
+    #[test]
+    fn download_precondition_failed() {
+        let tag = entity_tag(OID);
+        let weak = format!("W/{}", tag);
+        for value in &["\"other\"", weak.as_str()] {
+            let (response, body) = download_hello("objects_precondition_failed",
+                    &[("If-Match", value), ("If-None-Match", "*")]);
+            assert_eq!(&StatusCode::PreconditionFailed, response.status());
+            assert_eq!(json!({ "message": "Precondition failed" }),
+                       serde_json::from_slice::<serde_json::Value>(&body).unwrap());
+        }
+        for value in &[tag.as_str(), "*"] {
+            let (response, body) = download_hello("objects_precondition_passed",
+                    &[("If-Match", value)]);
+            assert_eq!(&StatusCode::Ok, response.status());
+            assert_eq!(b"hello", body.as_slice());
+        }
+    }
+
+    #[test]
+    fn download_if_range_date() {
+        let dir = TempDir::new("objects_if_range_date");
+        let store = Store::open(dir.path()).unwrap();
+        store.write_object(OID, &mut &b"hello"[..], Some(5)).unwrap();
+        let last_modified = DateTime::from(store.modified(OID).unwrap()).http_date();
+        let (response, body) = download_with(&store,
+                &[("Range", "bytes=1-"), ("If-Range", &last_modified)]);
+        assert_eq!(&StatusCode::PartialContent, response.status());
+        assert_eq!(b"ello", body.as_slice());
+    }
+
+    #[test]
+    fn download_missing() {
+        let dir = TempDir::new("objects_download_missing");
+        let store = Store::open(dir.path()).unwrap();
+        let (response, _) = parse_response(download(&store, OID, &[]));
+        assert_eq!(&StatusCode::NotFound, response.status());
+        let (response, _) = parse_response(download(&store, "../../tmp", &[]));
+        assert_eq!(&StatusCode::NotFound, response.status());
+    }
+
+    #[test]
+    fn upload_new() {
+        let dir = TempDir::new("objects_upload");
+        let store = Store::open(dir.path()).unwrap();
+        let (response, _) = parse_response(
+            upload(&store, OID, &mut &b"hello"[..], Some(5), None));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert_eq!(5, store.size(OID).unwrap());
+    }
+
+    #[test]
+    fn upload_existing() {
+        let dir = TempDir::new("objects_upload_existing");
+        let store = Store::open(dir.path()).unwrap();
+        store.write_object(OID, &mut &b"hello"[..], Some(5)).unwrap();
+        let mut reader = &b"hello"[..];
+        let (response, _) = parse_response(upload(&store, OID, &mut reader, Some(5), None));
+        assert_eq!(&StatusCode::Ok, response.status());
+        // The object wasn't read again.
+        assert_eq!(b"hello", reader);
+    }
+
+    #[test]
+    fn upload_too_large() {
+        let dir = TempDir::new("objects_upload_too_large");
+        let store = Store::open(dir.path()).unwrap();
+        let (response, json) = parse_response(
+            upload(&store, OID, &mut &b"hello"[..], Some(5), Some(4)));
+        assert_eq!(&StatusCode::PayloadTooLarge, response.status());
+        assert_eq!(json!({ "message": "Object is larger than 4 bytes" }), json);
+        let (response, json) = parse_response(
+            upload(&store, OID, &mut &b"hello"[..], None, Some(4)));
+        assert_eq!(&StatusCode::LengthRequired, response.status());
+        assert_eq!(json!({ "message": "Object size must be given" }), json);
+        assert!(!store.contains(OID));
+
+        let (response, _) = parse_response(
+            upload(&store, OID, &mut &b"hello"[..], Some(5), Some(5)));
+        assert_eq!(&StatusCode::Ok, response.status());
+        assert!(store.contains(OID));
+    }
+
+    #[test]
+    fn upload_truncated() {
+        let dir = TempDir::new("objects_upload_truncated");
+        let store = Store::open(dir.path()).unwrap();
+        let (response, json) = parse_response(
+            upload(&store, OID, &mut &b"hello"[..], Some(10), None));
+        assert_eq!(&StatusCode::UnprocessableEntity, response.status());
+        assert_eq!(json!({ "message":
+            "Object size does not match: Expected 10 bytes but received 5" }),
+            json);
+        assert!(!store.contains(OID));
+    }
+
+    #[test]
+    fn upload_corrupt() {
+        let dir = TempDir::new("objects_upload_corrupt");
+        let store = Store::open(dir.path()).unwrap();
+        let (response, json) = parse_response(
+            upload(&store, OID, &mut &b"jello"[..], Some(5), None));
+        assert_eq!(&StatusCode::UnprocessableEntity, response.status());
+        assert!(json["message"].as_str().unwrap()
+            .starts_with("Object hash does not match oid"));
+        assert!(!store.contains(OID));
+    }
+
+    #[test]
+    fn upload_malformed_body() {
+        let dir = TempDir::new("objects_upload_malformed");
+        let store = Store::open(dir.path()).unwrap();
+        let mut reader = ChunkedReader::new(&b"5\r\nhello0\r\n\r\n"[..]);
+        let (response, json) = parse_response(
+            upload(&store, OID, &mut reader, None, None));
+        assert_eq!(&StatusCode::BadRequest, response.status());
+        assert_eq!(json!({ "message": "HTTP parsing error: Missing chunk terminator at byte 8" }),
+                   json);
+        assert!(!store.contains(OID));
+    }
+
+    #[test]
+    fn upload_invalid_oid() {
+        let dir = TempDir::new("objects_upload_invalid");
+        let store = Store::open(dir.path()).unwrap();
+        let (response, _) = parse_response(
+            upload(&store, "../../tmp", &mut &b"hello"[..], Some(5), None));
+        assert_eq!(&StatusCode::UnprocessableEntity, response.status());
+    }
+}
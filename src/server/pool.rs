@@ -0,0 +1,209 @@
+use std::panic;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+// A fixed-size pool of worker threads. Jobs are handed directly to an idle
+// worker: if every worker is busy execute() blocks until one becomes free,
+// which in turn stops the server accepting further connections until it can
+// service them.
+pub struct ThreadPool {
+    workers: Vec<thread::JoinHandle<()>>,
+    sender: Option<mpsc::SyncSender<Job>>,
+    demand: Demand,
+}
+
+// The jobs waiting to be handed to a worker, so that jobs which would
+// otherwise sit idle for a while can finish early to make way. Each waiting
+// job can be claimed by only one of them, so no more finish than are needed.
+#[derive(Clone, Debug, Default)]
+pub struct Demand(Arc<DemandCounts>);
+
+#[derive(Debug, Default)]
+struct DemandCounts {
+    waiting: AtomicUsize,
+    // How many of the waiting jobs have been claimed.
+    claimed: AtomicUsize,
+}
+
+impl Demand {
+    // Claims a waiting job to make way for, returning false if there is none
+    // left unclaimed.
+    pub fn try_claim(&self) -> bool {
+        let counts = &self.0;
+        let mut claimed = counts.claimed.load(Ordering::SeqCst);
+        loop {
+            if claimed >= counts.waiting.load(Ordering::SeqCst) {
+                return false;
+            }
+            match counts.claimed.compare_exchange(claimed, claimed + 1,
+                                                  Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => return true,
+                Err(current) => claimed = current,
+            }
+        }
+    }
+
+    fn add(&self) {
+        self.0.waiting.fetch_add(1, Ordering::SeqCst);
+    }
+
+    // A worker has taken a waiting job. Whichever worker took it, one claim
+    // has been met.
+    fn taken(&self) {
+        let _ = self.0.claimed.fetch_update(Ordering::SeqCst, Ordering::SeqCst,
+                                            |claimed| claimed.checked_sub(1));
+        self.0.waiting.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ThreadPool {
+    pub fn new(size: usize) -> ThreadPool {
+        assert!(size > 0, "Thread pool must have at least one worker");
+        let (sender, receiver) = mpsc::sync_channel::<Job>(0);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let demand = Demand::default();
+        let workers = (0..size).map(|id| {
+            let receiver = Arc::clone(&receiver);
+            let demand = demand.clone();
+            thread::Builder::new()
+                .name(format!("worker-{}", id))
+                .spawn(move || worker(&receiver, &demand))
+                .expect("Failed to spawn worker thread")
+        }).collect();
+        ThreadPool{ workers, sender: Some(sender), demand }
+    }
+
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub fn demand(&self) -> Demand {
+        self.demand.clone()
+    }
+
+    pub fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        // The worker which takes the job counts it off again.
+        self.demand.add();
+        self.sender.as_ref().unwrap().send(Box::new(job))
+            .expect("All worker threads have exited");
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        // Closing the channel makes each worker exit once it is idle.
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn worker(receiver: &Mutex<mpsc::Receiver<Job>>, demand: &Demand) {
+    loop {
+        // The lock must be released before running the job so other workers
+        // can pick up jobs in the meantime.
+        let job = receiver.lock().unwrap().recv();
+        match job {
+            Ok(job) => {
+                demand.taken();
+                // A panic in one job must not take the worker down with it.
+                if panic::catch_unwind(panic::AssertUnwindSafe(job)).is_err() {
+                    println!("{} recovered from panic",
+                             thread::current().name().unwrap_or("worker"));
+                }
+            },
+            Err(_) => break,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn executes_all_jobs() {
+        let count = Arc::new(AtomicUsize::new(0));
+        {
+            let pool = ThreadPool::new(3);
+            assert_eq!(3, pool.size());
+            for _ in 0..20 {
+                let count = Arc::clone(&count);
+                pool.execute(move || { count.fetch_add(1, Ordering::SeqCst); });
+            }
+        }
+        // Dropping the pool waits for all jobs to finish.
+        assert_eq!(20, count.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn bounded_concurrency() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        {
+            let pool = ThreadPool::new(2);
+            for _ in 0..8 {
+                let running = Arc::clone(&running);
+                let max_running = Arc::clone(&max_running);
+                pool.execute(move || {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(10));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        }
+        assert_eq!(2, max_running.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn execute_blocks_when_busy() {
+        let pool = ThreadPool::new(1);
+        let (release, blocked) = mpsc::channel::<()>();
+        pool.execute(move || { let _ = blocked.recv(); });
+
+        // The only worker is busy, so handing over another job must wait.
+        let (done, handed_over) = mpsc::channel();
+        let pool = Arc::new(pool);
+        let submitter = {
+            let pool = Arc::clone(&pool);
+            thread::spawn(move || {
+                pool.execute(|| {});
+                done.send(()).unwrap();
+            })
+        };
+        assert!(handed_over.recv_timeout(Duration::from_millis(50)).is_err());
+        // Only one job is waiting, so it can only be claimed once.
+        assert!(pool.demand().try_claim());
+        assert!(!pool.demand().try_claim());
+        release.send(()).unwrap();
+        handed_over.recv_timeout(Duration::from_secs(5)).unwrap();
+        submitter.join().unwrap();
+        assert!(!pool.demand().try_claim());
+    }
+
+    #[test]
+    fn survives_panics() {
+        let count = Arc::new(AtomicUsize::new(0));
+        {
+            let pool = ThreadPool::new(1);
+            pool.execute(|| panic!("job failed"));
+            let count = Arc::clone(&count);
+            pool.execute(move || { count.fetch_add(1, Ordering::SeqCst); });
+        }
+        assert_eq!(1, count.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one worker")]
+    fn empty_pool() {
+        ThreadPool::new(0);
+    }
+}
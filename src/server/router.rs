@@ -0,0 +1,393 @@
+use super::http::{Method, Target, TargetForm};
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    // Matches the segment exactly.
+    Literal(String),
+    // {name}, optionally with a fixed prefix and/or suffix such as {repo}.git
+    // Matches a single, non-empty segment.
+    Capture { prefix: String, name: String, suffix: String },
+    // {name*} matches any number of whole segments, including none.
+    Rest(String),
+}
+
+#[derive(Debug, PartialEq)]
+struct Pattern {
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Pattern {
+        let invalid = || -> ! { panic!("Invalid route pattern: {}", pattern) };
+        if !pattern.starts_with('/') {
+            invalid();
+        }
+        let segments = pattern[1..].split('/').map(|segment| {
+            match (segment.find('{'), segment.find('}')) {
+                (None, None) => Segment::Literal(String::from(segment)),
+                (Some(open), Some(close)) if open < close => {
+                    let name = &segment[open+1..close];
+                    let prefix = &segment[..open];
+                    let suffix = &segment[close+1..];
+                    if name.is_empty() || suffix.contains(['{', '}']) {
+                        invalid();
+                    }
+                    match name.strip_suffix('*') {
+                        Some(name) if prefix.is_empty() && suffix.is_empty() => {
+                            Segment::Rest(String::from(name))
+                        },
+                        Some(_) => invalid(),
+                        None => Segment::Capture{ prefix: String::from(prefix),
+                                                  name: String::from(name),
+                                                  suffix: String::from(suffix) },
+                    }
+                },
+                _ => invalid(),
+            }
+        }).collect();
+        Pattern{ segments }
+    }
+
+    // Matches the decoded segments of a path. The raw segments, as sent, are
+    // used for {name*} captures: once decoded they could no longer be told
+    // apart from the separators between them.
+    fn matches(&self, decoded: &[String], raw: &[&str]) -> Option<Params> {
+        let mut params = Params{ captures: Vec::new() };
+        if match_segments(&self.segments, decoded, raw, &mut params) {
+            params.captures.reverse();
+            Some(params)
+        } else {
+            None
+        }
+    }
+}
+
+// Captures are pushed as the recursion unwinds, so in reverse order.
+fn match_segments(pattern: &[Segment], decoded: &[String], raw: &[&str],
+        params: &mut Params) -> bool {
+    let (first, pattern) = match pattern.split_first() {
+        Some(split) => split,
+        None => return decoded.is_empty(),
+    };
+    match *first {
+        Segment::Literal(ref literal) => {
+            !decoded.is_empty() && decoded[0] == *literal &&
+                match_segments(pattern, &decoded[1..], &raw[1..], params)
+        },
+        Segment::Capture{ ref prefix, ref name, ref suffix } => {
+            let segment = match decoded.first() {
+                Some(segment) => segment,
+                None => return false,
+            };
+            let matched = segment.len() > prefix.len() + suffix.len() &&
+                segment.starts_with(prefix.as_str()) &&
+                segment.ends_with(suffix.as_str());
+            if matched && match_segments(pattern, &decoded[1..], &raw[1..], params) {
+                let value = &segment[prefix.len()..segment.len() - suffix.len()];
+                params.captures.push((name.clone(), String::from(value)));
+                true
+            } else {
+                false
+            }
+        },
+        Segment::Rest(ref name) => {
+            // Take as many segments as possible, as the rest of the pattern
+            // is more likely to be found at the end of the path.
+            for count in (0..=decoded.len()).rev() {
+                if match_segments(pattern, &decoded[count..], &raw[count..], params) {
+                    let value: String = raw[..count].iter()
+                        .map(|segment| format!("/{}", segment))
+                        .collect();
+                    params.captures.push((name.clone(), value));
+                    return true;
+                }
+            }
+            false
+        },
+    }
+}
+
+// The values captured from the path by a route's pattern.
+#[derive(Debug, PartialEq)]
+pub struct Params {
+    captures: Vec<(String, String)>,
+}
+
+impl Params {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.captures.iter()
+            .find(|capture| capture.0 == name)
+            .map(|capture| capture.1.as_str())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Routed<'r, T: 'r> {
+    Found(&'r T, Params),
+    // An OPTIONS request, along with the methods allowed for the target.
+    Options(Vec<Method>),
+    // The path matched but not the method, along with the methods allowed.
+    MethodNotAllowed(Vec<Method>),
+    NotFound,
+    // No route has the method, whatever the target.
+    NotImplemented,
+}
+
+struct Route<T> {
+    pattern: Pattern,
+    methods: Vec<(Method, T)>,
+}
+
+// Dispatches requests to whatever was registered for their method and path.
+// Patterns are tried in the order they were first added, and the first one
+// matching the path decides the response: routes registered later never
+// shadow an earlier one, even for other methods.
+pub struct Router<T> {
+    routes: Vec<Route<T>>,
+}
+
+impl<T> Router<T> {
+    pub fn new() -> Router<T> {
+        Router{ routes: Vec::new() }
+    }
+
+    pub fn add(&mut self, method: Method, pattern: &str, value: T) -> &mut Self {
+        let pattern = Pattern::parse(pattern);
+        match self.routes.iter_mut().find(|route| route.pattern == pattern) {
+            Some(route) => route.methods.push((method, value)),
+            None => self.routes.push(Route{ pattern, methods: vec![(method, value)] }),
+        }
+        self
+    }
+
+    pub fn route(&self, method: &Method, target: &Target) -> Routed<'_, T> {
+        if !self.implements(method) {
+            return Routed::NotImplemented;
+        }
+        // OPTIONS * asks about the server as a whole.
+        if *target.form() == TargetForm::Asterisk {
+            return if *method == Method::OPTIONS {
+                Routed::Options(with_options(self.routes.iter()
+                    .flat_map(|route| route.methods.iter())))
+            } else {
+                Routed::NotFound
+            };
+        }
+
+        let raw: Vec<&str> = target.path().split('/').skip(1).collect();
+        for route in &self.routes {
+            let params = match route.pattern.matches(target.segments(), &raw) {
+                Some(params) => params,
+                None => continue,
+            };
+            // HEAD is answered as GET is, without the body.
+            let found = route.methods.iter().find(|&(m, _)| m == method).or_else(|| {
+                if *method != Method::HEAD {
+                    return None;
+                }
+                route.methods.iter().find(|&(m, _)| *m == Method::GET)
+            });
+            if let Some((_, value)) = found {
+                return Routed::Found(value, params);
+            }
+            let allowed = with_options(route.methods.iter());
+            return if *method == Method::OPTIONS {
+                Routed::Options(allowed)
+            } else {
+                Routed::MethodNotAllowed(allowed)
+            };
+        }
+        Routed::NotFound
+    }
+
+    fn implements(&self, method: &Method) -> bool {
+        let routed = |method: &Method| self.routes.iter()
+            .any(|route| route.methods.iter().any(|(m, _)| m == method));
+        *method == Method::OPTIONS || routed(method) ||
+            (*method == Method::HEAD && routed(&Method::GET))
+    }
+}
+
+// The distinct methods, in order, with HEAD following GET and OPTIONS (which
+// is always answered) added at the end.
+fn with_options<'r, T: 'r, I>(methods: I) -> Vec<Method>
+        where I: Iterator<Item = &'r (Method, T)> {
+    let mut allowed: Vec<Method> = Vec::new();
+    for (method, _) in methods {
+        if !allowed.contains(method) {
+            allowed.push(method.clone());
+        }
+        if *method == Method::GET && !allowed.contains(&Method::HEAD) {
+            allowed.push(Method::HEAD);
+        }
+    }
+    if !allowed.contains(&Method::OPTIONS) {
+        allowed.push(Method::OPTIONS);
+    }
+    allowed
+}
+
+// Formats methods for an Allow field.
+pub fn allow_value(methods: &[Method]) -> String {
+    methods.iter()
+        .map(|method| format!("{}", method))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(target: &str) -> Target {
+        Target::parse(target).unwrap()
+    }
+
+    fn router() -> Router<&'static str> {
+        let mut router = Router::new();
+        router.add(Method::GET, "/{owner}/{repo}.git/info/lfs/objects/{oid}", "download")
+              .add(Method::PUT, "/{owner}/{repo}.git/info/lfs/objects/{oid}", "upload")
+              .add(Method::POST, "/{repo*}/info/lfs/objects/batch", "batch")
+              .add(Method::GET, "/{repo*}/info/lfs/locks", "locks")
+              .add(Method::GET, "/", "index");
+        router
+    }
+
+    fn found<'r>(routed: Routed<'r, &'static str>) -> (&'static str, Params) {
+        match routed {
+            Routed::Found(value, params) => (*value, params),
+            other => panic!("Route not found: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn captures() {
+        let router = router();
+        let (value, params) = found(router.route(&Method::PUT,
+                &target("/alice/my%20repo.git/info/lfs/objects/abc")));
+        assert_eq!("upload", value);
+        assert_eq!(Some("alice"), params.get("owner"));
+        assert_eq!(Some("my repo"), params.get("repo"));
+        assert_eq!(Some("abc"), params.get("oid"));
+        assert_eq!(None, params.get("other"));
+    }
+
+    #[test]
+    fn rest_captures() {
+        let router = router();
+        let (value, params) = found(router.route(&Method::POST,
+                &target("/a/b%20c/info/lfs/objects/batch")));
+        assert_eq!("batch", value);
+        assert_eq!(Some("/a/b%20c"), params.get("repo"));
+
+        let (_, params) = found(router.route(&Method::POST,
+                &target("/info/lfs/objects/batch")));
+        assert_eq!(Some(""), params.get("repo"));
+
+        // The longest match is taken.
+        let (_, params) = found(router.route(&Method::GET,
+                &target("/x/info/lfs/y/info/lfs/locks")));
+        assert_eq!(Some("/x/info/lfs/y"), params.get("repo"));
+    }
+
+    #[test]
+    fn literal_only() {
+        let (value, _) = found(router().route(&Method::GET, &target("/")));
+        assert_eq!("index", value);
+    }
+
+    #[test]
+    fn not_found() {
+        let router = router();
+        for path in &["/alice/repo/info/lfs/objects/abc",
+                      "/alice/.git/info/lfs/objects/abc",
+                      "/alice/repo.git/info/lfs/objects/",
+                      "/alice/repo.git/info/lfs/objects/abc/def",
+                      "/info/lfs/locks/verify",
+                      "/foo"] {
+            assert_eq!(Routed::NotFound, router.route(&Method::GET, &target(path)));
+        }
+        assert_eq!(Routed::NotFound, router.route(&Method::GET, &target("*")));
+    }
+
+    #[test]
+    fn method_not_allowed() {
+        let router = router();
+        assert_eq!(Routed::MethodNotAllowed(vec![Method::GET, Method::HEAD, Method::PUT,
+                                                 Method::OPTIONS]),
+                   router.route(&Method::POST, &target("/a/b.git/info/lfs/objects/c")));
+        assert_eq!(Routed::MethodNotAllowed(vec![Method::POST, Method::OPTIONS]),
+                   router.route(&Method::GET, &target("/info/lfs/objects/batch")));
+    }
+
+    #[test]
+    fn first_pattern_wins() {
+        let mut router = Router::new();
+        router.add(Method::POST, "/objects/batch", "batch")
+              .add(Method::GET, "/objects/{oid}", "download");
+        assert_eq!(Routed::MethodNotAllowed(vec![Method::POST, Method::OPTIONS]),
+                   router.route(&Method::GET, &target("/objects/batch")));
+        let (value, _) = found(router.route(&Method::GET, &target("/objects/abc")));
+        assert_eq!("download", value);
+    }
+
+    #[test]
+    fn not_implemented() {
+        let router = router();
+        for method in &[Method::DELETE, Method::PROPFIND, Method::from("BREW").unwrap()] {
+            assert_eq!(Routed::NotImplemented,
+                       router.route(method, &target("/a/b.git/info/lfs/objects/c")));
+            assert_eq!(Routed::NotImplemented, router.route(method, &target("/foo")));
+        }
+        let mut router = router;
+        router.add(Method::DELETE, "/{repo*}/info/lfs/locks/{id}", "unlock");
+        assert_eq!(Routed::MethodNotAllowed(vec![Method::GET, Method::HEAD, Method::PUT,
+                                                 Method::OPTIONS]),
+                   router.route(&Method::DELETE, &target("/a/b.git/info/lfs/objects/c")));
+        assert_eq!(Routed::Options(vec![Method::GET, Method::HEAD, Method::PUT,
+                                        Method::OPTIONS]),
+                   router.route(&Method::OPTIONS, &target("/a/b.git/info/lfs/objects/c")));
+    }
+
+    #[test]
+    fn head_routed_as_get() {
+        let router = router();
+        let (value, params) = found(router.route(&Method::HEAD,
+                &target("/a/b.git/info/lfs/objects/c")));
+        assert_eq!("download", value);
+        assert_eq!(Some("c"), params.get("oid"));
+        assert_eq!(Routed::MethodNotAllowed(vec![Method::POST, Method::OPTIONS]),
+                   router.route(&Method::HEAD, &target("/info/lfs/objects/batch")));
+    }
+
+    #[test]
+    fn options() {
+        let router = router();
+        assert_eq!(Routed::Options(vec![Method::GET, Method::HEAD, Method::PUT,
+                                        Method::OPTIONS]),
+                   router.route(&Method::OPTIONS, &target("/a/b.git/info/lfs/objects/c")));
+        assert_eq!(Routed::Options(vec![Method::GET, Method::HEAD, Method::PUT, Method::POST,
+                                        Method::OPTIONS]),
+                   router.route(&Method::OPTIONS, &target("*")));
+        assert_eq!(Routed::NotFound, router.route(&Method::OPTIONS, &target("/foo")));
+
+        // Explicit OPTIONS routes take precedence.
+        let mut router = Router::new();
+        router.add(Method::OPTIONS, "/foo", "options");
+        let (value, _) = found(router.route(&Method::OPTIONS, &target("/foo")));
+        assert_eq!("options", value);
+    }
+
+    #[test]
+    fn allow() {
+        assert_eq!("GET, PUT, OPTIONS",
+                   allow_value(&[Method::GET, Method::PUT, Method::OPTIONS]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid route pattern")]
+    fn invalid_pattern() {
+        Router::new().add(Method::GET, "/{a*}.git", ());
+    }
+}
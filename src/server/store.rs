@@ -0,0 +1,246 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+use sha2::{Digest, Sha256};
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Computes the SHA-256 hash of everything read through it.
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> HashingReader<R> {
+        HashingReader{ inner, hasher: Sha256::new() }
+    }
+
+    fn oid(self) -> String {
+        format!("{:x}", self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.hasher.update(&buf[..len]);
+        Ok(len)
+    }
+}
+
+// A content-addressed object store. Objects are held under their oid, split
+// into two levels of subdirectories (as git-lfs does in .git/lfs/objects) to
+// keep directory sizes manageable:
+//   <root>/objects/2c/f2/2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824
+// Objects are first written to <root>/tmp and verified against their oid
+// before being moved into place, so a partially written or corrupt object is
+// never visible in the store.
+#[derive(Debug)]
+pub struct Store {
+    root: PathBuf,
+}
+
+impl Store {
+    pub fn open<P: AsRef<Path>>(root: P) -> io::Result<Store> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(root.join("objects"))?;
+        fs::create_dir_all(root.join("tmp"))?;
+        Ok(Store{ root })
+    }
+
+    fn object_path(&self, oid: &str) -> PathBuf {
+        self.root.join("objects").join(&oid[0..2]).join(&oid[2..4]).join(oid)
+    }
+
+    fn temp_path(&self, oid: &str) -> PathBuf {
+        let count = TEMP_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let name = format!("{}.{}.{}", oid, process::id(), count);
+        self.root.join("tmp").join(name)
+    }
+
+    pub fn contains(&self, oid: &str) -> bool {
+        self.object_path(oid).is_file()
+    }
+
+    pub fn size(&self, oid: &str) -> io::Result<u64> {
+        Ok(fs::metadata(self.object_path(oid))?.len())
+    }
+
+    // When the object was added to the store.
+    pub fn modified(&self, oid: &str) -> io::Result<SystemTime> {
+        fs::metadata(self.object_path(oid))?.modified()
+    }
+
+    pub fn open_object(&self, oid: &str) -> io::Result<File> {
+        File::open(self.object_path(oid))
+    }
+
+    // Opens length bytes of an object's content from the given offset.
+    // Objects are only ever held uncompressed, as uploads are decoded before
+    // being written, so the offset is simply one into the file.
+    pub fn open_range(&self, oid: &str, offset: u64, length: u64)
+            -> io::Result<io::Take<File>> {
+        let mut file = self.open_object(oid)?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(file.take(length))
+    }
+
+    // Writes exactly size bytes from the reader as the given object, or
+    // everything up to the end of the reader if the size isn't known. The
+    // object is only added to the store if all of them could be read and
+    // their SHA-256 hash matches the oid. Otherwise an UnexpectedEof or
+    // InvalidData error is returned respectively.
+    pub fn write_object<R: Read>(&self, oid: &str, reader: &mut R,
+            size: Option<u64>) -> io::Result<()> {
+        let temp_path = self.temp_path(oid);
+        let result = File::create(&temp_path)
+            .and_then(|mut file| {
+                let limit = size.unwrap_or(u64::MAX);
+                let mut hashing_reader = HashingReader::new(reader.take(limit));
+                let written = io::copy(&mut hashing_reader, &mut file)?;
+                if let Some(size) = size.filter(|&size| size != written) {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                            format!("Expected {} bytes but received {}",
+                                    size, written)));
+                }
+                let actual_oid = hashing_reader.oid();
+                if actual_oid != oid {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData,
+                            format!("Object hash is {}", actual_oid)));
+                }
+                file.sync_all()
+            })
+            .and_then(|_| {
+                let path = self.object_path(oid);
+                fs::create_dir_all(path.parent().unwrap())?;
+                fs::rename(&temp_path, &path)
+            });
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::*;
+
+    #[test]
+    fn open_creates_layout() {
+        let dir = TempDir::new("store_open");
+        let root = dir.path().join("store");
+        Store::open(&root).unwrap();
+        assert!(root.join("objects").is_dir());
+        assert!(root.join("tmp").is_dir());
+        // Opening an existing store is fine.
+        Store::open(&root).unwrap();
+    }
+
+    #[test]
+    fn write_and_read() {
+        let dir = TempDir::new("store_write");
+        let store = Store::open(dir.path()).unwrap();
+        assert!(!store.contains(OID));
+
+        store.write_object(OID, &mut &b"hello"[..], Some(5)).unwrap();
+        assert!(store.contains(OID));
+        assert_eq!(5, store.size(OID).unwrap());
+        assert!(store.modified(OID).unwrap() <= SystemTime::now());
+        assert!(dir.path().join("objects/2c/f2").join(OID).is_file());
+
+        let mut content = Vec::new();
+        store.open_object(OID).unwrap().read_to_end(&mut content).unwrap();
+        assert_eq!(b"hello", content.as_slice());
+        assert_eq!(0, fs::read_dir(dir.path().join("tmp")).unwrap().count());
+    }
+
+    #[test]
+    fn overwrite() {
+        let dir = TempDir::new("store_overwrite");
+        let store = Store::open(dir.path()).unwrap();
+        store.write_object(OID, &mut &b"hello"[..], Some(5)).unwrap();
+        store.write_object(OID, &mut &b"hello"[..], Some(5)).unwrap();
+        assert_eq!(5, store.size(OID).unwrap());
+    }
+
+    #[test]
+    fn write_reads_exact_size() {
+        let dir = TempDir::new("store_exact");
+        let store = Store::open(dir.path()).unwrap();
+        let mut reader = &b"hello world"[..];
+        store.write_object(OID, &mut reader, Some(5)).unwrap();
+        assert_eq!(5, store.size(OID).unwrap());
+        assert_eq!(b" world", reader);
+    }
+
+    #[test]
+    fn write_unknown_size() {
+        let dir = TempDir::new("store_unknown_size");
+        let store = Store::open(dir.path()).unwrap();
+        store.write_object(OID, &mut &b"hello"[..], None).unwrap();
+        assert_eq!(5, store.size(OID).unwrap());
+    }
+
+    #[test]
+    fn write_hash_mismatch() {
+        let dir = TempDir::new("store_hash_mismatch");
+        let store = Store::open(dir.path()).unwrap();
+        let error = store.write_object(OID, &mut &b"hellO"[..], Some(5)).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        assert!(!store.contains(OID));
+        assert_eq!(0, fs::read_dir(dir.path().join("tmp")).unwrap().count());
+    }
+
+    #[test]
+    fn hashing_reader() {
+        let mut reader = HashingReader::new(&b"hello"[..]);
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).unwrap();
+        assert_eq!(b"hello", content.as_slice());
+        assert_eq!(OID, reader.oid());
+
+        let reader = HashingReader::new(&b""[..]);
+        assert_eq!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                   reader.oid());
+    }
+
+    #[test]
+    fn write_truncated() {
+        let dir = TempDir::new("store_truncated");
+        let store = Store::open(dir.path()).unwrap();
+        let error = store.write_object(OID, &mut &b"hello"[..], Some(6)).unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, error.kind());
+        assert!(!store.contains(OID));
+        assert_eq!(0, fs::read_dir(dir.path().join("tmp")).unwrap().count());
+    }
+
+    #[test]
+    fn open_range() {
+        let dir = TempDir::new("store_range");
+        let store = Store::open(dir.path()).unwrap();
+        store.write_object(OID, &mut &b"hello"[..], Some(5)).unwrap();
+        let mut content = String::new();
+        store.open_range(OID, 1, 3).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!("ell", content);
+        assert!(store.open_range(&OID.replace('2', "3"), 0, 1).is_err());
+    }
+
+    #[test]
+    fn missing_object() {
+        let dir = TempDir::new("store_missing");
+        let store = Store::open(dir.path()).unwrap();
+        assert!(!store.contains(OID));
+        assert!(store.size(OID).is_err());
+        assert!(store.modified(OID).is_err());
+        assert!(store.open_object(OID).is_err());
+    }
+}